@@ -0,0 +1,141 @@
+use crate::wikimedia_types::Search;
+use once_cell::sync::Lazy;
+use regex::Regex;
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+//Snippets this similar most likely describe the same article
+const DUPLICATE_THRESHOLD: f64 = 0.8;
+
+pub fn jaccard_similarity(a: &HashSet<&str>, b: &HashSet<&str>) -> f64 {
+    let union = a.union(b).count();
+    if union == 0 {
+        return 0.0;
+    }
+
+    a.intersection(b).count() as f64 / union as f64
+}
+
+//Snippets contain searchmatch spans, only the words are compared
+static TAGS: Lazy<Regex> = Lazy::new(|| Regex::new(r"<[^>]*>").unwrap());
+
+//Snippets mark the matched words with <span class="searchmatch">
+pub fn strip_tags<'a>(snippet: &'a str, replacement: &str) -> Cow<'a, str> {
+    TAGS.replace_all(snippet, replacement)
+}
+
+fn snippet_words(snippet: &str) -> String {
+    strip_tags(snippet, " ").to_lowercase()
+}
+
+//Only neighbours are compared, the API ranks similar articles next to each other.
+//Returns the pageids of all results that have a near duplicate
+pub fn find_adjacent_duplicates(results: &[Search]) -> Vec<i64> {
+    let words: Vec<String> = results.iter().map(|r| snippet_words(&r.snippet)).collect();
+    let sets: Vec<HashSet<&str>> = words.iter().map(|w| w.split_whitespace().collect()).collect();
+
+    let mut duplicates = Vec::new();
+    for i in 1..results.len() {
+        if jaccard_similarity(&sets[i - 1], &sets[i]) > DUPLICATE_THRESHOLD {
+            for pageid in [results[i - 1].pageid, results[i].pageid] {
+                if !duplicates.contains(&pageid) {
+                    duplicates.push(pageid);
+                }
+            }
+        }
+    }
+
+    duplicates
+}
+
+//Quick filter of the loaded results by the page size in bytes, Ctrl+F cycles through them
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SizeFilter {
+    All,
+    Over10Kb,
+    Over50Kb,
+    Over100Kb,
+    Stubs,
+}
+
+impl SizeFilter {
+    pub fn next(self) -> SizeFilter {
+        match self {
+            SizeFilter::All => SizeFilter::Over10Kb,
+            SizeFilter::Over10Kb => SizeFilter::Over50Kb,
+            SizeFilter::Over50Kb => SizeFilter::Over100Kb,
+            SizeFilter::Over100Kb => SizeFilter::Stubs,
+            SizeFilter::Stubs => SizeFilter::All,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            SizeFilter::All => "All",
+            SizeFilter::Over10Kb => ">10KB",
+            SizeFilter::Over50Kb => ">50KB",
+            SizeFilter::Over100Kb => ">100KB",
+            SizeFilter::Stubs => "Stubs (<2KB)",
+        }
+    }
+
+    pub fn matches(self, size: i64) -> bool {
+        match self {
+            SizeFilter::All => true,
+            SizeFilter::Over10Kb => size > 10 * 1024,
+            SizeFilter::Over50Kb => size > 50 * 1024,
+            SizeFilter::Over100Kb => size > 100 * 1024,
+            SizeFilter::Stubs => size < 2 * 1024,
+        }
+    }
+}
+
+//Positions of the results the filter lets through, None if it shows all of them
+pub fn filtered_indices(results: &[Search], filter: SizeFilter) -> Option<Vec<usize>> {
+    if filter == SizeFilter::All {
+        return None;
+    }
+
+    Some(results.iter().enumerate().filter(|(_, r)| filter.matches(r.size)).map(|(i, _)| i).collect())
+}
+
+//Next or previous result the filter lets through, wraps around. The first one if the selection is hidden
+pub fn step_filtered(indices: &[usize], selected: Option<usize>, forward: bool) -> Option<usize> {
+    let position = selected.and_then(|s| indices.iter().position(|&i| i == s));
+    let next = match (position, forward) {
+        (None, _) => 0,
+        (Some(p), true) => (p + 1) % indices.len(),
+        (Some(p), false) => (p + indices.len() - 1) % indices.len(),
+    };
+    indices.get(next).copied()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn size_filters_cycle_and_select_by_size() {
+        let results: Vec<Search> = [500, 20_000, 60_000, 2_048]
+            .iter()
+            .map(|&size| Search {
+                ns: 0,
+                title: size.to_string(),
+                pageid: size,
+                size,
+                wordcount: 0,
+                snippet: String::new(),
+                timestamp: String::new(),
+            })
+            .collect();
+
+        assert_eq!(filtered_indices(&results, SizeFilter::All), None);
+        assert_eq!(filtered_indices(&results, SizeFilter::Over10Kb), Some(vec![1, 2]));
+        assert_eq!(filtered_indices(&results, SizeFilter::Over50Kb), Some(vec![2]));
+        assert_eq!(filtered_indices(&results, SizeFilter::Stubs), Some(vec![0]));
+        assert_eq!(SizeFilter::Stubs.next(), SizeFilter::All);
+        assert_eq!(step_filtered(&[1, 2], Some(2), true), Some(1));
+        assert_eq!(step_filtered(&[1, 2], Some(0), false), Some(1));
+        assert_eq!(step_filtered(&[], Some(0), true), None);
+    }
+}
@@ -0,0 +1,64 @@
+use crate::storage;
+use crate::Error;
+use std::fs;
+
+//Everything above this line in the editor is kept as the article's notes
+const NOTES_MARKER: &str = "----- Write your notes above this line, the article below is not saved -----";
+
+fn annotation_file(pageid: i64) -> String {
+    format!("annotations/{}.txt", pageid)
+}
+
+pub fn load_annotation(pageid: i64) -> Option<String> {
+    let path = storage::data_path(&annotation_file(pageid))?;
+    fs::read_to_string(path).ok().filter(|notes| !notes.trim().is_empty())
+}
+
+//Empty notes remove the file
+pub fn save_annotation(pageid: i64, notes: Option<&str>) -> Result<(), Error> {
+    let path = match storage::data_path(&annotation_file(pageid)) {
+        Some(path) => path,
+        None => return Ok(()),
+    };
+
+    match notes {
+        Some(notes) => {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, notes)?;
+        }
+        None if path.exists() => fs::remove_file(path)?,
+        None => {}
+    }
+
+    Ok(())
+}
+
+//What the editor is opened with, the notes so far followed by the article for reference
+pub fn editor_text(notes: Option<&str>, content: &str) -> String {
+    format!("{}\n{}\n{}", notes.unwrap_or_default(), NOTES_MARKER, content)
+}
+
+//The notes from the edited text, all of it if the marker was deleted
+pub fn notes_of(edited: &str) -> Option<String> {
+    let notes = match edited.find(NOTES_MARKER) {
+        Some(end) => &edited[..end],
+        None => edited,
+    };
+
+    Some(notes.trim().to_string()).filter(|notes| !notes.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_the_notes_above_the_marker_are_kept() {
+        let edited = editor_text(Some("Check the population figure"), "Vienna is the capital of Austria.");
+
+        assert_eq!(notes_of(&edited).as_deref(), Some("Check the population figure"));
+        assert_eq!(notes_of(&editor_text(None, "Vienna")), None);
+    }
+}
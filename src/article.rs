@@ -0,0 +1,55 @@
+use crate::gallery::GalleryImage;
+use crate::infobox::InfoboxRow;
+use crate::wikimedia_types::{Coordinate, Search};
+
+//"What links here", 'm' loads the next batch while continuation is set and no batch is loading
+pub struct Backlinks {
+    pub links: Vec<String>,
+    pub continuation: Option<String>,
+    pub loading: bool,
+}
+
+//Everything known about the open article. The text and infobox come with the first fetch,
+//the other parts are fetched when a view first needs them and kept until the article changes.
+//Sections aren't stored, the sections module finds the headings in the content
+pub struct WikipediaArticle {
+    pub search: Search,
+    //Cleaned text as shown in the content pane
+    pub content: String,
+    //Width the text was converted at, it is converted again when the pane width changes
+    pub width: u16,
+    //Empty if the article has none or the infobox panel is off
+    pub infobox: Vec<InfoboxRow>,
+    //Unprocessed parse HTML for the debug view
+    pub raw_html: Option<String>,
+    //Outer None until the map was opened, inner None for articles without a location
+    pub coordinates: Option<Option<Coordinate>>,
+    //Files used in the article with their captions, fetched when the gallery is opened
+    pub images: Option<Vec<GalleryImage>>,
+    //Linked articles, body links first, fetched when the link list is opened
+    pub links: Option<Vec<String>>,
+    //Articles linking here, fetched in batches when the list is opened
+    pub backlinks: Option<Backlinks>,
+    //Categories the article is in, fetched when the category browser is opened
+    pub categories: Option<Vec<String>>,
+    //Notes written with Ctrl+X, shown above the text
+    pub annotation: Option<String>,
+}
+
+impl WikipediaArticle {
+    pub fn new(search: Search, content: String, width: u16, infobox: Vec<InfoboxRow>) -> WikipediaArticle {
+        WikipediaArticle {
+            search,
+            content,
+            width,
+            infobox,
+            raw_html: None,
+            coordinates: None,
+            images: None,
+            links: None,
+            backlinks: None,
+            categories: None,
+            annotation: None,
+        }
+    }
+}
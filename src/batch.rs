@@ -0,0 +1,86 @@
+use crate::client::WikipediaClient;
+use crate::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+const BATCH_TEXT_WIDTH: u16 = 100;
+//Stay well below the API rate limits
+const BATCH_DELAY: Duration = Duration::from_millis(500);
+
+pub struct BatchSummary {
+    //Query and the file its top result was written to
+    pub written: Vec<(String, PathBuf)>,
+    pub no_results: Vec<String>,
+    //Query and the error
+    pub failed: Vec<(String, String)>,
+}
+
+fn file_name(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+//Writes the cleaned text of the top result of every query into out_dir,
+//one query per line, empty lines and lines starting with # are skipped
+pub async fn run_batch(client: &WikipediaClient, queries_path: &Path, out_dir: &Path) -> Result<BatchSummary, Error> {
+    let queries = fs::read_to_string(queries_path)?;
+    fs::create_dir_all(out_dir)?;
+
+    let mut summary = BatchSummary { written: Vec::new(), no_results: Vec::new(), failed: Vec::new() };
+
+    for query in queries.lines().map(str::trim).filter(|q| !q.is_empty() && !q.starts_with('#')) {
+        println!("Fetching {}", query);
+
+        let top_result = match client.search(query).await {
+            Ok(res) => res.query.search.into_iter().next(),
+            Err(e) => {
+                summary.failed.push((query.to_string(), e.to_string()));
+                continue;
+            }
+        };
+
+        match top_result {
+            Some(result) => {
+                tokio::time::sleep(BATCH_DELAY).await;
+
+                match client.fetch_html(result.pageid as usize, BATCH_TEXT_WIDTH).await {
+                    Ok(content) => {
+                        let path = out_dir.join(format!("{}.txt", file_name(&result.title)));
+                        fs::write(&path, format!("{}\n\n{}", result.title, content))?;
+                        summary.written.push((query.to_string(), path));
+                    }
+                    Err(e) => summary.failed.push((query.to_string(), e.to_string())),
+                }
+            }
+            None => summary.no_results.push(query.to_string()),
+        }
+
+        tokio::time::sleep(BATCH_DELAY).await;
+    }
+
+    Ok(summary)
+}
+
+pub fn summary_report(summary: &BatchSummary) -> String {
+    let mut report = format!(
+        "{} written, {} without results, {} failed\n",
+        summary.written.len(),
+        summary.no_results.len(),
+        summary.failed.len()
+    );
+
+    for (query, path) in &summary.written {
+        report.push_str(&format!("  ok         {} -> {}\n", query, path.display()));
+    }
+    for query in &summary.no_results {
+        report.push_str(&format!("  no result  {}\n", query));
+    }
+    for (query, error) in &summary.failed {
+        report.push_str(&format!("  failed     {}: {}\n", query, error));
+    }
+
+    report
+}
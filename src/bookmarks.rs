@@ -0,0 +1,89 @@
+use crate::client::WikipediaClient;
+use crate::markdown;
+use crate::storage;
+use crate::wikimedia_types::Search;
+use crate::Error;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const BOOKMARKS_FILE: &str = "bookmarks.json";
+const EXPORT_TEXT_WIDTH: u16 = 100;
+//Pause between two article requests of a batch export
+const EXPORT_DELAY: Duration = Duration::from_millis(500);
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct Bookmark {
+    pub search: Search,
+    pub added_at: DateTime<Local>,
+}
+
+pub fn load_bookmarks() -> Result<Vec<Bookmark>, Error> {
+    storage::load_json(BOOKMARKS_FILE)
+}
+
+pub fn save_bookmarks(bookmarks: &[Bookmark]) -> Result<(), Error> {
+    storage::save_json(BOOKMARKS_FILE, bookmarks)
+}
+
+//Returns true if the article is bookmarked afterwards
+pub fn toggle_bookmark(bookmarks: &mut Vec<Bookmark>, search: &Search) -> bool {
+    let before = bookmarks.len();
+    bookmarks.retain(|b| b.search.pageid != search.pageid);
+
+    if bookmarks.len() == before {
+        bookmarks.push(Bookmark {
+            search: search.clone(),
+            added_at: Local::now(),
+        });
+        true
+    } else {
+        false
+    }
+}
+
+fn anchor(title: &str) -> String {
+    title
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == ' ' || *c == '-')
+        .map(|c| if c == ' ' { '-' } else { c })
+        .collect()
+}
+
+//Fetches every bookmarked article one after another into a single Markdown file,
+//returns the file and the titles that couldn't be fetched
+pub async fn export_bookmarks<F: Fn(String)>(client: &WikipediaClient, bookmarks: &[Bookmark], progress: F) -> Result<(PathBuf, Vec<String>), Error> {
+    let mut articles = Vec::new();
+    let mut failed = Vec::new();
+
+    for (i, bookmark) in bookmarks.iter().enumerate() {
+        progress(format!("Exporting bookmark {}/{}: {}", i + 1, bookmarks.len(), bookmark.search.title));
+
+        match client.fetch_html(bookmark.search.pageid as usize, EXPORT_TEXT_WIDTH).await {
+            Ok(content) => articles.push((bookmark.search.title.clone(), content)),
+            Err(_) => failed.push(bookmark.search.title.clone()),
+        }
+
+        tokio::time::sleep(EXPORT_DELAY).await;
+    }
+
+    let mut export = String::from("# Bookmarks\n\n## Contents\n\n");
+    for (title, _) in &articles {
+        export.push_str(&format!("- [{}](#{})\n", title, anchor(title)));
+    }
+    if !failed.is_empty() {
+        export.push_str(&format!("\nCould not be fetched: {}\n", failed.join(", ")));
+    }
+    for (title, content) in &articles {
+        export.push_str(&format!("\n{}", markdown::article_to_markdown(title, content)));
+    }
+
+    let dir = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    let path = dir.join("tpedia_bookmarks.md");
+    fs::write(&path, export)?;
+
+    Ok((path, failed))
+}
@@ -0,0 +1,187 @@
+use crate::log::write_log;
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io;
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+//File name -> unix time the article was fetched
+const INDEX_FILE: &str = "index.json";
+
+//Raw article HTML in ~/.cache/tpedia/articles, a file's mtime is its last access
+#[derive(Clone)]
+pub struct ArticleCache {
+    dir: PathBuf,
+    //Language and wiki of the articles, pageids of different wikis overlap
+    prefix: String,
+    max_bytes: u64,
+    //Older articles are fetched again, None keeps them until they are evicted
+    ttl: Option<Duration>,
+}
+
+//FNV-1a, the file names have to stay the same between runs and Rust versions
+fn wiki_id(api_url: &str) -> u32 {
+    api_url.bytes().fold(0x811c9dc5, |hash, byte| (hash ^ u32::from(byte)).wrapping_mul(0x01000193))
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0)
+}
+
+impl ArticleCache {
+    //A max size of 0 disables the cache, a ttl of 0 never expires articles
+    pub fn new(language: &str, api_url: &str, max_mb: u64, ttl_hours: u64) -> Option<ArticleCache> {
+        if max_mb == 0 {
+            return None;
+        }
+
+        dirs::cache_dir().map(|dir| ArticleCache {
+            dir: dir.join("tpedia").join("articles"),
+            prefix: format!("{}_{:08x}", language, wiki_id(api_url)),
+            max_bytes: max_mb * 1024 * 1024,
+            ttl: if ttl_hours == 0 { None } else { Some(Duration::from_secs(ttl_hours * 60 * 60)) },
+        })
+    }
+
+    fn file_name(&self, pageid: usize) -> String {
+        format!("{}_{}.html", self.prefix, pageid)
+    }
+
+    fn read_index(&self) -> HashMap<String, u64> {
+        fs::read_to_string(self.dir.join(INDEX_FILE))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    fn write_index(&self, index: &HashMap<String, u64>) {
+        let result = serde_json::to_string(index)
+            .map_err(io::Error::from)
+            .and_then(|content| fs::write(self.dir.join(INDEX_FILE), content));
+
+        if let Err(e) = result {
+            write_log(&format!("could not write the article cache index: {}", e));
+        }
+    }
+
+    //Stale articles are treated as missing, so they are fetched again
+    pub fn load(&self, pageid: usize) -> Option<String> {
+        let file_name = self.file_name(pageid);
+
+        if let Some(ttl) = self.ttl {
+            let fetched_at = self.read_index().get(&file_name).copied().unwrap_or(0);
+            if unix_now().saturating_sub(fetched_at) > ttl.as_secs() {
+                return None;
+            }
+        }
+
+        let path = self.dir.join(&file_name);
+        let html = fs::read_to_string(&path).ok()?;
+
+        //Reading doesn't update the mtime, so the access is recorded by hand
+        if let Ok(file) = File::options().write(true).open(&path) {
+            let _ = file.set_modified(SystemTime::now());
+        }
+
+        Some(html)
+    }
+
+    pub fn store(&self, pageid: usize, html: &str) {
+        let file_name = self.file_name(pageid);
+        let result = fs::create_dir_all(&self.dir).and_then(|_| fs::write(self.dir.join(&file_name), html));
+
+        match result {
+            Ok(()) => {
+                let mut index = self.read_index();
+                index.insert(file_name, unix_now());
+                self.write_index(&index);
+                self.evict();
+            }
+            Err(e) => write_log(&format!("could not cache article {}: {}", pageid, e)),
+        }
+    }
+
+    pub fn remove(&self, pageid: usize) {
+        let path = self.dir.join(self.file_name(pageid));
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != io::ErrorKind::NotFound {
+                write_log(&format!("could not remove {}: {}", path.display(), e));
+            }
+        }
+    }
+
+    //Removes the least recently accessed articles until the cache fits into max_bytes
+    pub fn evict(&self) {
+        let mut entries = match self.entries() {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return,
+            Err(e) => {
+                write_log(&format!("could not read the article cache: {}", e));
+                return;
+            }
+        };
+
+        let mut total: u64 = entries.iter().map(|(_, size, _)| size).sum();
+        if total <= self.max_bytes {
+            return;
+        }
+
+        let mut index = self.read_index();
+        entries.sort_by_key(|(_, _, accessed)| *accessed);
+        for (path, size, _) in entries {
+            if total <= self.max_bytes {
+                break;
+            }
+
+            match fs::remove_file(&path) {
+                Ok(()) => {
+                    total -= size;
+                    if let Some(name) = path.file_name() {
+                        index.remove(name.to_string_lossy().as_ref());
+                    }
+                    write_log(&format!("evicted {} ({} bytes) from the article cache", path.display(), size));
+                }
+                Err(e) => write_log(&format!("could not evict {}: {}", path.display(), e)),
+            }
+        }
+        self.write_index(&index);
+    }
+
+    fn entries(&self) -> io::Result<Vec<(PathBuf, u64, SystemTime)>> {
+        let mut entries = Vec::new();
+
+        for entry in fs::read_dir(&self.dir)? {
+            let entry = entry?;
+            let metadata = entry.metadata()?;
+            if metadata.is_file() && entry.file_name() != INDEX_FILE {
+                entries.push((entry.path(), metadata.len(), metadata.modified()?));
+            }
+        }
+
+        Ok(entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(api_url: &str) -> ArticleCache {
+        ArticleCache {
+            dir: PathBuf::new(),
+            prefix: format!("en_{:08x}", wiki_id(api_url)),
+            max_bytes: 0,
+            ttl: None,
+        }
+    }
+
+    #[test]
+    fn wikis_get_their_own_files() {
+        let wikipedia = cache("https://en.wikipedia.org/w/api.php");
+        let own_wiki = cache("https://wiki.example.org/w/api.php");
+
+        assert_ne!(wikipedia.file_name(123), own_wiki.file_name(123));
+        assert_eq!(wikipedia.file_name(123), cache("https://en.wikipedia.org/w/api.php").file_name(123));
+        assert!(wikipedia.file_name(123).ends_with("_123.html"));
+    }
+}
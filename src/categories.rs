@@ -0,0 +1,136 @@
+use crate::wikimedia_types::Search;
+
+//A category of the browser opened with 'C'. Subcategories and member articles are fetched
+//when the category is first expanded
+pub struct CategoryNode {
+    //With the namespace, e.g. "Category:Mathematicians"
+    pub title: String,
+    pub children: Vec<CategoryNode>,
+    pub members: Vec<Search>,
+    pub expanded: bool,
+    pub loaded: bool,
+}
+
+impl CategoryNode {
+    pub fn new(title: String) -> CategoryNode {
+        CategoryNode {
+            title,
+            children: Vec::new(),
+            members: Vec::new(),
+            expanded: false,
+            loaded: false,
+        }
+    }
+}
+
+//A line of the browser, path holds the child indices from the top level category down
+#[derive(Debug, PartialEq)]
+pub enum TreeRow {
+    Category(Vec<usize>),
+    Member(Vec<usize>, usize),
+}
+
+impl TreeRow {
+    //Path of the category the row belongs to, for a category its parent
+    pub fn parent(&self) -> Option<&[usize]> {
+        match self {
+            TreeRow::Category(path) if path.len() > 1 => Some(&path[..path.len() - 1]),
+            TreeRow::Category(_) => None,
+            TreeRow::Member(path, _) => Some(path),
+        }
+    }
+}
+
+//Every visible line in display order, the members of a category after its subcategories
+pub fn rows(nodes: &[CategoryNode]) -> Vec<TreeRow> {
+    let mut rows = Vec::new();
+    push_rows(nodes, &mut Vec::new(), &mut rows);
+    rows
+}
+
+fn push_rows(nodes: &[CategoryNode], path: &mut Vec<usize>, rows: &mut Vec<TreeRow>) {
+    for (i, node) in nodes.iter().enumerate() {
+        path.push(i);
+        rows.push(TreeRow::Category(path.clone()));
+
+        if node.expanded {
+            push_rows(&node.children, path, rows);
+            rows.extend((0..node.members.len()).map(|m| TreeRow::Member(path.clone(), m)));
+        }
+        path.pop();
+    }
+}
+
+pub fn node<'a>(nodes: &'a [CategoryNode], path: &[usize]) -> Option<&'a CategoryNode> {
+    let (first, rest) = path.split_first()?;
+    let node = nodes.get(*first)?;
+    if rest.is_empty() { Some(node) } else { self::node(&node.children, rest) }
+}
+
+pub fn node_mut<'a>(nodes: &'a mut [CategoryNode], path: &[usize]) -> Option<&'a mut CategoryNode> {
+    let (first, rest) = path.split_first()?;
+    let node = nodes.get_mut(*first)?;
+    if rest.is_empty() { Some(node) } else { node_mut(&mut node.children, rest) }
+}
+
+//Indented text of the row, categories are marked as open or closed
+pub fn row_label(nodes: &[CategoryNode], row: &TreeRow) -> String {
+    match row {
+        TreeRow::Category(path) => {
+            let node = match node(nodes, path) {
+                Some(node) => node,
+                None => return String::new(),
+            };
+            let marker = if node.expanded { "▾" } else { "▸" };
+            format!("{}{} {}", "  ".repeat(path.len() - 1), marker, node.title)
+        }
+        TreeRow::Member(path, index) => {
+            let title = node(nodes, path).and_then(|n| n.members.get(*index)).map_or("", |m| m.title.as_str());
+            //Lined up with the titles of the subcategories
+            format!("{}  {}", "  ".repeat(path.len()), title)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn member(title: &str) -> Search {
+        Search {
+            ns: 0,
+            title: String::from(title),
+            pageid: 1,
+            size: 0,
+            wordcount: 0,
+            snippet: String::new(),
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn expanded_categories_show_subcategories_then_members() {
+        let mut logic = CategoryNode::new(String::from("Category:Logic"));
+        logic.expanded = true;
+        logic.members.push(member("Syllogism"));
+        let mut mathematics = CategoryNode::new(String::from("Category:Mathematics"));
+        mathematics.expanded = true;
+        mathematics.children.push(logic);
+        mathematics.members.push(member("Algebra"));
+        let tree = vec![mathematics, CategoryNode::new(String::from("Category:Philosophy"))];
+
+        let rows = rows(&tree);
+        let labels: Vec<String> = rows.iter().map(|r| row_label(&tree, r)).collect();
+
+        assert_eq!(labels, vec![
+            "▾ Category:Mathematics",
+            "  ▾ Category:Logic",
+            "      Syllogism",
+            "    Algebra",
+            "▸ Category:Philosophy",
+        ]);
+        assert_eq!(rows[2].parent(), Some(&[0, 0][..]));
+        assert_eq!(rows[1].parent(), Some(&[0][..]));
+        assert_eq!(rows[4].parent(), None);
+    }
+}
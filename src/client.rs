@@ -0,0 +1,452 @@
+use crate::cache::ArticleCache;
+use crate::config::Config;
+use crate::infobox::{self, InfoboxRow};
+use crate::local_index;
+use crate::log;
+use crate::stub;
+use crate::util::{self, QueryPreprocessOpts};
+use crate::wikimedia_types::{BacklinksResponse, CategoriesResponse, CategoryMembersResponse, Coordinate, CoordinatesResult, HtmlPageResult, ImagesResponse, InfoResult, Link, LinksPageResult, OpenSearchResponse, Search, SearchResponse, TopViewedResponse};
+use chrono::NaiveDate;
+use crate::Error;
+use regex::Regex;
+use reqwest::header::CONTENT_TYPE;
+use serde::de::DeserializeOwned;
+use std::path::PathBuf;
+
+const BACKLINKS_PER_REQUEST: u32 = 50;
+const CATEGORY_MEMBERS: u32 = 50;
+const TOP_VIEWED_URL: &str = "https://wikimedia.org/api/rest_v1/metrics/pageviews/top";
+
+//Added to every API request. Without utf8 some wikis escape non-ASCII characters,
+//formatversion 2 gives real booleans and flat page lists
+const FORMAT_PARAMS: &str = "format=json&utf8=1&formatversion=2";
+
+#[derive(Clone)]
+pub struct WikipediaClient {
+    client: reqwest::Client,
+    api_url: String,
+    //Searches and articles come from a tantivy index instead of the API if set
+    local_index_path: Option<PathBuf>,
+    cache: Option<ArticleCache>,
+    query_opts: QueryPreprocessOpts,
+    //srnamespace parameter, e.g. "0|14"
+    namespaces: String,
+    //API warnings are logged
+    debug: bool,
+}
+
+impl WikipediaClient {
+    pub fn new(config: &Config) -> Result<WikipediaClient, reqwest::Error> {
+        let mut builder = reqwest::Client::builder().user_agent(config.user_agent.as_str());
+
+        if let Some(proxy) = &config.proxy {
+            builder = builder.proxy(reqwest::Proxy::all(proxy.as_str())?);
+        }
+
+        Ok(WikipediaClient {
+            client: builder.build()?,
+            api_url: config.api_url(),
+            local_index_path: config.local_index_path.as_ref().map(PathBuf::from),
+            query_opts: QueryPreprocessOpts::from(config),
+            namespaces: match config.search_namespaces.as_slice() {
+                [] => String::from("0"),
+                namespaces => namespaces.iter().map(i64::to_string).collect::<Vec<String>>().join("|"),
+            },
+            cache: ArticleCache::new(&config.language, &config.api_url(), config.cache_max_mb, config.cache_ttl_hours),
+            debug: config.debug,
+        })
+    }
+
+    //The next fetch of the article goes to the API
+    pub fn forget_cached(&self, pageid: usize) {
+        if let Some(cache) = &self.cache {
+            cache.remove(pageid);
+        }
+    }
+
+    pub fn evict_cache(&self) {
+        if let Some(cache) = &self.cache {
+            cache.evict();
+        }
+    }
+
+    async fn get_json(&self, query: &str) -> Result<serde_json::Value, Error> {
+        let url = format!("{}?{}&{}", self.api_url, query, FORMAT_PARAMS);
+        self.get_json_from(&self.api_url, &url).await
+    }
+
+    //Captive portals and proxies answer with HTML pages instead of JSON. base is named when it can't be reached
+    async fn get_json_from(&self, base: &str, url: &str) -> Result<serde_json::Value, Error> {
+        let resp = self.client.get(url).send().await.map_err(|e| {
+            if e.is_connect() || e.is_timeout() {
+                Error::Unreachable(base.to_string())
+            } else {
+                Error::NetworkError(e)
+            }
+        })?;
+
+        let content_type = resp.headers().get(CONTENT_TYPE).and_then(|v| v.to_str().ok()).map(str::to_string);
+        let body = resp.text().await?;
+
+        json_body(content_type.as_deref(), &body)
+    }
+
+    pub async fn fetch_raw_html(&self, pageid: usize) -> Result<String, Error> {
+
+        if let Some(html) = self.cache.as_ref().and_then(|c| c.load(pageid)) {
+            return Ok(html);
+        }
+
+        let query = format!("action=parse&pageid={0}&prop=text", pageid);
+
+        let resp = self.get_json(&query).await?;
+
+        let page_res: HtmlPageResult = parse_response(resp, self.debug)?;
+
+        if let Some(cache) = &self.cache {
+            cache.store(pageid, &page_res.parse.text);
+        }
+
+        Ok(page_res.parse.text)
+    }
+
+    //Loads the article into the cache so opening it needs no request, without a cache there is nothing to keep it in
+    pub async fn prefetch(&self, pageid: usize) -> Result<(), Error> {
+
+        if self.local_index_path.is_some() || self.cache.is_none() {
+            return Ok(());
+        }
+
+        self.fetch_raw_html(pageid).await.map(|_| ())
+    }
+
+    //Titles starting with the term
+    pub async fn opensearch(&self, search_term: &str, limit: usize) -> Result<Vec<String>, Error> {
+
+        let term: String = url::form_urlencoded::byte_serialize(search_term.as_bytes()).collect();
+        let query = format!("action=opensearch&namespace=0&limit={}&search={}", limit, term);
+
+        let resp = self.get_json(&query).await?;
+
+        let opensearch_res: OpenSearchResponse = parse_response(resp, self.debug)?;
+
+        Ok(opensearch_res.results().into_iter().map(|(title, ..)| title).collect())
+    }
+
+    //Wikilinks to existing articles, in the order the API returns them
+    pub async fn fetch_links(&self, title: &str) -> Result<Vec<Link>, Error> {
+
+        let page: String = url::form_urlencoded::byte_serialize(title.as_bytes()).collect();
+        let query = format!("action=parse&page={0}&prop=links", page);
+
+        let resp = self.get_json(&query).await?;
+
+        let links_res: LinksPageResult = parse_response(resp, self.debug)?;
+
+        Ok(links_res.parse.links.into_iter().filter(|l| l.ns == 0 && l.exists).collect())
+    }
+
+    pub async fn fetch_page_links(&self, pageid: usize) -> Result<Vec<Link>, Error> {
+
+        let query = format!("action=parse&pageid={0}&prop=links", pageid);
+
+        let resp = self.get_json(&query).await?;
+
+        let links_res: LinksPageResult = parse_response(resp, self.debug)?;
+
+        Ok(links_res.parse.links.into_iter().filter(|l| l.ns == 0 && l.exists).collect())
+    }
+
+    //Articles linking to the title, 50 at a time. The token continues after the previous batch
+    //and comes back as None after the last one
+    pub async fn fetch_backlinks(&self, title: &str, continuation: Option<&str>) -> Result<(Vec<String>, Option<String>), Error> {
+
+        let page: String = url::form_urlencoded::byte_serialize(title.as_bytes()).collect();
+        let mut query = format!("action=query&list=backlinks&bltitle={0}&blnamespace=0&bllimit={1}", page, BACKLINKS_PER_REQUEST);
+        if let Some(token) = continuation {
+            let token: String = url::form_urlencoded::byte_serialize(token.as_bytes()).collect();
+            query.push_str(&format!("&blcontinue={}", token));
+        }
+
+        let resp = self.get_json(&query).await?;
+
+        let backlinks_res: BacklinksResponse = parse_response(resp, self.debug)?;
+
+        Ok((
+            backlinks_res.query.backlinks.into_iter().map(|b| b.title).collect(),
+            backlinks_res.continuation.map(|c| c.blcontinue),
+        ))
+    }
+
+    //Categories the page is in, hidden maintenance categories are left out
+    pub async fn fetch_categories(&self, title: &str) -> Result<Vec<String>, Error> {
+
+        let titles: String = url::form_urlencoded::byte_serialize(title.as_bytes()).collect();
+        let query = format!("action=query&prop=categories&clshow=!hidden&cllimit=max&titles={0}", titles);
+
+        let resp = self.get_json(&query).await?;
+
+        let categories_res: CategoriesResponse = parse_response(resp, self.debug)?;
+
+        Ok(categories_res.query.pages.into_iter().flat_map(|p| p.categories).map(|c| c.title).collect())
+    }
+
+    //Subcategories and articles of the category, CATEGORY_MEMBERS of each at most
+    pub async fn fetch_category_members(&self, category: &str) -> Result<(Vec<String>, Vec<Search>), Error> {
+
+        let title: String = url::form_urlencoded::byte_serialize(category.as_bytes()).collect();
+        let members = |cmtype: &str| format!("action=query&list=categorymembers&cmtitle={0}&cmtype={1}&cmlimit={2}", title, cmtype, CATEGORY_MEMBERS);
+
+        let resp = self.get_json(&members("subcat")).await?;
+        let subcategories: CategoryMembersResponse = parse_response(resp, self.debug)?;
+
+        let resp = self.get_json(&members("page")).await?;
+        let pages: CategoryMembersResponse = parse_response(resp, self.debug)?;
+
+        Ok((
+            subcategories.query.categorymembers.into_iter().map(|m| m.title).collect(),
+            pages.query.categorymembers.into_iter().map(|m| Search {
+                ns: m.ns,
+                title: m.title,
+                pageid: m.pageid,
+                size: 0,
+                wordcount: 0,
+                snippet: String::new(),
+                timestamp: String::new(),
+            }).collect(),
+        ))
+    }
+
+    //Primary coordinates of the article, None if it has no location
+    pub async fn fetch_coordinates(&self, pageid: usize) -> Result<Option<Coordinate>, Error> {
+
+        let query = format!("action=query&prop=coordinates&pageids={0}", pageid);
+
+        let resp = self.get_json(&query).await?;
+
+        let coordinates_res: CoordinatesResult = parse_response(resp, self.debug)?;
+
+        Ok(coordinates_res.query.pages.into_iter().find_map(|p| p.coordinates.into_iter().next()))
+    }
+
+    //Titles of the files used in the article, e.g. "File:Stephansdom.jpg"
+    pub async fn fetch_images(&self, pageid: usize) -> Result<Vec<String>, Error> {
+
+        let query = format!("action=query&prop=images&imlimit=max&pageids={0}", pageid);
+
+        let resp = self.get_json(&query).await?;
+
+        let images_res: ImagesResponse = parse_response(resp, self.debug)?;
+
+        Ok(images_res.query.pages.into_iter().flat_map(|p| p.images).map(|i| i.title).collect())
+    }
+
+    //Looks up a page that is not part of the search results, e.g. a talk page.
+    //None if the page does not exist
+    pub async fn fetch_page_info(&self, title: &str) -> Result<Option<Search>, Error> {
+        Ok(self.fetch_page_infos(&[title.to_string()]).await?.into_iter().next())
+    }
+
+    //Up to 50 titles per request, pages that do not exist are left out
+    async fn fetch_page_infos(&self, titles: &[String]) -> Result<Vec<Search>, Error> {
+
+        let titles: String = url::form_urlencoded::byte_serialize(titles.join("|").as_bytes()).collect();
+        let query = format!("action=query&prop=info&titles={0}", titles);
+
+        let resp = self.get_json(&query).await?;
+
+        let info_res: InfoResult = parse_response(resp, self.debug)?;
+
+        Ok(info_res.query.pages.into_iter().filter(|p| !p.missing).filter_map(|p| {
+            Some(Search {
+                ns: p.ns,
+                title: p.title,
+                pageid: p.pageid?,
+                size: p.length.unwrap_or(0),
+                wordcount: 0,
+                snippet: String::new(),
+                timestamp: p.touched.unwrap_or_default(),
+            })
+        }).collect())
+    }
+
+    //Most viewed articles of the day on the project, e.g. "en.wikipedia", with their views in rank order.
+    //Special pages and the main page are left out, most wikis keep the main page outside the article namespace
+    pub async fn fetch_top_viewed(&self, project: &str, day: NaiveDate, limit: usize) -> Result<Vec<(Search, u64)>, Error> {
+
+        let url = format!("{}/{}/all-access/{}", TOP_VIEWED_URL, project, day.format("%Y/%m/%d"));
+
+        let resp = self.get_json_from(TOP_VIEWED_URL, &url).await?;
+
+        let top_res: TopViewedResponse = parse_response(resp, self.debug)?;
+
+        let top: Vec<(String, u64)> = top_res.items.into_iter()
+            .flat_map(|d| d.articles)
+            .take(limit)
+            .map(|a| (a.article.replace('_', " "), a.views))
+            .collect();
+        let titles: Vec<String> = top.iter().map(|(title, _)| title.clone()).collect();
+        let pages = self.fetch_page_infos(&titles).await?;
+
+        //The info query returns the pages in its own order
+        let timestamp = format!("{}T00:00:00Z", day.format("%Y-%m-%d"));
+        Ok(top.into_iter().filter_map(|(title, views)| {
+            let mut page = pages.iter().find(|p| p.title == title && p.ns == 0)?.clone();
+            page.timestamp = timestamp.clone();
+            Some((page, views))
+        }).filter(|(page, _)| page.title != "Main Page").collect())
+    }
+
+    pub async fn fetch_html(&self, pageid: usize, text_width: u16) -> Result<String, Error> {
+
+        if let Some(index_path) = &self.local_index_path {
+            return local_index::fetch_content(index_path, pageid);
+        }
+
+        let html = self.fetch_raw_html(pageid).await?;
+
+        Ok(article_text(&html, text_width))
+    }
+
+    //Like fetch_html, but the infobox is returned as rows instead of being part of the text
+    pub async fn fetch_article(&self, pageid: usize, text_width: u16) -> Result<(String, Vec<InfoboxRow>), Error> {
+
+        if let Some(index_path) = &self.local_index_path {
+            return Ok((local_index::fetch_content(index_path, pageid)?, Vec::new()));
+        }
+
+        let html = self.fetch_raw_html(pageid).await?;
+
+        Ok(convert_article(&html, text_width, true))
+    }
+
+    pub async fn search(&self, search_term: &str) -> Result<SearchResponse, Error>  {
+        self.search_from(search_term, 0).await
+    }
+
+    //offset is the sroffset of the previous response's continue field
+    pub async fn search_from(&self, search_term: &str, offset: i64) -> Result<SearchResponse, Error>  {
+
+        if let Some(index_path) = &self.local_index_path {
+            return local_index::search(index_path, search_term);
+        }
+
+        let term = util::preprocess_query(search_term, &self.query_opts);
+        let query = format!("action=query&list=search&srsearch={}&sroffset={}&srnamespace={}", term, offset, self.namespaces);
+
+        let resp = self.get_json(&query).await?;
+
+        let search_resp: SearchResponse = parse_response(resp, self.debug)?;
+
+        Ok(search_resp)
+    }
+}
+
+//Anything not declared and parsed as JSON, e.g. the login page of a captive portal
+fn json_body(content_type: Option<&str>, body: &str) -> Result<serde_json::Value, Error> {
+    if !content_type.is_some_and(|v| v.contains("json")) {
+        return Err(Error::UnexpectedResponse);
+    }
+
+    serde_json::from_str(body).map_err(|_| Error::UnexpectedResponse)
+}
+
+//API errors come as {"error": {"code", "info"}} in place of the result, warnings, e.g. about
+//deprecated parameters, come along with a usable result. Other shapes keep the raw JSON for the debug display
+fn parse_response<T: DeserializeOwned>(resp: serde_json::Value, debug: bool) -> Result<T, Error> {
+    if debug {
+        if let Some(warnings) = resp.get("warnings") {
+            log::write_log(&format!("API warnings: {}", warnings));
+        }
+    }
+
+    T::deserialize(&resp).map_err(|e| match resp.get("error") {
+        Some(error) => Error::WikiApi {
+            code: error["code"].as_str().unwrap_or("unknown").to_string(),
+            info: error["info"].as_str().unwrap_or_default().to_string(),
+        },
+        None => Error::ParseResponseError(e, resp.to_string()),
+    })
+}
+
+//The text of the parse HTML, with the infobox split off into rows or left in the text
+pub fn convert_article(html: &str, text_width: u16, with_infobox: bool) -> (String, Vec<InfoboxRow>) {
+    if with_infobox {
+        let (body, rows) = infobox::extract_infobox(html);
+        (article_text(&body, text_width), rows)
+    } else {
+        (article_text(html, text_width), Vec::new())
+    }
+}
+
+//Redirects, disambiguation pages and empty articles are replaced by a notice of where to go instead
+fn article_text(html: &str, text_width: u16) -> String {
+    let text = clean_html(html, text_width);
+
+    match stub::detect_stub(html, &text) {
+        Some(stub) => stub::notice(&stub, &text),
+        None => text,
+    }
+}
+
+pub fn clean_html(html: &str, text_width: u16) -> String {
+    let html_regex = Regex::new(r#"<a href=\\#".*\\#">"#).unwrap();
+    let html_cleaned = html_regex.replace_all(html, "");
+
+    let text = html2text::from_read( String::from(html_cleaned).as_bytes(), text_width.into());
+
+    let re = Regex::new(r"(\[)+\d*(\])|(edit)+|\[|\]|(https:)?(/.*/.*)+[\s\S]|#+\s\W").unwrap();
+    //only Numbers (\[)+\d*(\])+
+    let cleaned = re.replace_all(&text, "");
+    let a = Regex::new(r"\d\s").unwrap();
+    let removed_single_digit = a.replace_all(&cleaned, "");
+
+    let mut removed_contents: String = String::from(removed_single_digit);
+
+    //A table of contents without a heading after it is left as it is
+    let contents_start = removed_contents.find("## Contents");
+    if let Some(i) = contents_start {
+        if let Some(end_index) = removed_contents[(i+11)..].find("## ") {
+            removed_contents = format!("{}{}", &removed_contents[..i], &removed_contents[(end_index+11+i)..]);
+        }
+    }
+
+    removed_contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wikimedia_types::SearchResponse;
+    use serde_json::json;
+
+    #[test]
+    fn api_errors_and_unknown_shapes_are_errors() {
+        let error = json!({"error": {"code": "badvalue", "info": "Unrecognized value for parameter \"list\""}});
+        match parse_response::<SearchResponse>(error, false) {
+            Err(Error::WikiApi { code, .. }) => assert_eq!(code, "badvalue"),
+            other => panic!("expected an API error, got {:?}", other),
+        }
+
+        let unknown = json!({"query": {"pages": []}});
+        match parse_response::<SearchResponse>(unknown, false) {
+            Err(Error::ParseResponseError(_, raw)) => assert!(raw.contains("pages")),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+
+        let warned = json!({
+            "warnings": {"main": {"warnings": "Unrecognized parameter: foo."}},
+            "query": {"searchinfo": {"totalhits": 0}, "search": []}
+        });
+        assert!(parse_response::<SearchResponse>(warned, false).is_ok());
+    }
+
+    #[test]
+    fn captive_portal_pages_are_unexpected() {
+        let portal = "<html><body><form action=\"/login\">Sign in to the hotel Wi-Fi</form></body></html>";
+        assert!(matches!(json_body(Some("text/html; charset=utf-8"), portal), Err(Error::UnexpectedResponse)));
+        assert!(matches!(json_body(None, portal), Err(Error::UnexpectedResponse)));
+        assert!(matches!(json_body(Some("application/json"), portal), Err(Error::UnexpectedResponse)));
+        assert!(json_body(Some("application/json; charset=utf-8"), "{\"batchcomplete\": true}").is_ok());
+    }
+}
@@ -0,0 +1,57 @@
+use base64::Engine;
+use std::io::{self, Write};
+
+pub enum CopyMethod {
+    System,
+    //OSC 52, the terminal puts the text into the clipboard, works over ssh and without a display
+    Terminal,
+}
+
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", base64::engine::general_purpose::STANDARD.encode(text))
+}
+
+//Tries the system clipboard first and falls back to the terminal's clipboard
+pub fn copy_to_clipboard(text: &str) -> io::Result<CopyMethod> {
+    let copied = arboard::Clipboard::new().and_then(|mut clipboard| clipboard.set_text(text.to_string()));
+
+    if copied.is_ok() {
+        return Ok(CopyMethod::System);
+    }
+
+    let mut stdout = io::stdout();
+    stdout.write_all(osc52(text).as_bytes())?;
+    stdout.flush()?;
+
+    Ok(CopyMethod::Terminal)
+}
+
+pub const MAX_PASTE_CHARS: usize = 256;
+
+//None if there is no system clipboard, e.g. over ssh, or it holds no text
+pub fn paste_from_clipboard() -> Option<String> {
+    arboard::Clipboard::new().and_then(|mut clipboard| clipboard.get_text()).ok()
+}
+
+//Line breaks become spaces and other control characters are dropped, the query is a single line.
+//Returns the sanitized text and whether it was truncated
+pub fn sanitize_paste(text: &str, max_chars: usize) -> (String, bool) {
+    let mut chars = text
+        .chars()
+        .map(|c| if c == '\n' || c == '\r' || c == '\t' { ' ' } else { c })
+        .filter(|c| !c.is_control());
+
+    let sanitized: String = chars.by_ref().take(max_chars).collect();
+    (sanitized, chars.next().is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pasted_text_is_a_single_line() {
+        assert_eq!(sanitize_paste("Vienna\r\nAustria\u{7}", 256), (String::from("Vienna  Austria"), false));
+        assert_eq!(sanitize_paste("abcdef", 4), (String::from("abcd"), true));
+    }
+}
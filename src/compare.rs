@@ -0,0 +1,39 @@
+use crate::wikimedia_types::Search;
+
+//One side of the compare view, the content is fetched in the background once a draw knows the pane's width
+pub struct ArticleReader {
+    pub article: Search,
+    pub content: Option<String>,
+    //Set when the fetch is scheduled, so every draw until it arrives doesn't start another
+    pub requested: bool,
+    pub scroll: u16,
+}
+
+impl ArticleReader {
+    pub fn new(article: Search) -> ArticleReader {
+        ArticleReader { article, content: None, requested: false, scroll: 0 }
+    }
+}
+
+pub struct CompareView {
+    pub readers: [ArticleReader; 2],
+    //Index of the reader that gets the scroll keys
+    pub focused: usize,
+}
+
+impl CompareView {
+    pub fn new(left: Search, right: Search) -> CompareView {
+        CompareView {
+            readers: [ArticleReader::new(left), ArticleReader::new(right)],
+            focused: 0,
+        }
+    }
+
+    pub fn focused_reader(&mut self) -> &mut ArticleReader {
+        &mut self.readers[self.focused]
+    }
+
+    pub fn toggle_focus(&mut self) {
+        self.focused = 1 - self.focused;
+    }
+}
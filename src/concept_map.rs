@@ -0,0 +1,55 @@
+use crate::client::WikipediaClient;
+use crate::links;
+use crate::wikimedia_types::Search;
+use crate::Error;
+use std::collections::HashMap;
+
+//Only the links the article points to most often are expanded, each with a few of their own links
+const EXPANDED_LINKS: usize = 5;
+const LINKS_PER_NODE: usize = 3;
+
+pub struct ConceptNode {
+    pub title: String,
+    pub depth: usize,
+}
+
+//Adjacency list of the article and its most linked wikilinks, two hops deep.
+//The article's links come most linked first
+pub async fn build_concept_map(client: &WikipediaClient, article: &Search) -> Result<HashMap<String, Vec<String>>, Error> {
+    let mut map = HashMap::new();
+    let title = &article.title;
+
+    let links: Vec<String> = client.fetch_page_links(article.pageid as usize).await?.into_iter().map(|l| l.title).collect();
+    let links = links::most_linked(links, &client.fetch_raw_html(article.pageid as usize).await?);
+
+    for link in links.iter().take(EXPANDED_LINKS) {
+        //A single broken link shouldn't hide the rest of the map
+        if let Ok(children) = client.fetch_links(link).await {
+            let children = children
+                .into_iter()
+                .map(|l| l.title)
+                .filter(|t| t != title)
+                .take(LINKS_PER_NODE)
+                .collect();
+            map.insert(link.clone(), children);
+        }
+    }
+    map.insert(title.to_string(), links);
+
+    Ok(map)
+}
+
+//Depth first order, so every node is directly followed by its links
+pub fn flatten_concept_map(title: &str, map: &HashMap<String, Vec<String>>) -> Vec<ConceptNode> {
+    let mut nodes = vec![ConceptNode { title: title.to_string(), depth: 0 }];
+
+    for link in map.get(title).into_iter().flatten() {
+        nodes.push(ConceptNode { title: link.clone(), depth: 1 });
+
+        for child in map.get(link).into_iter().flatten() {
+            nodes.push(ConceptNode { title: child.clone(), depth: 2 });
+        }
+    }
+
+    nodes
+}
@@ -0,0 +1,349 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("error reading the config file: {0}")]
+    Read(#[from] std::io::Error),
+    #[error("error parsing the config file: {0}")]
+    Parse(#[from] toml::de::Error),
+    #[error("invalid api_url '{0}', expected an http(s) URL ending in api.php")]
+    ApiUrl(String),
+    #[error("invalid network settings: {0}")]
+    NetworkSettings(#[from] reqwest::Error),
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+#[serde(default)]
+pub struct Config {
+    //Wikipedia language edition, e.g. "en" or "de"
+    pub language: String,
+    //MediaWiki API used instead of https://{language}.wikipedia.org/w/api.php,
+    //e.g. "https://wiki.example.com/w/api.php" for an intranet wiki
+    pub api_url: Option<String>,
+    //At least MIN_TICK_MS, shorter ticks keep the event loop busy
+    pub tick_ms: u64,
+    //Compares the terminal size on every tick, for terminals whose resize events get lost
+    pub resize_poll: bool,
+    pub user_agent: String,
+    pub proxy: Option<String>,
+    //Open the article directly if the search identifies exactly one article
+    pub feeling_lucky: bool,
+    pub text_alignment: TextAlignment,
+    //Brighter titles for recently edited results and dimmed ones for old results
+    pub freshness_colors: bool,
+    //Grey out the search box while it isn't focused, otherwise only the thick border tells
+    pub dim_search_box: bool,
+    //Number the results 1., 2., 3. in the order they are listed
+    pub result_ranks: bool,
+    //Show the matching text of the article below each result
+    pub show_snippets: bool,
+    //Strip markup, citation markers and cut off brackets from the snippets
+    pub clean_snippets: bool,
+    //How many articles are kept in the recently viewed list
+    pub recent_limit: usize,
+    //Non-interactive command the article is passed to with '&', e.g. "pandoc -f markdown -t html".
+    //Its output is shown in an overlay
+    pub external_tool: Option<String>,
+    //Bold and underlined headings in the article, toggled with Ctrl+B
+    pub styled_headings: bool,
+    //Loaded results shown at a time, PageUp and PageDown move between the windows. 0 shows all
+    pub results_window: usize,
+    //Highlight the words of the search in the article, 'M' toggles it for the session
+    pub highlight_search_terms: bool,
+    //Show the article's infobox as a panel beside the text at start, 'i' toggles it for the session
+    pub infobox: bool,
+    //Switches between the result list and the article, both keep their position
+    pub article_toggle_key: char,
+    //Articles opened on the way to the current one shown before its title, 0 hides them
+    pub breadcrumb_depth: usize,
+    //Narrower article panes show a hint to widen the terminal instead of the text
+    pub min_text_width: u16,
+    //Emit OSC 8 escape sequences so the article title can be clicked
+    pub hyperlinks: HyperlinkMode,
+    //tantivy index built with `tpedia index --dump-path <dump>`, used instead of the API
+    pub local_index_path: Option<String>,
+    //What a new search does to the current results: replace, stack or confirm
+    pub new_search: NewSearchMode,
+    //How many covered result lists are kept when new_search is "stack"
+    pub results_stack_depth: usize,
+    //Animate page jumps in the article instead of jumping at once
+    pub smooth_scroll: bool,
+    //Lines the animation moves per tick
+    pub smooth_scroll_speed: u16,
+    //PageUp and PageDown move to the previous or next section heading at start, 'S' toggles it for the session
+    pub section_scroll: bool,
+    //Search for "United States" when "US" is typed
+    pub expand_abbreviations: bool,
+    //Capitalize every word of the query, helps finding proper nouns
+    pub capitalize_query: bool,
+    //How many past searches are kept in history.json and the Past searches tab
+    pub history_limit: usize,
+    //Entries of the dropdown below the search box from the local history and from the API,
+    //both 0 hides the dropdown
+    pub suggestions_history: usize,
+    pub suggestions_api: usize,
+    //Reveal a newly opened article a few characters per tick, space pauses
+    pub typewriter: bool,
+    pub typewriter_speed: usize,
+    //Links listed with 'L' before 'a' shows all of them, 0 always shows all
+    pub max_links: usize,
+    //Namespaces searched through the API, e.g. [0, 14] for articles and categories
+    pub search_namespaces: Vec<i64>,
+    //Search while typing instead of waiting for Enter
+    pub incremental_search: bool,
+    //Load the top result into the cache in the background, so opening it is instant
+    pub prefetch_top_result: bool,
+    //Size limit of the article cache in MiB, 0 disables the cache
+    pub cache_max_mb: u64,
+    //Cached articles older than this are fetched again, 0 keeps them until evicted
+    pub cache_ttl_hours: u64,
+    //Offer corrections for misspelled queries, only used on the English Wikipedia
+    pub spell_check: bool,
+    //Quit after this many minutes without input, for shared terminals, 0 never quits
+    pub idle_timeout_minutes: u64,
+    //Enables maintainer tools like the rendering issue report
+    pub debug: bool,
+
+    //Tables have to come after all plain values, toml cannot serialize them otherwise
+    //Days since the last edit that separate fresh, recent, aging and stale articles
+    pub freshness_days: FreshnessDays,
+    //Named queries saved with Ctrl+S in the search box and listed on the Home screen
+    pub search_presets: BTreeMap<String, String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            language: String::from("en"),
+            api_url: None,
+            tick_ms: 200,
+            resize_poll: true,
+            user_agent: format!("Tpedia/{} (https://github.com/EnglederLucas/Tpedia)", env!("CARGO_PKG_VERSION")),
+            proxy: None,
+            feeling_lucky: false,
+            text_alignment: TextAlignment::default(),
+            freshness_colors: true,
+            dim_search_box: true,
+            result_ranks: true,
+            show_snippets: false,
+            clean_snippets: true,
+            recent_limit: 20,
+            external_tool: None,
+            styled_headings: true,
+            highlight_search_terms: true,
+            results_window: 20,
+            infobox: true,
+            article_toggle_key: 'o',
+            breadcrumb_depth: 3,
+            min_text_width: 20,
+            hyperlinks: HyperlinkMode::default(),
+            local_index_path: None,
+            new_search: NewSearchMode::default(),
+            results_stack_depth: 5,
+            smooth_scroll: false,
+            smooth_scroll_speed: 5,
+            section_scroll: false,
+            expand_abbreviations: false,
+            capitalize_query: false,
+            history_limit: 100,
+            suggestions_history: 3,
+            suggestions_api: 5,
+            typewriter: false,
+            typewriter_speed: 20,
+            max_links: 50,
+            search_namespaces: vec![0],
+            incremental_search: false,
+            prefetch_top_result: false,
+            cache_max_mb: 100,
+            cache_ttl_hours: 24,
+            spell_check: true,
+            idle_timeout_minutes: 0,
+            debug: false,
+            freshness_days: FreshnessDays::default(),
+            search_presets: BTreeMap::new(),
+        }
+    }
+}
+
+impl Config {
+    pub fn api_url(&self) -> String {
+        match &self.api_url {
+            Some(url) => url.clone(),
+            None => format!("https://{}.wikipedia.org/w/api.php", self.language),
+        }
+    }
+
+    //Wikipedia uses short /wiki/ links, other wikis are only known to have index.php.
+    //The title is encoded like in the API queries, e.g. "C++" or "AT&T" would break the URL otherwise
+    pub fn article_url(&self, title: &str) -> String {
+        let title: String = url::form_urlencoded::byte_serialize(title.replace(' ', "_").as_bytes()).collect();
+        match &self.api_url {
+            Some(url) => format!("{}index.php?title={}", url.trim_end_matches("api.php"), title),
+            None => format!("https://{}.wikipedia.org/wiki/{}", self.language, title),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum HyperlinkMode {
+    #[default]
+    Off,
+    Detect,
+    On,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum NewSearchMode {
+    #[default]
+    Replace,
+    //Esc in the result list goes back to the previous results
+    Stack,
+    //A second Enter is needed to replace the results
+    Confirm,
+}
+
+//Each value is the upper bound of its category, older articles are stale
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct FreshnessDays {
+    pub fresh: i64,
+    pub recent: i64,
+    pub aging: i64,
+}
+
+impl Default for FreshnessDays {
+    fn default() -> Self {
+        FreshnessDays {
+            fresh: 7,
+            recent: 90,
+            aging: 365,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TextAlignment {
+    #[default]
+    Left,
+    Justify,
+}
+
+pub fn default_index_path() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("tpedia").join("index"))
+}
+
+pub fn config_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join("tpedia").join("config.toml"))
+}
+
+//0 would make the input thread send ticks without pause
+const MIN_TICK_MS: u64 = 20;
+
+//A missing config file is not an error, the defaults are used instead
+pub fn load_config() -> Result<Config, ConfigError> {
+    let path = match config_path() {
+        Some(p) if p.exists() => p,
+        _ => return Ok(Config::default()),
+    };
+
+    let content = fs::read_to_string(path)?;
+    let mut config: Config = toml::from_str(&content)?;
+    config.tick_ms = config.tick_ms.max(MIN_TICK_MS);
+
+    if let Some(api_url) = &config.api_url {
+        let valid = url::Url::parse(api_url)
+            .map(|url| ["http", "https"].contains(&url.scheme()) && url.path().ends_with("api.php"))
+            .unwrap_or(false);
+        if !valid {
+            return Err(ConfigError::ApiUrl(api_url.clone()));
+        }
+    }
+
+    Ok(config)
+}
+
+#[derive(Error, Debug)]
+pub enum SaveConfigError {
+    #[error("error writing the config file: {0}")]
+    Write(#[from] std::io::Error),
+    #[error("error serializing the config: {0}")]
+    Serialize(#[from] toml::ser::Error),
+    #[error("error parsing the config file: {0}")]
+    Parse(#[from] toml_edit::TomlError),
+}
+
+//Only the given keys are replaced, the user's comments, formatting and other keys stay as they are
+fn update_config_text(existing: &str, config: &Config, keys: &[&str]) -> Result<String, SaveConfigError> {
+    let values: toml_edit::DocumentMut = toml::to_string(config)?.parse()?;
+    let mut document: toml_edit::DocumentMut = existing.parse()?;
+
+    for key in keys {
+        match values.get(key) {
+            Some(item) => document[*key] = item.clone(),
+            None => {
+                document.remove(key);
+            }
+        }
+    }
+
+    Ok(document.to_string())
+}
+
+pub fn save_config(config: &Config, keys: &[&str]) -> Result<(), SaveConfigError> {
+    let path = match config_path() {
+        Some(p) => p,
+        None => return Ok(()),
+    };
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let existing = if path.exists() { fs::read_to_string(&path)? } else { String::new() };
+    fs::write(path, update_config_text(&existing, config, keys)?)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_config_can_be_saved() {
+        let saved = toml::to_string(&Config::default()).unwrap();
+        let loaded: Config = toml::from_str(&saved).unwrap();
+
+        assert_eq!(loaded.freshness_days, FreshnessDays::default());
+        assert!(loaded.search_presets.is_empty());
+    }
+
+    #[test]
+    fn article_urls_encode_the_title() {
+        let config = Config::default();
+        assert_eq!(config.article_url("AT&T Park"), "https://en.wikipedia.org/wiki/AT%26T_Park");
+
+        let wiki = Config { api_url: Some(String::from("https://wiki.example.com/w/api.php")), ..Config::default() };
+        assert_eq!(wiki.article_url("C++ (language)"), "https://wiki.example.com/w/index.php?title=C%2B%2B_%28language%29");
+    }
+
+    #[test]
+    fn saving_keeps_comments_and_other_keys() {
+        let existing = "# My settings\nlanguage = \"de\" # German\ntick_ms = 0\n";
+        let mut config = Config { styled_headings: false, ..Config::default() };
+        config.search_presets.insert(String::from("rust"), String::from("rust language"));
+
+        let saved = update_config_text(existing, &config, &["styled_headings", "search_presets"]).unwrap();
+
+        assert!(saved.starts_with("# My settings\nlanguage = \"de\" # German\ntick_ms = 0\n"));
+        assert!(saved.contains("styled_headings = false"));
+        assert!(saved.contains("[search_presets]\nrust = \"rust language\""));
+        assert!(!saved.contains("debug"));
+    }
+}
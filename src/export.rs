@@ -0,0 +1,31 @@
+use crate::wikimedia_types::Search;
+use crate::Error;
+use chrono::Local;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+
+pub fn results_csv_path() -> PathBuf {
+    let dir = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    dir.join(format!("tpedia_results_{}.csv", Local::now().format("%Y%m%d_%H%M%S")))
+}
+
+//Every field is quoted, snippets contain commas and html
+pub fn export_results_csv(results: &[Search], path: &Path) -> Result<(), Error> {
+    let file = File::create(path)?;
+    let mut writer = csv::WriterBuilder::new().quote_style(csv::QuoteStyle::Always).from_writer(file);
+
+    writer.write_record(["pageid", "title", "wordcount", "size", "timestamp", "snippet"])?;
+    for result in results {
+        writer.write_record([
+            result.pageid.to_string(),
+            result.title.clone(),
+            result.wordcount.to_string(),
+            result.size.to_string(),
+            result.timestamp.clone(),
+            result.snippet.clone(),
+        ])?;
+    }
+    writer.flush()?;
+
+    Ok(())
+}
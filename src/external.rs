@@ -0,0 +1,54 @@
+use crate::Error;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::Command;
+use tempfile::NamedTempFile;
+
+//The article is written to a temp file whose path is appended to the configured command,
+//e.g. "pandoc -f markdown -t html" runs "pandoc -f markdown -t html /tmp/.tmpXXXX".
+//Batch tools only: stdin is closed and the output is returned, stderr as the error
+pub fn run_external_tool(command: &str, content: &str) -> Result<String, Error> {
+    let mut parts = command.split_whitespace();
+    let program = parts.next().ok_or_else(|| Error::ExternalToolError(String::from("no command configured")))?;
+
+    let mut file = NamedTempFile::new()?;
+    file.write_all(content.as_bytes())?;
+
+    let output = Command::new(program)
+        .args(parts)
+        .arg(file.path())
+        .output()
+        .map_err(|e| Error::ExternalToolError(e.to_string()))?;
+
+    if !output.status.success() {
+        return Err(Error::ExternalToolError(String::from_utf8_lossy(&output.stderr).into_owned()));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+//Opens the text in $EDITOR, nano if unset, and returns it as saved.
+//The editor needs the real terminal, so raw mode has to be disabled before
+pub fn edit_in_editor(text: &str) -> Result<String, Error> {
+    let editor = env::var("EDITOR").unwrap_or_else(|_| String::from("nano"));
+
+    let mut file = NamedTempFile::new()?;
+    file.write_all(text.as_bytes())?;
+
+    let status = Command::new(&editor)
+        .arg(file.path())
+        .status()
+        .map_err(|e| Error::ExternalToolError(format!("{}: {}", editor, e)))?;
+
+    if !status.success() {
+        return Err(Error::ExternalToolError(format!("{} exited with {}", editor, status)));
+    }
+
+    Ok(fs::read_to_string(file.path())?)
+}
+
+//Hands the URL to the platform's opener, which keeps its output off the TUI
+pub fn open_url(url: &str) -> Result<(), Error> {
+    opener::open(url).map_err(|e| Error::ExternalToolError(e.to_string()))
+}
@@ -0,0 +1,130 @@
+//Case-insensitive search in the article text. Lowercasing can change the length of a
+//character, e.g. 'İ' becomes "i̇", so the scan runs over the lowercased characters and
+//maps every match back to the byte range of the original characters
+
+//Byte ranges of the non-overlapping matches in the line, always on char boundaries
+pub fn find_in_line(line: &str, query: &str) -> Vec<(usize, usize)> {
+    let query: Vec<char> = query.chars().flat_map(char::to_lowercase).collect();
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    //Every lowercased character with the byte range of the character it came from
+    let folded: Vec<(char, usize, usize)> = line
+        .char_indices()
+        .flat_map(|(start, c)| c.to_lowercase().map(move |lower| (lower, start, start + c.len_utf8())))
+        .collect();
+
+    let mut matches = Vec::new();
+    let mut i = 0;
+    while i + query.len() <= folded.len() {
+        let window = &folded[i..i + query.len()];
+        if window.iter().zip(&query).all(|((c, ..), q)| c == q) {
+            matches.push((window[0].1, window[query.len() - 1].2));
+            i += query.len();
+        } else {
+            i += 1;
+        }
+    }
+
+    matches
+}
+
+//Words of a search query worth highlighting. Excluded words, operators like "AND",
+//keywords like "intitle:" and short words like "of" are left out
+pub fn query_terms(query: &str) -> Vec<String> {
+    query
+        .split_whitespace()
+        .filter(|w| !w.starts_with('-') && !w.contains(':') && !matches!(*w, "AND" | "NOT"))
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .filter(|w| w.chars().count() >= 3)
+        .map(str::to_string)
+        .collect()
+}
+
+//First word of the line without punctuation and heading marks, e.g. "Vienna" of "## (Vienna) today"
+pub fn first_word(line: &str) -> Option<&str> {
+    line.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()))
+        .find(|w| !w.is_empty())
+}
+
+//Matches of any of the terms, the one starting first and then the longer one wins where they overlap
+pub fn find_terms_in_line(line: &str, terms: &[String]) -> Vec<(usize, usize)> {
+    let mut matches: Vec<(usize, usize)> = terms.iter().flat_map(|t| find_in_line(line, t)).collect();
+    matches.sort_unstable_by_key(|&(start, end)| (start, std::cmp::Reverse(end)));
+
+    let mut end = 0;
+    matches.retain(|&(start, match_end)| {
+        let keep = start >= end;
+        if keep {
+            end = match_end;
+        }
+        keep
+    });
+    matches
+}
+
+//Lines that contain the query, in order
+pub fn matching_lines(text: &str, query: &str) -> Vec<usize> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !find_in_line(line, query).is_empty())
+        .map(|(i, _)| i)
+        .collect()
+}
+
+//First matching line after the current one, or before it when searching backwards. Wraps around
+pub fn next_match(lines: &[usize], current: usize, backwards: bool) -> Option<usize> {
+    if backwards {
+        lines.iter().rev().find(|&&l| l < current).or_else(|| lines.last()).copied()
+    } else {
+        lines.iter().find(|&&l| l > current).or_else(|| lines.first()).copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accented_terms_match_regardless_of_case() {
+        let line = "Die Österreichische Nationalbibliothek, ÖSTERREICH";
+
+        assert_eq!(find_in_line(line, "österreich"), vec![(4, 15), (41, 52)]);
+        assert_eq!(&line[4..15], "Österreich");
+    }
+
+    #[test]
+    fn cjk_terms_match_on_char_boundaries() {
+        let line = "東京は日本の首都。東京都";
+        let matches = find_in_line(line, "東京");
+
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|&(start, end)| &line[start..end] == "東京"));
+    }
+
+    #[test]
+    fn length_changing_case_folding_keeps_ranges_valid() {
+        let line = "İstanbul and istanbul";
+        let matches = find_in_line(line, "stanbul");
+
+        assert_eq!(matches.len(), 2);
+        assert!(matches.iter().all(|&(start, end)| line.is_char_boundary(start) && line.is_char_boundary(end)));
+        assert_eq!(next_match(&[2, 5, 9], 5, false), Some(9));
+        assert_eq!(next_match(&[2, 5, 9], 2, true), Some(9));
+    }
+
+    #[test]
+    fn search_terms_skip_operators_and_overlaps() {
+        let terms = query_terms("\"Vienna\" AND opera -ball intitle:state of");
+        assert_eq!(terms, vec!["Vienna", "opera"]);
+
+        let line = "The Vienna State Opera, Viennas opera house";
+        let matches = find_terms_in_line(line, &[String::from("vienna"), String::from("vien"), String::from("opera")]);
+        let found: Vec<&str> = matches.iter().map(|&(start, end)| &line[start..end]).collect();
+        assert_eq!(found, vec!["Vienna", "Opera", "Vienna", "opera"]);
+        assert_eq!(first_word("## (Vienna) today"), Some("Vienna"));
+        assert_eq!(first_word("  — "), None);
+    }
+}
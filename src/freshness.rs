@@ -0,0 +1,75 @@
+use crate::config::FreshnessDays;
+use chrono::{DateTime, Utc};
+use tui::style::{Color, Modifier, Style};
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Freshness {
+    Fresh,
+    Recent,
+    Aging,
+    Stale,
+    Unknown,
+}
+
+impl Freshness {
+    //timestamp is the last edit as returned by the search API, e.g. "2021-03-04T12:00:00Z"
+    pub fn from_timestamp(timestamp: &str, days: &FreshnessDays) -> Freshness {
+        let edited = match DateTime::parse_from_rfc3339(timestamp) {
+            Ok(edited) => edited.with_timezone(&Utc),
+            Err(_) => return Freshness::Unknown,
+        };
+
+        match (Utc::now() - edited).num_days() {
+            d if d < days.fresh => Freshness::Fresh,
+            d if d < days.recent => Freshness::Recent,
+            d if d < days.aging => Freshness::Aging,
+            _ => Freshness::Stale,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Freshness::Fresh => "🟢 Fresh",
+            Freshness::Recent => "🟡 Recent",
+            Freshness::Aging => "🟠 Aging",
+            Freshness::Stale => "🔴 Stale",
+            Freshness::Unknown => "🔵 Unknown",
+        }
+    }
+
+    pub fn color(self) -> Color {
+        match self {
+            Freshness::Fresh => Color::Green,
+            Freshness::Recent => Color::Yellow,
+            Freshness::Aging => Color::Rgb(255, 165, 0),
+            Freshness::Stale => Color::Red,
+            Freshness::Unknown => Color::Blue,
+        }
+    }
+
+    //Style of the title in the result list, unparsable timestamps keep the default
+    pub fn title_style(self) -> Style {
+        match self {
+            Freshness::Fresh => Style::default().fg(Color::White).add_modifier(Modifier::BOLD),
+            Freshness::Aging => Style::default().fg(Color::Gray),
+            Freshness::Stale => Style::default().fg(Color::DarkGray),
+            Freshness::Recent | Freshness::Unknown => Style::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn thresholds_come_from_the_config() {
+        let days = FreshnessDays { fresh: 1, recent: 2, aging: 30 };
+        let edited = (Utc::now() - Duration::days(10)).to_rfc3339();
+
+        assert_eq!(Freshness::from_timestamp(&edited, &days), Freshness::Aging);
+        assert_eq!(Freshness::from_timestamp(&edited, &FreshnessDays::default()), Freshness::Recent);
+        assert_eq!(Freshness::from_timestamp("yesterday", &days), Freshness::Unknown);
+    }
+}
@@ -0,0 +1,85 @@
+use crate::infobox;
+use regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct GalleryImage {
+    //With the namespace, e.g. "File:Stephansdom.jpg"
+    pub title: String,
+    //Empty for images without a caption, e.g. icons in navigation boxes
+    pub caption: String,
+}
+
+//"/wiki/File:Vienna_at_night.jpg" -> "File:Vienna at night.jpg"
+fn file_title(href: &str) -> String {
+    let name = href.rsplit('/').next().unwrap_or(href);
+    percent_encoding::percent_decode_str(name).decode_utf8_lossy().replace('_', " ")
+}
+
+fn caption_text(caption: &str) -> String {
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+    let text = infobox::decode_entities(&tags.replace_all(caption, ""));
+    text.split_whitespace().collect::<Vec<&str>>().join(" ")
+}
+
+//Captions by file title. Current parser output wraps images in <figure> with a <figcaption>,
+//older output in a thumbinner div with a thumbcaption
+fn extract_captions(html: &str) -> HashMap<String, String> {
+    let figure = Regex::new(r#"(?s)<figure[^>]*>(.*?)</figure>|<div class="thumbinner"[^>]*>(.*?)</div>\s*</div>"#).unwrap();
+    let file_link = Regex::new(r#"href="([^"]*/[^"/]*:[^"]+)""#).unwrap();
+    let caption = Regex::new(r#"(?s)<figcaption[^>]*>(.*?)</figcaption>|<div class="thumbcaption"[^>]*>(.*)"#).unwrap();
+
+    let mut captions = HashMap::new();
+    for block in figure.captures_iter(html) {
+        let block = block.get(1).or_else(|| block.get(2)).map_or("", |m| m.as_str());
+
+        let title = match file_link.captures(block) {
+            Some(link) => file_title(&link[1]),
+            None => continue,
+        };
+        let text = caption
+            .captures(block)
+            .and_then(|c| c.get(1).or_else(|| c.get(2)))
+            .map(|c| caption_text(c.as_str()))
+            .unwrap_or_default();
+
+        captions.entry(title).or_insert(text);
+    }
+
+    captions
+}
+
+//The images listed by the API in their order, with the caption they have in the article
+pub fn build_gallery(titles: Vec<String>, html: &str) -> Vec<GalleryImage> {
+    let captions = extract_captions(html);
+
+    titles
+        .into_iter()
+        .map(|title| {
+            let caption = captions.get(&title).cloned().unwrap_or_default();
+            GalleryImage { title, caption }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn captions_are_matched_to_their_files() {
+        let html = concat!(
+            r#"<figure typeof="mw:File/Thumb"><a href="/wiki/File:Stephansdom_Wien.jpg" class="mw-file-description">"#,
+            r#"<img src="//upload.wikimedia.org/a.jpg"></a><figcaption><a href="/wiki/St._Stephen%27s_Cathedral">St. Stephen&#39;s</a> at night</figcaption></figure>"#,
+            r#"<div class="thumb tright"><div class="thumbinner"><a href="/wiki/File:Wien_Karte.png" class="image"><img></a>"#,
+            r#"<div class="thumbcaption">Map of the districts</div></div></div>"#,
+        );
+        let titles = vec![String::from("File:Stephansdom Wien.jpg"), String::from("File:Wien Karte.png"), String::from("File:Commons-logo.svg")];
+
+        let gallery = build_gallery(titles, html);
+
+        assert_eq!(gallery[0].caption, "St. Stephen's at night");
+        assert_eq!(gallery[1].caption, "Map of the districts");
+        assert_eq!(gallery[2].caption, "");
+    }
+}
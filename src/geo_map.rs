@@ -0,0 +1,59 @@
+use crate::wikimedia_types::Coordinate;
+
+//Size of the map popup in cells
+pub const MAP_WIDTH: u16 = 60;
+pub const MAP_HEIGHT: u16 = 20;
+
+//Degrees visible at once, a third of the world so panning is useful
+const VIEW_LON: f64 = 120.0;
+const VIEW_LAT: f64 = 60.0;
+//Degrees moved per arrow key
+const PAN_STEP: f64 = 15.0;
+
+pub struct GeoMap {
+    pub title: String,
+    pub location: Coordinate,
+    //Center of the visible part as longitude, latitude
+    center: (f64, f64),
+}
+
+impl GeoMap {
+    pub fn new(title: &str, location: Coordinate) -> GeoMap {
+        let mut map = GeoMap {
+            title: title.to_string(),
+            location,
+            center: (0.0, 0.0),
+        };
+        map.pan(location.lon, location.lat);
+        map
+    }
+
+    //Keeps the view inside the world so the map never pans into emptiness
+    pub fn pan(&mut self, lon: f64, lat: f64) {
+        let max_lon = 180.0 - VIEW_LON / 2.0;
+        let max_lat = 90.0 - VIEW_LAT / 2.0;
+        self.center = (
+            (self.center.0 + lon).clamp(-max_lon, max_lon),
+            (self.center.1 + lat).clamp(-max_lat, max_lat),
+        );
+    }
+
+    pub fn pan_step(&mut self, lon_steps: f64, lat_steps: f64) {
+        self.pan(lon_steps * PAN_STEP, lat_steps * PAN_STEP);
+    }
+
+    pub fn x_bounds(&self) -> [f64; 2] {
+        [self.center.0 - VIEW_LON / 2.0, self.center.0 + VIEW_LON / 2.0]
+    }
+
+    pub fn y_bounds(&self) -> [f64; 2] {
+        [self.center.1 - VIEW_LAT / 2.0, self.center.1 + VIEW_LAT / 2.0]
+    }
+}
+
+pub fn osm_url(location: Coordinate) -> String {
+    format!(
+        "https://www.openstreetmap.org/?mlat={0}&mlon={1}#map=10/{0}/{1}",
+        location.lat, location.lon
+    )
+}
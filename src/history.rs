@@ -0,0 +1,64 @@
+use crate::storage;
+use crate::Error;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+const HISTORY_FILE: &str = "history.json";
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct HistoryEntry {
+    pub query: String,
+    pub timestamp: DateTime<Local>,
+    //totalhits of the search, missing in entries saved by older versions
+    #[serde(default)]
+    pub result_count: Option<i64>,
+}
+
+pub fn load_history() -> Result<Vec<HistoryEntry>, Error> {
+    storage::load_json(HISTORY_FILE)
+}
+
+pub fn save_history(history: &[HistoryEntry]) -> Result<(), Error> {
+    storage::save_json(HISTORY_FILE, history)
+}
+
+//The most recently used entry is always the last one
+pub fn push_history(history: &mut Vec<HistoryEntry>, query: &str, result_count: i64, limit: usize) {
+    let query = query.trim();
+    if query.is_empty() {
+        return;
+    }
+
+    history.retain(|e| e.query != query);
+    history.push(HistoryEntry {
+        query: query.to_string(),
+        timestamp: Local::now(),
+        result_count: Some(result_count),
+    });
+    if history.len() > limit {
+        history.drain(..history.len() - limit);
+    }
+}
+
+pub fn history_completions<'a>(history: &'a [HistoryEntry], prefix: &str) -> Vec<&'a str> {
+    let prefix = prefix.to_lowercase();
+
+    history
+        .iter()
+        .rev()
+        .map(|e| e.query.as_str())
+        .filter(|q| q.to_lowercase().starts_with(&prefix))
+        .collect()
+}
+
+//Entries containing the pattern anywhere, newest first, for Ctrl+R
+pub fn history_matches<'a>(history: &'a [HistoryEntry], pattern: &str) -> Vec<&'a str> {
+    let pattern = pattern.to_lowercase();
+
+    history
+        .iter()
+        .rev()
+        .map(|e| e.query.as_str())
+        .filter(|q| q.to_lowercase().contains(&pattern))
+        .collect()
+}
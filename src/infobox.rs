@@ -0,0 +1,133 @@
+use regex::Regex;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct InfoboxRow {
+    //Empty for rows that only have a value, e.g. the image caption
+    pub label: String,
+    //Empty for section headers that span the whole table
+    pub value: String,
+}
+
+//Byte range of the first infobox table including nested tables
+fn infobox_range(html: &str) -> Option<(usize, usize)> {
+    let start_regex = Regex::new(r#"<table[^>]*class="[^"]*\binfobox\b[^"]*"[^>]*>"#).unwrap();
+    let tag_regex = Regex::new(r"<(/?)table\b").unwrap();
+
+    let start = start_regex.find(html)?;
+    let mut depth = 1;
+
+    for tag in tag_regex.captures_iter(&html[start.end()..]) {
+        let m = tag.get(0).unwrap();
+        if tag[1].is_empty() {
+            depth += 1;
+            continue;
+        }
+
+        depth -= 1;
+        if depth == 0 {
+            let end = html[start.end() + m.start()..].find('>').map(|i| start.end() + m.start() + i + 1)?;
+            return Some((start.start(), end));
+        }
+    }
+
+    None
+}
+
+//Entities the parse and search APIs leave in text
+pub fn decode_entities(text: &str) -> String {
+    text.replace("&nbsp;", " ")
+        .replace("&#160;", " ")
+        .replace("&minus;", "-")
+        .replace("&#8722;", "-")
+        .replace("&ndash;", "–")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+fn cell_text(cell: &str) -> String {
+    //Footnote markers and embedded styles would end up as noise in the value
+    let noise = Regex::new(r#"(?s)<sup[^>]*class="[^"]*reference[^"]*"[^>]*>.*?</sup>|<style[^>]*>.*?</style>"#).unwrap();
+    let breaks = Regex::new(r"<br\s*/?>|</li>").unwrap();
+    let tags = Regex::new(r"<[^>]*>").unwrap();
+
+    let text = noise.replace_all(cell, "");
+    let text = breaks.replace_all(&text, ", ");
+    let text = tags.replace_all(&text, " ");
+    let text = decode_entities(&text);
+
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ");
+    text.replace(" ,", ",").trim_matches(|c: char| c == ',' || c.is_whitespace()).to_string()
+}
+
+fn parse_rows(table: &str) -> Vec<InfoboxRow> {
+    let row_regex = Regex::new(r"(?s)<tr[^>]*>(.*?)</tr>").unwrap();
+    let cell_regex = Regex::new(r"(?s)<(th|td)[^>]*>(.*?)</(?:th|td)>").unwrap();
+
+    let mut rows = Vec::new();
+    for row in row_regex.captures_iter(table) {
+        let mut label = String::new();
+        let mut value = String::new();
+
+        for cell in cell_regex.captures_iter(&row[1]) {
+            let text = cell_text(&cell[2]);
+            match &cell[1] {
+                "th" if label.is_empty() => label = text,
+                _ if value.is_empty() => value = text,
+                _ => {}
+            }
+        }
+
+        if !label.is_empty() || !value.is_empty() {
+            rows.push(InfoboxRow { label, value });
+        }
+    }
+
+    rows
+}
+
+//Splits the infobox off the parse HTML, html2text would flatten its table into unreadable text.
+//Articles without an infobox are returned unchanged with no rows
+pub fn extract_infobox(html: &str) -> (String, Vec<InfoboxRow>) {
+    match infobox_range(html) {
+        Some((start, end)) => {
+            let rows = parse_rows(&html[start..end]);
+            (format!("{}{}", &html[..start], &html[end..]), rows)
+        }
+        None => (html.to_string(), Vec::new()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn infobox_is_split_into_rows() {
+        let html = concat!(
+            "<p>Intro</p>",
+            r#"<table class="infobox vcard"><tbody>"#,
+            r#"<tr><th colspan="2">Vienna</th></tr>"#,
+            r#"<tr><th scope="row">Population</th><td>1,982,097<sup class="reference"><a>[3]</a></sup></td></tr>"#,
+            r#"<tr><th>Districts</th><td><table><tr><td>23</td></tr></table></td></tr>"#,
+            "</tbody></table>",
+            "<p>Body</p>",
+        );
+
+        let (body, rows) = extract_infobox(html);
+
+        assert_eq!(body, "<p>Intro</p><p>Body</p>");
+        assert_eq!(rows[0], InfoboxRow { label: String::from("Vienna"), value: String::new() });
+        assert_eq!(rows[1], InfoboxRow { label: String::from("Population"), value: String::from("1,982,097") });
+    }
+
+    #[test]
+    fn articles_without_infobox_are_unchanged() {
+        let (body, rows) = extract_infobox("<p>No facts here</p>");
+
+        assert_eq!(body, "<p>No facts here</p>");
+        assert!(rows.is_empty());
+    }
+}
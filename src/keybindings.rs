@@ -0,0 +1,240 @@
+use crate::storage;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+pub struct Keybinding {
+    pub key: &'static str,
+    pub action: &'static str,
+    pub description: &'static str,
+}
+
+const fn bind(key: &'static str, action: &'static str, description: &'static str) -> Keybinding {
+    Keybinding { key, action, description }
+}
+
+//Every key the input handling in main.rs reacts to, grouped by the context it works in
+pub const KEYBINDINGS: &[(&str, &[Keybinding])] = &[
+    ("Global", &[
+        bind("q", "quit", "Exit Tpedia"),
+        bind("h", "home", "Show the home tab"),
+        bind("Ctrl+H/F2", "report bug", "Open a pre-filled GitHub issue in the browser"),
+        bind("v", "recent", "Show the recently viewed articles"),
+        bind("r", "results", "Show the results tab"),
+        bind("p", "past searches", "Show the search history"),
+        bind("s", "search", "Focus the search box"),
+        bind("Tab/Ctrl+I", "next pane", "Cycle focus between menu, search box, results, article, contents and status bar"),
+        bind("B", "export bookmarks", "Write all bookmarked articles into one Markdown file"),
+        bind("Ctrl+O", "recent searches", "Pick one of this session's searches to run again (outside the search box)"),
+        bind("Ctrl+R", "retry", "Try the last failed search or article again (outside the search box)"),
+        bind("Ctrl+T", "trending", "List yesterday's most viewed articles"),
+        bind("Ctrl+K", "command palette", "Find and run any command by name (outside the search box)"),
+        bind("Ctrl+L", "redraw", "Clear the terminal and draw everything again"),
+        bind("Ctrl+U", "undo", "Go back to before the last search or opened article (outside the search box)"),
+        bind("Ctrl+Y", "redo", "Go forward again after Ctrl+U (outside the search box)"),
+        bind("Ctrl+Z", "suspend", "Stop Tpedia and return to the shell, fg resumes it (Unix only)"),
+        bind("F5", "reload config", "Read the config file again"),
+        bind("F6", "save screen", "Save the screen as plain text and ANSI"),
+        bind("F10", "keybindings", "Write this reference to keybindings.txt"),
+        bind("F11", "full screen", "Hide menu and footer and maximize the window"),
+        bind("F12", "lua", "Open the Lua REPL"),
+    ]),
+    ("Menu", &[
+        bind("Left/Right", "tab", "Switch to the previous or next tab"),
+        bind("Enter/Down", "list", "Move the focus to the list of the tab"),
+    ]),
+    ("Search Mode", &[
+        bind("Enter", "search", "Search for the query"),
+        bind("Alt+Enter", "feeling lucky", "Search and open the top result"),
+        bind("Tab", "complete", "Next history completion, leaves the box if there is none"),
+        bind("Shift+Tab", "complete", "Previous history completion"),
+        bind("Up/Down", "suggestion", "Highlight a suggestion, Enter searches for it"),
+        bind("Ctrl+P/Ctrl+N", "suggestion", "Same as Up and Down"),
+        bind("Ctrl+A", "select all", "Select the query, typing replaces and Backspace clears it"),
+        bind("Ctrl+C", "copy", "Copy the selected query to the clipboard"),
+        bind("Ctrl+V", "paste", "Append the clipboard text to the query"),
+        bind("Ctrl+R", "history search", "Search the history, again for an older match, Esc restores the query"),
+        bind("Ctrl+S", "save preset", "Name the query and keep it as a preset on the Home screen"),
+        bind("Ctrl+U", "clear", "Delete the whole query"),
+        bind("Ctrl+W", "delete word", "Delete the last word of the query"),
+        bind("Ctrl+K", "kill line", "Delete to the end, the cursor is always there so nothing happens"),
+        bind("F7", "spell check", "Check the query for spelling mistakes"),
+        bind("y", "accept", "Search for the suggested spelling"),
+        bind("Esc", "leave", "Leave the search box"),
+    ]),
+    ("Home", &[
+        bind("Up/Down", "select", "Move through the recently viewed articles or the presets"),
+        bind("Left/Right", "switch list", "Switch between recently viewed articles and search presets"),
+        bind("Enter", "open", "Open the selected article where it was left, or run the preset"),
+        bind("X", "delete preset", "Delete the selected preset, again to confirm"),
+        bind("o", "article", "Back to the open article (article_toggle_key)"),
+    ]),
+    ("Past searches", &[
+        bind("Up/Down", "select", "Move through the past searches"),
+        bind("Enter", "search again", "Run the selected search again"),
+        bind("d/Delete", "delete", "Remove the search from the history file"),
+    ]),
+    ("Results", &[
+        bind("Up/Down", "select", "Move through the results"),
+        bind("Enter", "open", "Open the selected article"),
+        bind("1-9", "quick open", "Open the result with that rank"),
+        bind("Ctrl+F", "size filter", "Show only results >10KB, >50KB, >100KB, stubs (<2KB) or all again"),
+        bind("Ctrl+D", "duplicates", "Highlight results with near identical snippets"),
+        bind("y", "copy query", "Copy the search query to the clipboard"),
+        bind("m", "load more", "Load the next page of results"),
+        bind("[/]", "page", "Show the previous or next page of results instead"),
+        bind("PgUp/PgDn", "window", "Jump to the previous or next results_window of the loaded results"),
+        bind("Home/End", "first/last", "Select the first or last loaded result"),
+        bind("Esc", "previous results", "Go back to the results of the last search (new_search = \"stack\")"),
+        bind("c", "compare", "Pick the article to compare, twice to open both"),
+        bind("P", "pin", "Pin or unpin the result, pinned results stay on top for the session"),
+        bind("F9", "export csv", "Write the results to a CSV file"),
+    ]),
+    ("Status Bar", &[
+        bind("y", "copy", "Copy the status message to the clipboard"),
+        bind("Delete", "dismiss", "Clear the status message"),
+        bind("Esc/Up", "leave", "Move the focus back to the article or the list"),
+    ]),
+    ("Contents", &[
+        bind("Up/Down", "heading", "Highlight the previous or next heading of the article"),
+        bind("Enter", "jump", "Scroll the article to the heading"),
+        bind("Esc", "leave", "Move the focus back to the article"),
+    ]),
+    ("Article View", &[
+        bind("Up/Down", "scroll", "Scroll the article"),
+        bind("PgUp/PgDn", "page", "Scroll the article by a page"),
+        bind("Home", "top", "Scroll back to the top of the article"),
+        bind("S", "section scroll", "PageUp/PageDown move to the previous or next heading instead (section_scroll)"),
+        bind("Esc", "close", "Close the article, its position is kept"),
+        bind("Ctrl+W", "discard", "Close the article and forget its position"),
+        bind("o", "results", "Back to the result list (article_toggle_key)"),
+        bind("Space", "pause reveal", "Pause or resume the typewriter reveal (typewriter = true)"),
+        bind("Backspace", "back", "Go back to the previous article of the breadcrumb"),
+        bind("t", "talk page", "Open the discussion of the article"),
+        bind("/", "find", "Find text in the article, case is ignored"),
+        bind("n/N", "next match", "Jump to the next or previous match of the find"),
+        bind("Ctrl+N", "search word", "Edit and search for the find match or the first word of the top line"),
+        bind("M", "search terms", "Toggle the highlighting of the search words for the session"),
+        bind("z", "fold", "Fold or unfold the section at the top"),
+        bind("Z", "fold all", "Show only the headings"),
+        bind("X", "unfold all", "Show all sections again"),
+        bind("~", "markdown", "Preview the article as Markdown"),
+        bind("b", "bookmark", "Add or remove a bookmark"),
+        bind("Ctrl+B", "headings", "Toggle bold and underlined headings"),
+        bind("Ctrl+E", "wayback", "Open the archived version from the last edit in the browser"),
+        bind("Ctrl+G", "map", "Show the article's location on a world map"),
+        bind("i", "infobox", "Show or hide the infobox panel"),
+        bind("*", "search word", "Search for the first word of the top line"),
+        bind("&", "external tool", "Run the external_tool on the article and show its output"),
+        bind("Ctrl+X", "notes", "Write notes on the article in $EDITOR, shown above the text"),
+        bind("c", "compare", "Pick the article to compare, twice to open both"),
+        bind("U", "refresh", "Fetch the article again instead of using the cache"),
+        bind("F8", "concept map", "Show the article's links two hops deep"),
+        bind("G", "gallery", "List the article's images with their captions"),
+        bind("L", "links", "List the linked articles, links in the text first"),
+        bind("W", "what links here", "List the articles that link to this one"),
+        bind("C", "categories", "Browse the categories of the article"),
+        bind("H", "raw html", "Show the unprocessed article HTML (debug only)"),
+        bind("R", "report", "Write a rendering issue report (debug only)"),
+    ]),
+    ("Compare View", &[
+        bind("Left/Right", "switch", "Move the focus to the other article"),
+        bind("Up/Down", "scroll", "Scroll the focused article"),
+        bind("Esc", "close", "Close the compare view"),
+    ]),
+    ("Lua REPL", &[
+        bind("Enter", "run", "Evaluate the line, expressions print their value"),
+        bind("Esc", "close", "Close the REPL, its globals are kept"),
+    ]),
+    ("World Map", &[
+        bind("Arrows", "pan", "Move the visible part of the map"),
+        bind("Esc", "close", "Close the map"),
+    ]),
+    ("Error", &[
+        bind("Enter", "retry", "Try the failed search or article again"),
+        bind("Esc", "dismiss", "Hide the error"),
+        bind("q/F-keys", "global", "Quit and the global function keys work while the error is shown"),
+    ]),
+    ("Markdown Preview", &[
+        bind("Up/Down", "scroll", "Scroll the preview"),
+        bind("w", "write", "Write the Markdown to the download directory"),
+        bind("Esc", "close", "Close the preview"),
+    ]),
+    ("Command Palette", &[
+        bind("Typing", "filter", "Fuzzy filter the commands by name and description"),
+        bind("Up/Down", "select", "Move through the commands"),
+        bind("Enter", "run", "Run the selected command"),
+        bind("Esc", "close", "Close the palette"),
+    ]),
+    ("Categories", &[
+        bind("Up/Down", "select", "Move through the categories and articles"),
+        bind("Right", "expand", "Show the subcategories and articles of the category"),
+        bind("Left", "parent", "Collapse, go to the parent, or show the categories above the top level"),
+        bind("Enter", "open", "Open the article, or expand and collapse the category"),
+        bind("Esc", "close", "Close the category browser"),
+    ]),
+    ("Recent Searches", &[
+        bind("Up/Down", "select", "Move through the searches, newest first"),
+        bind("Enter", "search", "Run the search again"),
+        bind("d", "delete", "Remove the search from the popup"),
+        bind("Esc", "close", "Close the popup"),
+    ]),
+    ("What Links Here", &[
+        bind("Up/Down", "select", "Move through the linking articles"),
+        bind("m", "load more", "Load the next 50 linking articles"),
+        bind("Enter", "open", "Open the selected article"),
+        bind("Esc", "close", "Close the list"),
+    ]),
+    ("Links", &[
+        bind("Up/Down", "select", "Move through the links"),
+        bind("a", "show all", "Show all links instead of the first max_links"),
+        bind("Enter", "open", "Open the linked article"),
+        bind("Esc", "close", "Close the link list"),
+    ]),
+    ("Gallery", &[
+        bind("Up/Down", "select", "Move through the images"),
+        bind("Enter", "open", "Open the image in the default viewer"),
+        bind("Esc", "close", "Close the gallery"),
+    ]),
+    ("Concept Map", &[
+        bind("Up/Down", "select", "Move through the linked articles"),
+        bind("Enter", "open", "Open the selected article"),
+        bind("Esc", "close", "Close the concept map"),
+    ]),
+];
+
+pub fn format_keybindings() -> String {
+    let all = || KEYBINDINGS.iter().flat_map(|(_, bindings)| bindings.iter());
+    let key_width = all().map(|b| b.key.len()).max().unwrap_or(0);
+    let action_width = all().map(|b| b.action.len()).max().unwrap_or(0);
+
+    let mut text = String::from("Tpedia keybindings\n");
+    for (context, bindings) in KEYBINDINGS {
+        text.push_str(&format!("\n{}\n{}\n", context, "-".repeat(context.len())));
+
+        for binding in bindings.iter() {
+            text.push_str(&format!(
+                "{:key_width$}  {:action_width$}  {}\n",
+                binding.key,
+                binding.action,
+                binding.description,
+                key_width = key_width,
+                action_width = action_width
+            ));
+        }
+    }
+
+    text
+}
+
+pub fn write_keybindings() -> io::Result<PathBuf> {
+    let path = storage::data_path("keybindings.txt")
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no data directory"))?;
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    fs::write(&path, format_keybindings())?;
+
+    Ok(path)
+}
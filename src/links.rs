@@ -0,0 +1,91 @@
+use regex::Regex;
+use std::collections::HashMap;
+
+//Navigation boxes and the footer come after the body and repeat the same hub links on every
+//article of a topic, they start at the first navbox or the authority control box
+fn body_html(html: &str) -> &str {
+    let footer = Regex::new(r#"class="[^"]*\b(navbox|authority-control)\b"#).unwrap();
+    match footer.find(html) {
+        Some(m) => &html[..m.start()],
+        None => html,
+    }
+}
+
+//Every article link of the body in the order they appear, repeated links included
+fn body_link_targets(html: &str) -> Vec<String> {
+    let link = Regex::new(r##"href="/wiki/([^"#]+)"##).unwrap();
+
+    link.captures_iter(body_html(html))
+        .map(|captures| percent_encoding::percent_decode_str(&captures[1]).decode_utf8_lossy().replace('_', " "))
+        .collect()
+}
+
+//Article titles linked from the body in the order they appear, each once
+fn body_links(html: &str) -> Vec<String> {
+    let mut titles: Vec<String> = Vec::new();
+    for title in body_link_targets(html) {
+        if !titles.contains(&title) {
+            titles.push(title);
+        }
+    }
+
+    titles
+}
+
+//Links the body points to most often first, ties in reading order
+pub fn most_linked(links: Vec<String>, html: &str) -> Vec<String> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for title in body_link_targets(html) {
+        *counts.entry(title).or_default() += 1;
+    }
+
+    let mut ranked = prioritize_links(links, html);
+    ranked.sort_by_key(|l| std::cmp::Reverse(counts.get(l).copied().unwrap_or(0)));
+    ranked
+}
+
+//Links from the body first in reading order, then the rest in the API's alphabetical order
+pub fn prioritize_links(links: Vec<String>, html: &str) -> Vec<String> {
+    let body = body_links(html);
+    let (mut in_body, rest): (Vec<String>, Vec<String>) = links.into_iter().partition(|l| body.contains(l));
+
+    in_body.sort_by_key(|l| body.iter().position(|b| b == l));
+    in_body.extend(rest);
+    in_body
+}
+
+//The first max links, all of them if max is 0 or show_all is set
+pub fn visible_links(links: &[String], max: usize, show_all: bool) -> &[String] {
+    if show_all || max == 0 {
+        links
+    } else {
+        &links[..links.len().min(max)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn body_links_come_first_and_are_capped() {
+        let html = concat!(
+            r#"<p><a href="/wiki/Danube">Danube</a> flows through <a href="/wiki/Vienna">Vienna</a></p>"#,
+            r#"<div class="navbox"><a href="/wiki/Austria">Austria</a> <a href="/wiki/Danube">Danube</a></div>"#,
+        );
+        let links = vec![String::from("Austria"), String::from("Danube"), String::from("Vienna")];
+
+        let prioritized = prioritize_links(links, html);
+
+        assert_eq!(prioritized, vec!["Danube", "Vienna", "Austria"]);
+        assert_eq!(visible_links(&prioritized, 2, false), &prioritized[..2]);
+        assert_eq!(visible_links(&prioritized, 2, true).len(), 3);
+
+        let html = concat!(
+            r#"<p><a href="/wiki/Danube">Danube</a> flows through <a href="/wiki/Vienna">Vienna</a>, "#,
+            r#"the capital on the <a href="/wiki/Danube">river</a></p>"#,
+        );
+        let links = vec![String::from("Austria"), String::from("Danube"), String::from("Vienna")];
+        assert_eq!(most_linked(links, html), vec!["Danube", "Vienna", "Austria"]);
+    }
+}
@@ -0,0 +1,201 @@
+use crate::wikimedia_types::{Query, Search, SearchResponse, Searchinfo};
+use crate::Error;
+use bzip2::read::BzDecoder;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+use tantivy::collector::{Count, TopDocs};
+use tantivy::query::{QueryParser, TermQuery};
+use tantivy::schema::{Field, IndexRecordOption, Schema, Value, INDEXED, STORED, TEXT};
+use tantivy::{doc, Index, IndexWriter, TantivyDocument, Term};
+
+const SNIPPET_LENGTH: usize = 200;
+
+struct Fields {
+    pageid: Field,
+    title: Field,
+    content: Field,
+    timestamp: Field,
+}
+
+fn build_schema() -> (Schema, Fields) {
+    let mut builder = Schema::builder();
+    let fields = Fields {
+        pageid: builder.add_u64_field("pageid", INDEXED | STORED),
+        title: builder.add_text_field("title", TEXT | STORED),
+        content: builder.add_text_field("content", TEXT | STORED),
+        timestamp: builder.add_text_field("timestamp", STORED),
+    };
+
+    (builder.build(), fields)
+}
+
+fn index_error<E: std::fmt::Display>(e: E) -> Error {
+    Error::LocalIndexError(e.to_string())
+}
+
+fn open_index(index_path: &Path) -> Result<(Index, Fields), Error> {
+    let index = Index::open_in_dir(index_path).map_err(index_error)?;
+    let schema = index.schema();
+
+    let fields = Fields {
+        pageid: schema.get_field("pageid").map_err(index_error)?,
+        title: schema.get_field("title").map_err(index_error)?,
+        content: schema.get_field("content").map_err(index_error)?,
+        timestamp: schema.get_field("timestamp").map_err(index_error)?,
+    };
+
+    Ok((index, fields))
+}
+
+#[derive(Default)]
+struct DumpPage {
+    title: String,
+    ns: String,
+    id: String,
+    timestamp: String,
+    text: String,
+    is_redirect: bool,
+}
+
+//Reads a pages-articles XML dump (plain or .bz2) and indexes every article that isn't a redirect
+pub fn build_index(dump_path: &Path, index_path: &Path) -> Result<usize, Error> {
+    let file = File::open(dump_path)?;
+    let input: Box<dyn BufRead> = if dump_path.extension().is_some_and(|e| e == "bz2") {
+        Box::new(BufReader::new(BzDecoder::new(file)))
+    } else {
+        Box::new(BufReader::new(file))
+    };
+
+    //A reindex replaces the old index, other directories are left alone
+    if index_path.join("meta.json").exists() {
+        fs::remove_dir_all(index_path)?;
+    }
+    fs::create_dir_all(index_path)?;
+    let (schema, fields) = build_schema();
+    let index = Index::create_in_dir(index_path, schema).map_err(index_error)?;
+    let mut writer: IndexWriter = index.writer(100_000_000).map_err(index_error)?;
+
+    let mut reader = Reader::from_reader(input);
+    let mut buf = Vec::new();
+    let mut page = DumpPage::default();
+    let mut current_tag: Vec<u8> = Vec::new();
+    let mut in_revision = false;
+    let mut indexed = 0;
+
+    loop {
+        match reader.read_event_into(&mut buf).map_err(index_error)? {
+            Event::Start(e) => {
+                match e.name().as_ref() {
+                    b"page" => page = DumpPage::default(),
+                    b"revision" => in_revision = true,
+                    _ => {}
+                }
+                current_tag = e.name().as_ref().to_vec();
+            }
+            Event::Empty(e) if e.name().as_ref() == b"redirect" => page.is_redirect = true,
+            Event::Text(t) => {
+                let text = t.unescape().map_err(index_error)?;
+                match (current_tag.as_slice(), in_revision) {
+                    (b"title", _) => page.title.push_str(&text),
+                    (b"ns", _) => page.ns.push_str(&text),
+                    (b"id", false) => page.id.push_str(&text),
+                    (b"timestamp", true) => page.timestamp.push_str(&text),
+                    (b"text", true) => page.text.push_str(&text),
+                    _ => {}
+                }
+            }
+            Event::End(e) => {
+                match e.name().as_ref() {
+                    b"revision" => in_revision = false,
+                    b"page" => {
+                        if let (Ok(id), "0", false) = (page.id.trim().parse::<u64>(), page.ns.trim(), page.is_redirect) {
+                            writer.add_document(doc!(
+                                fields.pageid => id,
+                                fields.title => page.title.clone(),
+                                fields.content => page.text.clone(),
+                                fields.timestamp => page.timestamp.clone(),
+                            )).map_err(index_error)?;
+                            indexed += 1;
+                        }
+                    }
+                    _ => {}
+                }
+                current_tag.clear();
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+
+    writer.commit().map_err(index_error)?;
+
+    Ok(indexed)
+}
+
+pub fn search(index_path: &Path, search_term: &str) -> Result<SearchResponse, Error> {
+    let (index, fields) = open_index(index_path)?;
+    let searcher = index.reader().map_err(index_error)?.searcher();
+
+    let parser = QueryParser::for_index(&index, vec![fields.title, fields.content]);
+    let (query, _) = parser.parse_query_lenient(search_term);
+    let (top_docs, totalhits) = searcher
+        .search(&query, &(TopDocs::with_limit(20), Count))
+        .map_err(index_error)?;
+
+    let mut results = Vec::new();
+    for (_, address) in top_docs {
+        let document: TantivyDocument = searcher.doc(address).map_err(index_error)?;
+        let text = |field: Field| {
+            document.get_first(field).and_then(|v| v.as_str()).unwrap_or_default().to_string()
+        };
+        let content = text(fields.content);
+
+        results.push(Search {
+            ns: 0,
+            title: text(fields.title),
+            pageid: document.get_first(fields.pageid).and_then(|v| v.as_u64()).unwrap_or_default() as i64,
+            size: content.len() as i64,
+            wordcount: content.split_whitespace().count() as i64,
+            snippet: content.chars().take(SNIPPET_LENGTH).collect(),
+            timestamp: text(fields.timestamp),
+        });
+    }
+
+    Ok(SearchResponse {
+        batchcomplete: Some(true),
+        search_response_continue: None,
+        query: Query {
+            searchinfo: Searchinfo {
+                totalhits: totalhits as i64,
+                suggestion: None,
+                suggestionsnippet: None,
+            },
+            search: results,
+        },
+    })
+}
+
+//The dump only contains wikitext, which is shown as is
+pub fn fetch_content(index_path: &Path, pageid: usize) -> Result<String, Error> {
+    let (index, fields) = open_index(index_path)?;
+    let searcher = index.reader().map_err(index_error)?.searcher();
+
+    let term = Term::from_field_u64(fields.pageid, pageid as u64);
+    let query = TermQuery::new(term, IndexRecordOption::Basic);
+    let top_docs = searcher.search(&query, &TopDocs::with_limit(1)).map_err(index_error)?;
+
+    let (_, address) = top_docs
+        .first()
+        .ok_or_else(|| Error::LocalIndexError(format!("page {} is not in the local index", pageid)))?;
+    let document: TantivyDocument = searcher.doc(*address).map_err(index_error)?;
+
+    Ok(document
+        .get_first(fields.content)
+        .and_then(|v| v.as_str())
+        .unwrap_or_default()
+        .to_string())
+}
@@ -0,0 +1,21 @@
+use crate::storage;
+use chrono::Local;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+
+//Appends a line to ~/.local/share/tpedia/tpedia.log, the TUI owns the terminal so
+//background housekeeping can't print, failing to log is never an error
+pub fn write_log(message: &str) {
+    let path = match storage::data_path("tpedia.log") {
+        Some(p) => p,
+        None => return,
+    };
+
+    if let Some(dir) = path.parent() {
+        let _ = fs::create_dir_all(dir);
+    }
+
+    if let Ok(mut file) = OpenOptions::new().create(true).append(true).open(path) {
+        let _ = writeln!(file, "[{}] {}", Local::now().format("%Y-%m-%d %H:%M:%S"), message);
+    }
+}
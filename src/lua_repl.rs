@@ -0,0 +1,144 @@
+use crate::wikimedia_types::Search;
+use crate::{set_article_open, AppState, MenuItem};
+use mlua::{Lua, MultiValue, Table, Value};
+use std::cell::RefCell;
+
+pub struct LuaRepl {
+    lua: Lua,
+    pub input: String,
+    //Entered lines and their results, oldest first
+    pub output: Vec<String>,
+    //Everything tpedia.search returned, so tpedia.open can find the pageids
+    known_articles: Vec<Search>,
+}
+
+impl LuaRepl {
+    pub fn new() -> LuaRepl {
+        LuaRepl {
+            lua: Lua::new(),
+            input: String::new(),
+            output: vec![String::from("Lua REPL, tpedia.search(term), tpedia.open(pageid), tpedia.get_content(), tpedia.set_status(msg)")],
+            known_articles: Vec::new(),
+        }
+    }
+}
+
+fn search_to_table<'lua>(lua: &'lua Lua, search: &Search) -> mlua::Result<Table<'lua>> {
+    let table = lua.create_table()?;
+    table.set("title", search.title.clone())?;
+    table.set("pageid", search.pageid)?;
+    table.set("wordcount", search.wordcount)?;
+    table.set("size", search.size)?;
+    table.set("snippet", search.snippet.clone())?;
+    table.set("timestamp", search.timestamp.clone())?;
+    Ok(table)
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::Nil => String::from("nil"),
+        Value::Boolean(b) => b.to_string(),
+        Value::Integer(i) => i.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.to_string_lossy().into_owned(),
+        Value::Table(table) => {
+            let entries: Vec<String> = table
+                .clone()
+                .pairs::<Value, Value>()
+                .filter_map(Result::ok)
+                .map(|(k, v)| format!("{} = {}", format_value(&k), format_value(&v)))
+                .collect();
+            format!("{{ {} }}", entries.join(", "))
+        }
+        other => other.type_name().to_string(),
+    }
+}
+
+//The tpedia functions borrow the app state for the duration of one line.
+//print writes into the output, stdout is the terminal in raw mode
+fn eval_line(lua: &Lua, known_articles: &mut Vec<Search>, output: &mut Vec<String>, state: &mut AppState, line: &str) -> mlua::Result<String> {
+    let state = RefCell::new(state);
+    let known_articles = RefCell::new(known_articles);
+    let output = RefCell::new(output);
+
+    lua.scope(|scope| {
+        lua.globals().set("print", scope.create_function(|_, values: MultiValue| {
+            output.borrow_mut().push(values.iter().map(format_value).collect::<Vec<String>>().join("\t"));
+            Ok(())
+        })?)?;
+
+        let tpedia = lua.create_table()?;
+
+        tpedia.set("search", scope.create_function(|lua, term: String| {
+            let (client, runtime) = {
+                let state = state.borrow();
+                (state.client.clone(), state.runtime.clone())
+            };
+            let res = runtime.block_on(client.search(&term)).map_err(mlua::Error::external)?;
+
+            let results = lua.create_table()?;
+            for (i, search) in res.query.search.iter().enumerate() {
+                results.set(i + 1, search_to_table(lua, search)?)?;
+            }
+            known_articles.borrow_mut().extend(res.query.search);
+
+            Ok(results)
+        })?)?;
+
+        tpedia.set("open", scope.create_function(|_, pageid: i64| {
+            let mut state = state.borrow_mut();
+            let known = known_articles.borrow();
+
+            let results = if state.current_search_results.iter().any(|s| s.pageid == pageid) {
+                state.current_search_results.clone()
+            } else if known.iter().any(|s| s.pageid == pageid) {
+                known.clone()
+            } else {
+                return Err(mlua::Error::RuntimeError(format!("unknown pageid {}, use tpedia.search first", pageid)));
+            };
+
+            let index = results.iter().position(|s| s.pageid == pageid);
+            state.current_search_results = results;
+            state.search_result_list_state.select(index);
+            state.active_menu_item = MenuItem::Results;
+            set_article_open(&mut state, true);
+            state.current_article = None;
+            state.scroll = 0;
+
+            Ok(())
+        })?)?;
+
+        tpedia.set("get_content", scope.create_function(|_, ()| Ok(state.borrow().current_article.as_ref().map(|a| a.content.clone())))?)?;
+
+        tpedia.set("set_status", scope.create_function(|_, message: String| {
+            state.borrow_mut().status_message = Some(message);
+            Ok(())
+        })?)?;
+
+        lua.globals().set("tpedia", tpedia)?;
+
+        //Expressions are shown with their value, statements are executed as they are.
+        //Only a line that doesn't parse as an expression runs again, others could repeat side effects
+        let values: MultiValue = match lua.load(format!("return {}", line)).eval() {
+            Ok(values) => values,
+            Err(mlua::Error::SyntaxError { .. }) => lua.load(line).eval()?,
+            Err(e) => return Err(e),
+        };
+
+        Ok(values.iter().map(format_value).collect::<Vec<String>>().join("\t"))
+    })
+}
+
+pub fn run_input(repl: &mut LuaRepl, state: &mut AppState) {
+    let line = std::mem::take(&mut repl.input);
+    if line.trim().is_empty() {
+        return;
+    }
+
+    repl.output.push(format!("> {}", line));
+    match eval_line(&repl.lua, &mut repl.known_articles, &mut repl.output, state, &line) {
+        Ok(result) if result.is_empty() => {}
+        Ok(result) => repl.output.push(result),
+        Err(e) => repl.output.push(format!("error: {}", e)),
+    }
+}
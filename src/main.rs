@@ -1,416 +1,4158 @@
 use crossterm::{
-    event::{self, Event as CEvent, KeyCode},
-    terminal::{disable_raw_mode, enable_raw_mode},
+    cursor::MoveTo,
+    event::{self, Event as CEvent, KeyCode, KeyEvent, KeyModifiers},
+    execute,
+    style::Print,
+    terminal::{disable_raw_mode, enable_raw_mode, SetTitle},
 };
-use regex::Regex;
-use wikimedia_types::{HtmlPageResult, Search, SearchResponse};
-use std::{convert::TryInto, fmt::Debug};
-use std::sync::mpsc;
+use analysis::SizeFilter;
+use article::{Backlinks, WikipediaArticle};
+use chrono::{DateTime, Local};
+use client::WikipediaClient;
+use categories::{CategoryNode, TreeRow};
+use compare::CompareView;
+use concept_map::ConceptNode;
+use config::{Config, ConfigError, HyperlinkMode, NewSearchMode, TextAlignment};
+use bookmarks::Bookmark;
+use freshness::Freshness;
+use gallery::GalleryImage;
+use geo_map::GeoMap;
+use history::HistoryEntry;
+use infobox::InfoboxRow;
+use lua_repl::LuaRepl;
+use navigation::{NavigationSnapshot, UndoHistory};
+use palette::{AppAction, CommandPalette};
+use recent::RecentArticle;
+use results_stack::{ResultsKind, ResultsLayer};
+use session::Session;
+use suggestions::Suggestion;
+use snapshot::BufferCapture;
+use wikimedia_types::{Coordinate, Search, SearchResponse};
+use std::{collections::{BTreeMap, HashMap, HashSet, VecDeque}, convert::TryInto, fmt::Debug};
+use std::sync::{mpsc, Arc};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::thread;
+use std::fs;
 use std::io;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, Instant};
 use thiserror::Error;
-use tui::{Terminal, backend::CrosstermBackend, layout::{Alignment, Constraint, Direction, Layout}, style::{Color, Modifier, Style}, text::{Span, Spans}, widgets::{Block, BorderType, Borders, List, ListItem, ListState, Paragraph, Tabs, Wrap}};
+use unicode_width::UnicodeWidthStr;
+use tokio::runtime::Handle;
+use tokio::sync::{oneshot, watch, Mutex};
+use tui::{Frame, Terminal, backend::{Backend, CrosstermBackend}, buffer::Buffer, layout::{Alignment, Constraint, Direction, Layout, Rect}, style::{Color, Modifier, Style}, text::{Span, Spans, Text}, symbols::Marker, widgets::{Block, BorderType, Borders, Clear, List, ListItem, ListState, Paragraph, Tabs, Wrap, canvas::{Canvas, Map, MapResolution}}};
+mod analysis;
+mod annotations;
+mod article;
+mod batch;
+mod bookmarks;
+mod cache;
+mod categories;
+mod client;
+mod clipboard;
+mod compare;
+mod concept_map;
+mod config;
+mod export;
+mod external;
+mod find;
+mod freshness;
+mod gallery;
+mod geo_map;
+mod history;
+mod infobox;
+mod keybindings;
+mod links;
+mod local_index;
+mod lua_repl;
+mod log;
+mod markdown;
+mod navigation;
+mod pagination;
+mod palette;
+mod pins;
+mod recent;
+mod render;
+mod report;
+mod results_stack;
+mod serde_compat;
+mod sections;
+mod session;
+mod snapshot;
+mod spell;
+mod storage;
+mod stub;
+mod suggestions;
+mod util;
 mod wikimedia_types;
 
 
+//What Enter in the error pane or Ctrl+R tries again
+#[derive(Clone)]
+enum RetryAction {
+    Search(String),
+    //pageid of the article in the result list
+    OpenArticle(i64),
+}
+
+//Failure of an action the user is waiting for, shown in place of the content until dismissed
+struct AppError {
+    message: String,
+    //Response that could not be parsed, shown in debug mode
+    raw_response: Option<String>,
+    occurred_at: DateTime<Local>,
+    retry: RetryAction,
+}
+
+impl AppError {
+    fn new(error: &Error, retry: RetryAction) -> AppError {
+        AppError {
+            message: error.to_string(),
+            raw_response: match error {
+                Error::ParseResponseError(_, raw) => Some(raw.clone()),
+                _ => None,
+            },
+            occurred_at: Local::now(),
+            retry,
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum PromptKind {
+    //Ctrl+S, the query is saved under the name
+    PresetName,
+    //'/' in the article, the focus goes back to it afterwards
+    Find,
+    //Ctrl+N in the article, searches Wikipedia for the edited word
+    Search,
+}
+
+//Single line input that temporarily takes over the search box
+struct Prompt {
+    kind: PromptKind,
+    text: String,
+}
+
+struct ReverseSearch {
+    pattern: String,
+    //How many older matches Ctrl+R skipped
+    index: usize,
+    //Restored by Esc
+    original: String,
+}
+
 #[derive(Error, Debug)]
 pub enum Error {
     #[error("error reading the DB file: {0}")]
     ReadDBError(#[from] std::io::Error),
     #[error("error parsing the DB file: {0}")]
     ParseDBError(#[from] serde_json::Error),
+    #[error("network error: {0}")]
+    NetworkError(#[from] reqwest::Error),
+    #[error("can't reach {0}, check the network or api_url in the config")]
+    Unreachable(String),
+    #[error("Unexpected response from server (are you behind a captive portal?)")]
+    UnexpectedResponse,
+    #[error("Wikipedia API error {code}: {info}")]
+    WikiApi { code: String, info: String },
+    //The raw JSON is shown in debug mode
+    #[error("error parsing the API response: {0}")]
+    ParseResponseError(serde_json::Error, String),
+    #[error("external tool failed: {0}")]
+    ExternalToolError(String),
+    #[error("local index error: {0}")]
+    LocalIndexError(String),
+    #[error("error writing the CSV file: {0}")]
+    CsvError(#[from] csv::Error),
 }
 
 //Every User Interaction
+//What the parse HTML is fetched for, both are debug views
+#[derive(Clone, Copy)]
+enum RawHtmlUse {
+    View,
+    Report,
+}
+
 enum Event<I> {
     Input(I),
     Tick,
+    //Progress and results of background work
+    Status(String),
+    //Results of an incremental search and the query they belong to
+    SearchResults(String, SearchResponse),
+    //opensearch titles for the query
+    Suggestions(String, Vec<String>),
+    //Text and infobox of the article with the pageid, converted at the width
+    ArticleFetched(i64, u16, Result<(String, Vec<InfoboxRow>), Error>),
+    //Text of the article with the pageid for a compare reader
+    CompareFetched(i64, Result<String, Error>),
+    //Results of the query submitted with Enter and whether to open the top result
+    SearchFinished(String, bool, Result<SearchResponse, Error>),
+    //Yesterday's most viewed articles with their views
+    TrendingFetched(Result<Vec<(Search, u64)>, Error>),
+    //Next batch of articles linking to the article with the pageid
+    BacklinksFetched(i64, Result<(Vec<String>, Option<String>), Error>),
+    //Categories of the page with the title
+    CategoriesFetched(String, Result<Vec<String>, Error>),
+    //Subcategories and articles of the category at the path in the browser
+    CategoryMembersFetched(Vec<usize>, String, Result<(Vec<String>, Vec<Search>), Error>),
+    //Location of the article with the pageid, None if it has none
+    CoordinatesFetched(i64, Result<Option<Coordinate>, Error>),
+    //Concept map around the article with the title
+    ConceptMapBuilt(String, Result<HashMap<String, Vec<String>>, Error>),
+    //Batch of the query from the offset, loaded with 'm'
+    MoreResultsFetched(String, i64, Result<SearchResponse, Error>),
+    //Page of the query at the offset, loaded with '[' or ']'
+    SearchPageFetched(String, u32, i64, Result<SearchResponse, Error>),
+    //Talk page of the selected article with the pageid
+    TalkPageFetched(i64, Result<Option<Search>, Error>),
+    //Search for the title of an article to open, and the breadcrumb trail it gets
+    TitledArticleFetched(String, Vec<String>, Result<SearchResponse, Error>),
+    //Link titles or image titles of the article with the pageid, with its parse HTML
+    LinksFetched(i64, Result<(Vec<String>, String), Error>),
+    ImagesFetched(i64, Result<(Vec<String>, String), Error>),
+    //Parse HTML of the article with the pageid and what it was fetched for
+    RawHtmlFetched(i64, RawHtmlUse, Result<String, Error>),
+    Resize,
 }
 
 
 //Menu
-#[derive(Copy, Clone, Debug, )]
+#[derive(PartialEq, Eq, Clone, Copy, Debug, Hash)]
 enum MenuItem{
-    Home,Results
+    Home,Results,History
+}
+
+impl MenuItem {
+    //Tab order
+    fn all() -> &'static [MenuItem] {
+        &[MenuItem::Home, MenuItem::Results, MenuItem::History]
+    }
+
+    fn title(self) -> &'static str {
+        match self {
+            MenuItem::Home => "Home",
+            MenuItem::Results => "Results",
+            MenuItem::History => "Past searches",
+        }
+    }
 }
 
+//Index of the tab, the only place variants are mapped to indices
 impl From<MenuItem> for usize {
     fn from(input: MenuItem) -> usize {
         match input {
             MenuItem::Home => 0,
-            MenuItem::Results => 1
+            MenuItem::Results => 1,
+            MenuItem::History => 2,
         }
     }
 }
 
-impl PartialEq for MenuItem {
-    fn eq(&self, other: &MenuItem) -> bool {
-        usize::from(*self) == usize::from(*other)
-    }
+//The pane keyboard input goes to, cycled with Tab (Ctrl+I).
+//ArticleContent and Toc are only focused while an article is open,
+//the Toc replaces the result list while it has the focus
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum FocusedPane {
+    MenuBar, SearchBox, ResultsList, ArticleContent, Toc, StatusBar
 }
 
-impl Eq for MenuItem {}
+struct AppState {
+    config: Config,
+    client: WikipediaClient,
+    //Shared with the input thread so a config reload can change the tick rate
+    tick_ms: Arc<AtomicU64>,
+    //Background jobs are spawned on the runtime the render task runs on
+    runtime: Handle,
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    enable_raw_mode().expect("can run in raw mode");
+    active_menu_item: MenuItem,
+    focus: FocusedPane,
+    search_string: String,
+    //Ctrl+R history search, the search box shows the match
+    reverse_search: Option<ReverseSearch>,
+    prompt: Option<Prompt>,
+    error_state: Option<AppError>,
+    //Kept after the error pane is dismissed, until a search or an article succeeds
+    last_failure: Option<RetryAction>,
+    //Query found with '/', highlighted in the article until another one is opened
+    find: Option<String>,
+    //Words of the search highlighted in the article, 'M' toggles it for the session
+    highlight_terms: bool,
+    //The infobox panel, 'i' toggles it for the session
+    show_infobox: bool,
+    //PageUp and PageDown move by section, 'S' toggles it for the session
+    section_scroll: bool,
+    //Ctrl+A selected the whole query, the next key replaces, copies or deletes it
+    select_all: bool,
+    search_result_list_state: ListState,
+    current_search_results: Vec<Search>,
+    //Pageids of results with a near duplicate snippet, found with Ctrl+D
+    duplicate_results: Vec<i64>,
+    //'P' in the results, listed above the results of every search this session
+    pinned_results: Vec<Search>,
+    //Ctrl+F, hides loaded results outside the size category until the next search
+    size_filter: SizeFilter,
+    //What the current results were found for
+    results_kind: ResultsKind,
+    results_query: String,
+    //Query and offset of the next results, None once all results are loaded
+    search_continuation: Option<(String, i64)>,
+    //Result lists covered by newer searches, the last one is the most recent
+    results_stack: Vec<ResultsLayer>,
+    //Set by the first Enter when new_search is "confirm"
+    confirm_replace: bool,
+    is_selected: bool,
 
+    scroll: u16,
+    //Where the smooth scroll animation is heading
+    scroll_target: Option<u16>,
+    //Characters of the article shown by the typewriter reveal, None shows everything
+    reveal: Option<usize>,
+    //Titles of the articles followed to the current one, oldest first
+    breadcrumbs: Vec<String>,
+    //Where each article was left during this session, by pageid
+    scroll_positions: HashMap<i64, u16>,
+    //Numbers of the folded sections of each article, by pageid
+    collapsed_sections: HashMap<i64, HashSet<usize>>,
+    toc_list_state: ListState,
+    //Set while the raw HTML of the article with this pageid is shown, with the scroll position to return to
+    raw_view: Option<(i64, u16)>,
+    reveal_paused: bool,
+    //Visible lines of the article, the distance of a page jump
+    page_height: u16,
+    //None until the open article was fetched
+    current_article: Option<WikipediaArticle>,
+    //Article picked with 'c' that waits for a second one to compare with
+    compare_first: Option<Search>,
+    compare: Option<CompareView>,
+    //Position of the content title and the url it links to
+    article_link: Option<(u16, u16, String, String)>,
 
-    let mut search_mode = false;
+    markdown_preview: Option<String>,
+    tool_output: Option<String>,
+    concept_map: Option<Vec<ConceptNode>>,
+    //Kept when closed so Lua globals survive until the next F12
+    lua_repl: Option<LuaRepl>,
+    lua_repl_open: bool,
+    concept_map_state: ListState,
+    //World map popup of the open article's location, opened with Ctrl+G
+    geo_map: Option<GeoMap>,
+    //Shared by the full screen overlays, only one of them is open at a time
+    overlay_scroll: u16,
+    status_message: Option<String>,
+    //A brief confirmation and when it is cleared, a newer message stays
+    status_expires: Option<(Instant, String)>,
 
-    let (tx, rx) = mpsc::channel();
-    let tick_rate = Duration::from_millis(200);
+    search_history: Vec<HistoryEntry>,
+    //Selection in the Past searches tab, which lists the newest search first
+    history_list_state: ListState,
+    //Prefix typed by the user and the index of the shown completion
+    completion: Option<(String, usize)>,
+    //Dropping the sender cancels the pending incremental search
+    pending_search: Option<oneshot::Sender<()>>,
+    //Query submitted with Enter, dropping the sender cancels it
+    pending_submit: Option<(String, oneshot::Sender<()>)>,
+    //Title of the article looked up to open it, only the newest lookup opens
+    pending_title: Option<String>,
+    //'[' and ']' page through the results of results_query, search_offset is the sroffset of the page.
+    //search_total_pages is 0 for lists that are not search results
+    search_page: u32,
+    search_offset: i64,
+    search_total_pages: u32,
+    //Ctrl+U goes back to before the last search or opened article, Ctrl+Y forward again
+    undo_history: UndoHistory,
+    //Images of the open article shown in place of everything else
+    gallery_open: bool,
+    gallery_state: ListState,
+    //Links of the open article shown in place of everything else, and whether all are listed
+    links_open: bool,
+    links_show_all: bool,
+    links_state: ListState,
+    //Articles linking to the open one, shown in place of everything else
+    backlinks_open: bool,
+    backlinks_state: ListState,
+    //Category browser of the open article, shown in place of everything else
+    category_tree: Option<Vec<CategoryNode>>,
+    category_state: ListState,
+    //Queries searched in this session, newest first, shown in the Ctrl+O popup
+    recent_queries: VecDeque<String>,
+    recent_queries_open: bool,
+    recent_queries_state: ListState,
+    //Ctrl+K, drawn over everything and gets all keys while open
+    palette: Option<CommandPalette>,
+    //Key of the command run from the palette, handled before the next event
+    replayed_key: Option<KeyEvent>,
+    //Fetch of the opened article and its pageid, dropping the sender cancels it
+    pending_article: Option<(i64, oneshot::Sender<()>)>,
+    //Most viewed articles of yesterday, when they were fetched and their views by pageid
+    trending: Option<(Instant, Vec<Search>, HashMap<i64, u64>)>,
+    //Background fetch of the top result and its pageid, dropping the sender cancels it
+    prefetch: Option<(i64, oneshot::Sender<()>)>,
+    //Dropdown below the search box and the highlighted entry
+    suggestions: Vec<Suggestion>,
+    suggestion_index: Option<usize>,
+    //Last opensearch titles, kept until newer ones arrive so the dropdown doesn't flicker
+    api_suggestions: Vec<String>,
+    pending_suggestions: Option<oneshot::Sender<()>>,
+    //Corrected query offered before searching, accepted with 'y'
+    spelling_suggestion: Option<String>,
+
+    recent_articles: Vec<RecentArticle>,
+    recent_list_state: ListState,
+    //The Home screen's keys go to the presets instead of the recently viewed articles
+    presets_focused: bool,
+    preset_list_state: ListState,
+    //Preset waiting for a second 'X' to be deleted
+    confirm_delete_preset: Option<String>,
+    bookmarks: Vec<Bookmark>,
+
+    snapshot_requested: bool,
+    snapshot: Option<Buffer>,
+    //URL opened in the browser once it was shown in the footer for a moment
+    pending_open: Option<(String, Instant)>,
+    //The window is restored on exit if F11 maximized it
+    fullscreen: bool,
+    //Start of the last resize burst, the draw holds back refetching while it lasts
+    last_resize: Option<Instant>,
+}
+
+impl AppState {
+    //Nothing loaded yet, main fills in the session and the saved lists
+    fn new(config: Config, client: WikipediaClient, tick_ms: Arc<AtomicU64>, runtime: Handle) -> AppState {
+        let highlight_terms = config.highlight_search_terms;
+        let show_infobox = config.infobox;
+        let section_scroll = config.section_scroll;
+
+        AppState {
+            config,
+            client,
+            tick_ms,
+            runtime,
+            active_menu_item: MenuItem::Home,
+            focus: FocusedPane::ResultsList,
+            search_string: String::new(),
+            highlight_terms,
+            show_infobox,
+            section_scroll,
+            select_all: false,
+            reverse_search: None,
+            prompt: None,
+            error_state: None,
+            last_failure: None,
+            find: None,
+            search_result_list_state: ListState::default(),
+            current_search_results: Vec::new(),
+            duplicate_results: Vec::new(),
+            pinned_results: Vec::new(),
+            size_filter: SizeFilter::All,
+            results_kind: ResultsKind::Search,
+            results_query: String::new(),
+            search_continuation: None,
+            results_stack: Vec::new(),
+            confirm_replace: false,
+            is_selected: false,
+            scroll: 0,
+            scroll_target: None,
+            reveal: None,
+            breadcrumbs: Vec::new(),
+            scroll_positions: HashMap::new(),
+            collapsed_sections: HashMap::new(),
+            toc_list_state: ListState::default(),
+            raw_view: None,
+            reveal_paused: false,
+            page_height: 0,
+            current_article: None,
+            compare_first: None,
+            compare: None,
+            article_link: None,
+            markdown_preview: None,
+            tool_output: None,
+            concept_map: None,
+            lua_repl: None,
+            lua_repl_open: false,
+            concept_map_state: ListState::default(),
+            geo_map: None,
+            overlay_scroll: 0,
+            status_message: None,
+            status_expires: None,
+            search_history: Vec::new(),
+            history_list_state: ListState::default(),
+            completion: None,
+            pending_search: None,
+            pending_submit: None,
+            pending_title: None,
+            search_page: 0,
+            search_offset: 0,
+            search_total_pages: 0,
+            undo_history: UndoHistory::default(),
+            gallery_open: false,
+            gallery_state: ListState::default(),
+            links_open: false,
+            links_show_all: false,
+            links_state: ListState::default(),
+            backlinks_open: false,
+            backlinks_state: ListState::default(),
+            category_tree: None,
+            recent_queries: VecDeque::new(),
+            recent_queries_open: false,
+            recent_queries_state: ListState::default(),
+            category_state: ListState::default(),
+            palette: None,
+            pending_article: None,
+            trending: None,
+            replayed_key: None,
+            prefetch: None,
+            suggestions: Vec::new(),
+            suggestion_index: None,
+            api_suggestions: Vec::new(),
+            pending_suggestions: None,
+            spelling_suggestion: None,
+            recent_articles: Vec::new(),
+            recent_list_state: ListState::default(),
+            presets_focused: false,
+            preset_list_state: ListState::default(),
+            confirm_delete_preset: None,
+            bookmarks: Vec::new(),
+            snapshot_requested: false,
+            snapshot: None,
+            pending_open: None,
+            fullscreen: false,
+            last_resize: None,
+        }
+    }
+}
+
+//How often the main loop checks that the input thread is still alive
+const INPUT_WATCHDOG_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_INPUT_RESTARTS: u32 = 3;
+//The countdown before the idle timeout quits is shown this long
+const IDLE_WARNING: Duration = Duration::from_secs(10);
+//How long a URL is shown in the footer before the browser opens it
+const OPEN_DELAY: Duration = Duration::from_secs(3);
+//Resize events closer together than this belong to one resize
+const RESIZE_BURST: Duration = Duration::from_millis(500);
+//Queries kept for the Ctrl+O popup
+const RECENT_QUERIES: usize = 20;
+//How long confirmations stay in the footer
+const BRIEF_STATUS: Duration = Duration::from_secs(1);
+//Names of the result lists that are not searches
+const RECENT_LIST: &str = "Recently viewed";
+const TRENDING_LIST: &str = "Trending";
+const TRENDING_ARTICLES: usize = 50;
+//The list of a day doesn't change once it is complete, but the day does
+const TRENDING_MAX_AGE: Duration = Duration::from_secs(60 * 60);
+//Longer responses would push the retry hint off the screen
+const MAX_RAW_RESPONSE_CHARS: usize = 2000;
+//Size of the Ctrl+K popup
+const PALETTE_WIDTH: u16 = 70;
+const PALETTE_HEIGHT: u16 = 20;
+//30 frames a second, the render task draws at most once per interval
+const FRAME_INTERVAL: Duration = Duration::from_millis(1000 / 30);
+
+//Reads keys and sends ticks until the terminal fails or the receiver is gone,
+//a failure is logged since the TUI can't print it
+fn spawn_input_thread(tx: mpsc::Sender<Event<KeyEvent>>, tick_ms: Arc<AtomicU64>) -> thread::JoinHandle<()> {
     thread::spawn(move || {
         let mut last_tick = Instant::now();
         loop {
+            let tick_rate = Duration::from_millis(tick_ms.load(Ordering::Relaxed));
             let timeout = tick_rate
                 .checked_sub(last_tick.elapsed())
                 .unwrap_or_else(|| Duration::from_secs(0));
 
-            if event::poll(timeout).expect("poll works") {
-
-                if let CEvent::Key(key) = event::read().expect("can read events") {
-                    tx.send(Event::Input(key)).expect("can send events");
+            match event::poll(timeout) {
+                Ok(true) => match event::read() {
+                    Ok(CEvent::Key(key)) => {
+                        if tx.send(Event::Input(key)).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(CEvent::Resize(..)) => {
+                        if tx.send(Event::Resize).is_err() {
+                            return;
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        log::write_log(&format!("Reading terminal events failed: {}", e));
+                        return;
+                    }
+                },
+                Ok(false) => {}
+                Err(e) => {
+                    log::write_log(&format!("Polling terminal events failed: {}", e));
+                    return;
                 }
             }
 
             if last_tick.elapsed() >= tick_rate {
-                if let Ok(_) = tx.send(Event::Tick) {
-                    last_tick = Instant::now();
+                if tx.send(Event::Tick).is_err() {
+                    return;
                 }
+                last_tick = Instant::now();
             }
         }
-    });
+    })
+}
+
+//Quitting shouldn't be blocked by a session or config that can't be saved
+fn save_on_exit(state: &AppState, initial_styled_headings: bool) {
+    let session = Session {
+        results_kind: state.results_kind,
+        results_query: state.results_query.clone(),
+        results: state.current_search_results.clone(),
+        search_result_list_state: (&state.search_result_list_state).into(),
+        recent_list_state: (&state.recent_list_state).into(),
+    };
+    let _ = session::save_session(&session);
+    if state.config.styled_headings != initial_styled_headings {
+        let _ = config::save_config(&state.config, &["styled_headings"]);
+    }
+}
+
+//Names the window after the open article or the search, so it can be found in the taskbar or tmux.
+//shown is the title set last, the terminal is only written to when it changes
+fn update_terminal_title(state: &AppState, shown: &mut String) {
+    let selected = safe_get_selected(&state.current_search_results, &state.search_result_list_state);
+    let title = match (state.active_menu_item, selected) {
+        (MenuItem::Results, Some(article)) if state.is_selected => format!("Tpedia — {}", article.title),
+        (MenuItem::Results, _) if !state.results_query.is_empty() => format!("Tpedia — Search: {}", state.results_query),
+        _ => String::from("Tpedia"),
+    };
+
+    if title != *shown {
+        let _ = execute!(io::stdout(), SetTitle(&title));
+        *shown = title;
+    }
+}
+
+//Raw mode turns Ctrl+Z into a key instead of SIGTSTP, so the shell's terminal is restored
+//by hand before stopping. Returns once the shell continues the process with fg
+#[cfg(unix)]
+fn suspend(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<(), Box<dyn std::error::Error>> {
+    disable_raw_mode()?;
+    terminal.show_cursor()?;
+
+    unsafe {
+        libc::raise(libc::SIGSTOP);
+    }
+
+    enable_raw_mode()?;
+    terminal.clear()?;
+
+    Ok(())
+}
+
+fn restore_terminal(screen: &mut Screen, fullscreen: bool) -> Result<(), Box<dyn std::error::Error>> {
+    screen.restored = true;
+    let terminal = &mut screen.terminal;
+    if fullscreen {
+        execute!(terminal.backend_mut(), Print(util::window_state_sequence(false)))?;
+    }
+    //An empty title lets the terminal show its own again
+    execute!(terminal.backend_mut(), SetTitle(""))?;
+    disable_raw_mode()?;
+    terminal.show_cursor()?;
+
+    Ok(())
+}
+
+//The terminal is shared by the event loop and the render task. Once it is restored
+//for the shell, the render task stops so nothing is drawn over the shell
+struct Screen {
+    terminal: Terminal<CrosstermBackend<io::Stdout>>,
+    restored: bool,
+}
+
+//Draws whenever the event loop signals a change, at most once per FRAME_INTERVAL.
+//The state is locked before the screen, in the same order as the event loop
+async fn render_loop(shared_state: Arc<Mutex<AppState>>, screen: Arc<Mutex<Screen>>, mut changed: watch::Receiver<()>, menu_titles: Vec<&'static str>, worker_tx: mpsc::Sender<Event<KeyEvent>>) {
+    let mut terminal_title = String::new();
+
+    while changed.changed().await.is_ok() {
+        let next_frame = tokio::time::Instant::now() + FRAME_INTERVAL;
+        {
+            let mut state = shared_state.lock().await;
+            let mut screen = screen.lock().await;
+            if screen.restored {
+                return;
+            }
+            if let Err(e) = draw(&mut screen.terminal, &mut state, &menu_titles, &worker_tx, &mut terminal_title) {
+                log::write_log(&format!("Drawing failed: {}", e));
+                return;
+            }
+        }
+        tokio::time::sleep_until(next_frame).await;
+    }
+}
+
+fn draw(terminal: &mut Terminal<CrosstermBackend<io::Stdout>>, state: &mut AppState, menu_titles: &[&str], worker_tx: &mpsc::Sender<Event<KeyEvent>>, terminal_title: &mut String) -> Result<(), Box<dyn std::error::Error>> {
+    update_terminal_title(state, terminal_title);
+    terminal.draw(|rect| draw_frame(rect, state, menu_titles, worker_tx))?;
+
+    if let Some(buffer) = state.snapshot.take() {
+        state.status_message = match snapshot::save_snapshot(&buffer) {
+            Ok(path) => Some(format!("Screen saved to {}", path.display())),
+            Err(e) => Some(format!("Saving the screen failed: {}", e)),
+        };
+    }
+
+    let hyperlinks = match state.config.hyperlinks {
+        HyperlinkMode::Off => false,
+        HyperlinkMode::Detect => util::terminal_supports_hyperlinks(),
+        HyperlinkMode::On => true,
+    };
+
+    //tui can't hold escape sequences in its buffer, so the link is written over the rendered title
+    if let (true, Some((x, y, title, url))) = (hyperlinks, &state.article_link) {
+        execute!(terminal.backend_mut(), MoveTo(*x, *y), Print(util::osc8_link(url, title)))?;
+    }
+
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = config::load_config()?;
+
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("index") {
+        return run_index_command(&args[2..], &config);
+    }
+    if args.iter().any(|a| a == "--batch") {
+        return run_batch_command(&args[1..], &config);
+    }
+
+    let client = WikipediaClient::new(&config)?;
+    client.evict_cache();
+    //The config file is only rewritten on exit if Ctrl+B changed the setting
+    let initial_styled_headings = config.styled_headings;
+
+    //Background jobs and the render task share one runtime
+    let runtime = tokio::runtime::Runtime::new()?;
+
+    enable_raw_mode().expect("can run in raw mode");
+
+    let (tx, rx) = mpsc::channel();
+    let worker_tx = tx.clone();
+    let tick_ms = Arc::new(AtomicU64::new(config.tick_ms));
+    let mut input_thread = spawn_input_thread(tx.clone(), Arc::clone(&tick_ms));
+    let mut input_restarts = 0;
 
     let stdout = io::stdout();
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
     terminal.clear()?;
 
-    let menu_titles = vec!["Home", "Results", "Quit"];
-    let mut active_menu_item = MenuItem::Home;
-
-    let mut search_string: String = String::new();
-    let mut search_result_list_state = ListState::default();
-    search_result_list_state.select(Some(0));
+    //Quit is only a hint for the 'q' key, not a tab that can be active
+    let menu_titles: Vec<&str> = MenuItem::all().iter().map(|m| m.title()).chain(["Quit"]).collect();
 
-    // let mut current_search_response: SearchResponse;
-    let mut current_search_results: Vec<Search> = Vec::new();
-    let mut is_selected = false;
-
-    let mut scroll: u16 = 0;
-    let mut current_content: Option<String> = None;
+    let (session, status_message) = match session::load_session() {
+        Ok(session) => (session, None),
+        Err(e) => (Session::default(), Some(e.to_string())),
+    };
 
-    loop {
-        terminal.draw(|rect| {
+    let mut search_result_list_state: ListState = session.search_result_list_state.into();
+    if search_result_list_state.selected().is_none() {
+        search_result_list_state.select(Some(0));
+    }
 
-            let size = rect.size();
-            let chunks = Layout::default()
-                .direction(Direction::Vertical)
-                .margin(2)
-                .constraints(
-                    [
-                        Constraint::Length(3),
-                        Constraint::Min(2),
-                        Constraint::Length(3),
-                    ]
-                    .as_ref(),
-                )
-                .split(size);
-
-            let copyright = Paragraph::new("by Lucas Engleder")
-                .style(Style::default().fg(Color::LightCyan))
-                .alignment(Alignment::Center)
-                .block(
-                    Block::default()
-                        .borders(Borders::ALL)
-                        .style(Style::default().fg(Color::White))
-                        .border_type(BorderType::Plain),
-                );
-
-            let menu = menu_titles
-                .iter()
-                .map(|t| {
-                    let (first, rest) = t.split_at(1);
-                    Spans::from(vec![
-                        Span::styled(
-                            first,
-                            Style::default()
-                                .fg(Color::Yellow)
-                                .add_modifier(Modifier::UNDERLINED),
-                        ),
-                        Span::styled(rest, Style::default().fg(Color::White)),
-                    ])
-                })
-                .collect();
+    let mut recent_list_state: ListState = session.recent_list_state.into();
+    if recent_list_state.selected().is_none() {
+        recent_list_state.select(Some(0));
+    }
 
-            { 
-                let tabs = Tabs::new(menu)
-                    .select(active_menu_item.into())
-                    .block(Block::default().title("Menu").borders(Borders::ALL))
-                    .style(Style::default().fg(Color::White))
-                    .highlight_style(Style::default().fg(Color::Yellow))
-                    .divider(Span::raw("|"));
-
-                let search_box = Block::default() 
-                    .borders(Borders::ALL)
-                    .style(Style::default().fg(Color::Yellow))
-                    .border_type(BorderType::Plain);
-
-                let search_text = Paragraph::new(format!("{}{}"," 🔍 ", search_string.clone()))
-                    .block(search_box)
-                    .style(Style::default()
-                    .fg(Color::Yellow));
-
-                let navbar = Layout::default()
-                    .direction(Direction::Horizontal)
-                    .constraints(
-                        [Constraint::Percentage(70), Constraint::Percentage(30)].as_ref(),
-                    )
-                    .split(chunks[0]);
+    let (recent_articles, status_message) = match recent::load_recent() {
+        Ok(recent) => (recent, status_message),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+    let (bookmarks, status_message) = match bookmarks::load_bookmarks() {
+        Ok(bookmarks) => (bookmarks, status_message),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
+    let (search_history, status_message) = match history::load_history() {
+        Ok(history) => (history, status_message),
+        Err(e) => (Vec::new(), Some(e.to_string())),
+    };
 
+    let mut state = AppState::new(config, client, tick_ms, runtime.handle().clone());
+    state.search_result_list_state = search_result_list_state;
+    state.current_search_results = session.results;
+    state.results_kind = session.results_kind;
+    state.results_query = session.results_query;
+    state.status_message = status_message;
+    state.search_history = search_history;
+    state.recent_articles = recent_articles;
+    state.recent_list_state = recent_list_state;
+    state.bookmarks = bookmarks;
 
-                rect.render_widget(tabs, navbar[0]);
+    let mut last_input = Instant::now();
+    let mut known_size = terminal.size()?;
+    let mut idle_warning_shown = false;
 
-                rect.render_widget(search_text, navbar[1]);   
-            }
+    let shared_state = Arc::new(Mutex::new(state));
+    let screen = Arc::new(Mutex::new(Screen { terminal, restored: false }));
+    let (redraw_tx, redraw_rx) = watch::channel(());
+    runtime.spawn(render_loop(Arc::clone(&shared_state), Arc::clone(&screen), redraw_rx, menu_titles, worker_tx.clone()));
 
-            //Content Page, depends on which tab
-            match active_menu_item {
-                MenuItem::Home => rect.render_widget(render_home(), chunks[1]),
-                MenuItem::Results => {
-                    let results_chunks = Layout::default()
-                        .direction(Direction::Horizontal)
-                        .constraints(
-                            [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
-                        )
-                        .split(chunks[1]);
+    loop {
+        //Every pass asks for a frame, also the ones that continue early
+        let _ = redraw_tx.send(());
 
+        //The render task dropped its receiver, without it nothing would be drawn anymore
+        if redraw_tx.is_closed() {
+            let fullscreen = shared_state.blocking_lock().fullscreen;
+            restore_terminal(&mut screen.blocking_lock(), fullscreen)?;
+            return Err("the render task stopped, see tpedia.log".into());
+        }
 
-                    let list = render_search_list(current_search_results.clone());
-                    rect.render_stateful_widget(list, results_chunks[0], &mut search_result_list_state);
+        //Without the input thread there are no keys and no ticks, so it is restarted a few times
+        if input_thread.is_finished() {
+            if input_restarts == MAX_INPUT_RESTARTS {
+                log::write_log("The input thread stopped repeatedly, exiting");
+                let fullscreen = shared_state.blocking_lock().fullscreen;
+                restore_terminal(&mut screen.blocking_lock(), fullscreen)?;
+                return Err("the input thread stopped repeatedly, see tpedia.log".into());
+            }
 
-                    if is_selected {
-                        let selected_item = get_selected_search(current_search_results.clone(), &mut search_result_list_state);
+            input_restarts += 1;
+            log::write_log(&format!("The input thread stopped, restarting it ({}/{})", input_restarts, MAX_INPUT_RESTARTS));
+            let tick_ms = Arc::clone(&shared_state.blocking_lock().tick_ms);
+            input_thread = spawn_input_thread(tx.clone(), tick_ms);
+        }
 
-                        let res  = render_page_content(selected_item.clone(), current_content.clone(), scroll,(size.width as f64 * 0.8).floor() as u16);
-                        let page = res.0;
-                        current_content = Some(res.1);
-                        rect.render_widget(page, results_chunks[1]);
-                    }
-                }
+        let replayed_key = shared_state.blocking_lock().replayed_key.take();
+        let received = match replayed_key {
+            Some(key) => Ok(Event::Input(key)),
+            None => rx.recv_timeout(INPUT_WATCHDOG_INTERVAL),
+        };
+        let event = match received {
+            Ok(event) => event,
+            Err(mpsc::RecvTimeoutError::Timeout) => continue,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                log::write_log("The event channel disconnected, exiting");
+                let fullscreen = shared_state.blocking_lock().fullscreen;
+                restore_terminal(&mut screen.blocking_lock(), fullscreen)?;
+                return Ok(());
             }
+        };
 
-            //Footer
-            rect.render_widget(copyright, chunks[2]);
-        })?;
+        //Held while the event is handled, the render task draws in between events
+        let mut guard = shared_state.blocking_lock();
+        let state: &mut AppState = &mut guard;
+        if let Event::Input(_) = event {
+            last_input = Instant::now();
+            if idle_warning_shown {
+                idle_warning_shown = false;
+                state.status_message = None;
+            }
+        }
 
-        match rx.recv()? {
+        match event {
             Event::Input(event) => {
-                if search_mode {
+                //Clears artifacts left by other programs or a laggy connection, the draw repaints everything
+                if event.code == KeyCode::Char('l') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                    screen.blocking_lock().terminal.clear()?;
+                    continue;
+                }
+                //The search box has its own Ctrl+K
+                if state.focus != FocusedPane::SearchBox && event.code == KeyCode::Char('k') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                    state.palette = Some(CommandPalette::new());
+                    continue;
+                }
+                if let Some(palette) = state.palette.as_mut() {
                     match event.code {
-                        KeyCode::Char(c) => {
-                            search_string.push(c);
-                        }
-                        KeyCode::Backspace => {
-                            search_string.pop();
-                        }
                         KeyCode::Enter => {
-                            let rt = tokio::runtime::Runtime::new().unwrap();
-                        
-                            let res = rt.block_on(search(search_string.clone())).unwrap();
-
-                            current_search_results = res.query.search;
-                            search_mode = false;
-                            active_menu_item = MenuItem::Results;
+                            let action = palette.selected();
+                            state.palette = None;
 
-                            is_selected = false;
-                            search_result_list_state.select(Some(0));
+                            if let Some(action) = action {
+                                run_palette_action(state, action);
+                            }
                         }
-                        KeyCode::Esc => search_mode = false, 
+                        KeyCode::Esc => state.palette = None,
+                        KeyCode::Down => palette.move_selection(true),
+                        KeyCode::Up => palette.move_selection(false),
+                        KeyCode::Backspace => palette.backspace(),
+                        KeyCode::Char(c) if !event.modifiers.intersects(KeyModifiers::CONTROL | KeyModifiers::ALT) => palette.type_char(c),
                         _ => {}
                     }
-                } 
-                else if is_selected {
+                    continue;
+                }
+                if state.focus != FocusedPane::SearchBox && event.modifiers.contains(KeyModifiers::CONTROL) && matches!(event.code, KeyCode::Char('u') | KeyCode::Char('y')) {
+                    undo_navigation(state, if event.code == KeyCode::Char('u') { AppAction::Undo } else { AppAction::Redo });
+                    continue;
+                }
+                #[cfg(unix)]
+                if event.code == KeyCode::Char('z') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                    suspend(&mut screen.blocking_lock().terminal)?;
+                    continue;
+                }
+
+                if state.focus != FocusedPane::SearchBox && event.code == KeyCode::Char('o') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                    open_recent_queries(state);
+                    continue;
+                }
+                if state.recent_queries_open {
+                    recent_queries_key(state, event.code, &worker_tx);
+                    continue;
+                }
+
+                //Also after the error was dismissed, the search box has its own Ctrl+R
+                if state.focus != FocusedPane::SearchBox && event.code == KeyCode::Char('r') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                    state.error_state = None;
+                    match state.last_failure.clone() {
+                        Some(action) => retry(state, action, &worker_tx),
+                        None => state.status_message = Some(String::from("Nothing failed that could be retried")),
+                    }
+                    continue;
+                }
+
+                if let Some(error) = state.error_state.take() {
+                    //Quitting and the global function keys work with the error shown, no pane binds them
+                    let global = matches!(event.code, KeyCode::F(2) | KeyCode::F(5) | KeyCode::F(6) | KeyCode::F(10) | KeyCode::F(11) | KeyCode::F(12))
+                        || ((event.code == KeyCode::Char('q') || is_bug_report_key(&event)) && state.focus != FocusedPane::SearchBox);
+
+                    match (event.code, error.retry) {
+                        (KeyCode::Enter, action) => retry(state, action, &worker_tx),
+                        (KeyCode::Esc, _) => {}
+                        (_, retry) => state.error_state = Some(AppError { retry, ..error }),
+                    }
+                    if !global {
+                        continue;
+                    }
+                }
+
+                if state.markdown_preview.is_some() {
                     match event.code {
-                        KeyCode::Esc => {
-                            is_selected = false;
-                        }
-                        KeyCode::Down => {
-                            scroll += 1;
-                        }
-                        KeyCode::Up => {
-                            if scroll > 0 {
-                                scroll -= 1;
+                        KeyCode::Char('w') => {
+                            let markdown = state.markdown_preview.take().unwrap_or_default();
+
+                            if let Some(selected_item) = safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+                                state.status_message = match markdown::write_markdown(&selected_item.title, &markdown) {
+                                    Ok(path) => Some(format!("Exported to {}", path.display())),
+                                    Err(e) => Some(format!("Export failed: {}", e)),
+                                };
                             }
                         }
+                        KeyCode::Esc => state.markdown_preview = None,
+                        KeyCode::Down => state.overlay_scroll += 1,
+                        KeyCode::Up => state.overlay_scroll = state.overlay_scroll.saturating_sub(1),
                         _ => {}
                     }
-                } 
-                else if  active_menu_item == MenuItem::Results {
+                    continue;
+                }
+
+                if state.tool_output.is_some() {
+                    match event.code {
+                        KeyCode::Esc => state.tool_output = None,
+                        KeyCode::Down => state.overlay_scroll += 1,
+                        KeyCode::Up => state.overlay_scroll = state.overlay_scroll.saturating_sub(1),
+                        _ => {}
+                    }
+                    continue;
+                }
+
+                if let (true, Some(images)) = (state.gallery_open, state.current_article.as_ref().and_then(|a| a.images.as_ref())) {
+                    let amount = images.len();
+                    let selected = state.gallery_state.selected().unwrap_or(0);
+
                     match event.code {
                         KeyCode::Enter => {
-                            is_selected = true;
-                            current_content = None;
-                            scroll = 0;
-                        },
-                        KeyCode::Down => {
-                            if let Some(selected) = search_result_list_state.selected() {
-                                let amount_results = current_search_results.len();
-                                if selected >= amount_results - 1 && amount_results != 0 {
-                                    search_result_list_state.select(Some(0));
-                                } else if amount_results != 0 {
-                                    search_result_list_state.select(Some(selected + 1));
-                                }
-                            }
-                        }
-                        KeyCode::Up => {
-                            if let Some(selected) = search_result_list_state.selected() {
-                                let amount_results = current_search_results.len();
-                            
-                                if selected > 0 &&  amount_results != 0 {
-                                    search_result_list_state.select(Some(selected - 1));
-                                } else if  amount_results != 0  {
-                                    search_result_list_state.select(Some(amount_results - 1));
-                                }
+                            if let Some(image) = images.get(selected) {
+                                //Special:FilePath redirects to the file itself instead of its description page
+                                let name = image.title.split_once(':').map_or(image.title.as_str(), |(_, name)| name);
+                                let url = state.config.article_url(&format!("Special:FilePath/{}", name));
+                                state.status_message = match external::open_url(&url) {
+                                    Ok(()) => Some(format!("Opening {}", image.title)),
+                                    Err(e) => Some(e.to_string()),
+                                };
                             }
                         }
+                        KeyCode::Esc => state.gallery_open = false,
+                        KeyCode::Down => state.gallery_state.select(Some((selected + 1) % amount)),
+                        KeyCode::Up => state.gallery_state.select(Some((selected + amount - 1) % amount)),
                         _ => {}
                     }
+                    continue;
                 }
 
-                if !search_mode {
+                if let (true, Some(all_links)) = (state.links_open, state.current_article.as_ref().and_then(|a| a.links.as_ref())) {
+                    let shown = links::visible_links(all_links, state.config.max_links, state.links_show_all);
+                    let amount = shown.len();
+                    let selected = state.links_state.selected().unwrap_or(0);
+
                     match event.code {
-                        KeyCode::Char('q') => {
-                            disable_raw_mode()?;
-                            terminal.show_cursor()?;
-                            break;
+                        KeyCode::Enter => {
+                            let title = shown.get(selected).cloned();
+                            state.links_open = false;
+
+                            if let Some(title) = title {
+                                open_article_by_title(state, &title, &worker_tx);
+                            }
                         }
-                        KeyCode::Char('h') => active_menu_item = MenuItem::Home,
-                        KeyCode::Char('r') => active_menu_item = MenuItem::Results,
-                        KeyCode::Char('s') => {
-                            search_mode = true;
-                        },
+                        KeyCode::Char('a') => state.links_show_all = !state.links_show_all,
+                        KeyCode::Esc => state.links_open = false,
+                        KeyCode::Down => state.links_state.select(Some((selected + 1) % amount)),
+                        KeyCode::Up => state.links_state.select(Some((selected + amount - 1) % amount)),
                         _ => {}
                     }
-                } 
-            },
-            Event::Tick => {}
-        }
-    }
+                    continue;
+                }
 
-    Ok(())
-}
+                if let Some(tree) = &state.category_tree {
+                    let rows = categories::rows(tree);
+                    let selected = state.category_state.selected().unwrap_or(0).min(rows.len().saturating_sub(1));
 
-async fn fetch_html(pageid: usize, text_width: u16) -> Result<String, Box<dyn std::error::Error>> {
+                    match (event.code, rows.get(selected)) {
+                        (KeyCode::Enter, Some(TreeRow::Member(path, index))) => {
+                            let title = categories::node(tree, path).and_then(|n| n.members.get(*index)).map(|m| m.title.clone());
+                            state.category_tree = None;
 
-    let url = format!("https://en.wikipedia.org/w/api.php?action=parse&format=json&pageid={0}&prop=text&formatversion=2", pageid);
+                            if let Some(title) = title {
+                                open_article_by_title(state, &title, &worker_tx);
+                            }
+                        }
+                        (KeyCode::Enter, Some(TreeRow::Category(path))) | (KeyCode::Right, Some(TreeRow::Category(path))) => {
+                            let path = path.clone();
+                            let collapse = event.code == KeyCode::Enter && categories::node(tree, &path).is_some_and(|n| n.expanded);
+                            if collapse {
+                                set_category_expanded(state, &path, false);
+                            } else {
+                                expand_category(state, &path, &worker_tx);
+                            }
+                        }
+                        (KeyCode::Left, Some(row)) => {
+                            let expanded_category = match row {
+                                TreeRow::Category(path) if categories::node(tree, path).is_some_and(|n| n.expanded) => Some(path.clone()),
+                                _ => None,
+                            };
+                            match (expanded_category, row.parent()) {
+                                (Some(path), _) => set_category_expanded(state, &path, false),
+                                (None, Some(parent)) => {
+                                    let parent = TreeRow::Category(parent.to_vec());
+                                    state.category_state.select(rows.iter().position(|r| *r == parent));
+                                }
+                                (None, None) => {
+                                    let title = categories::node(tree, &[selected_root(&rows, selected)]).map(|n| n.title.clone());
+                                    if let Some(title) = title {
+                                        show_parent_categories(state, &title, &worker_tx);
+                                    }
+                                }
+                            }
+                        }
+                        (KeyCode::Esc, _) => state.category_tree = None,
+                        (KeyCode::Down, _) if !rows.is_empty() => state.category_state.select(Some((selected + 1) % rows.len())),
+                        (KeyCode::Up, _) if !rows.is_empty() => state.category_state.select(Some((selected + rows.len() - 1) % rows.len())),
+                        _ => {}
+                    }
+                    continue;
+                }
 
-    let resp = reqwest::get(&url)
-        .await?    
-        .json::<serde_json::Value>()        
-        .await?;
+                if let (true, Some(backlinks)) = (state.backlinks_open, state.current_article.as_ref().and_then(|a| a.backlinks.as_ref())) {
+                    let amount = backlinks.links.len();
+                    let selected = state.backlinks_state.selected().unwrap_or(0);
 
-    let page_res: HtmlPageResult = serde_json::from_value(resp).unwrap();
+                    match event.code {
+                        KeyCode::Enter => {
+                            let title = backlinks.links.get(selected).cloned();
+                            state.backlinks_open = false;
 
-    let html_regex = Regex::new(r#"<a href=\\#".*\\#">"#).unwrap();
-    let html_cleaned = html_regex.replace_all(&page_res.parse.text, "");
+                            if let Some(title) = title {
+                                open_article_by_title(state, &title, &worker_tx);
+                            }
+                        }
+                        KeyCode::Char('m') => load_backlinks(state, &worker_tx),
+                        KeyCode::Esc => state.backlinks_open = false,
+                        KeyCode::Down => state.backlinks_state.select(Some((selected + 1) % amount)),
+                        KeyCode::Up => state.backlinks_state.select(Some((selected + amount - 1) % amount)),
+                        _ => {}
+                    }
+                    continue;
+                }
 
-    let text = html2text::from_read( String::from(html_cleaned).as_bytes(), text_width.into());
+                if let Some(nodes) = &state.concept_map {
+                    let amount = nodes.len();
+                    let selected = state.concept_map_state.selected().unwrap_or(0);
 
-    let re = Regex::new(r"(\[)+\d*(\])|(edit)+|\[|\]|(https:)?(/.*/.*)+[\s\S]|#+\s\W").unwrap();
-    //only Numbers (\[)+\d*(\])+
-    let cleaned = re.replace_all(&text, "");
-    let a = Regex::new(r"\d\s").unwrap();
-    let removed_single_digit = a.replace_all(&cleaned, "");
+                    match event.code {
+                        KeyCode::Enter => {
+                            let title = nodes.get(selected).map(|n| n.title.clone());
+                            state.concept_map = None;
 
-    let mut removed_contents: String = String::from(removed_single_digit);
+                            if let Some(title) = title {
+                                open_article_by_title(state, &title, &worker_tx);
+                            }
+                        }
+                        KeyCode::Esc => state.concept_map = None,
+                        KeyCode::Down => state.concept_map_state.select(Some((selected + 1) % amount)),
+                        KeyCode::Up => state.concept_map_state.select(Some((selected + amount - 1) % amount)),
+                        _ => {}
+                    }
+                    continue;
+                }
 
-    let contents_start = removed_contents.find("## Contents");
-    match contents_start {
-        None => {}
-        Some(i) => {
-            let end_index = removed_contents[(i+11)..].find("## ").unwrap();
+                if let Some(map) = &mut state.geo_map {
+                    match event.code {
+                        KeyCode::Esc => state.geo_map = None,
+                        KeyCode::Left => map.pan_step(-1.0, 0.0),
+                        KeyCode::Right => map.pan_step(1.0, 0.0),
+                        KeyCode::Up => map.pan_step(0.0, 1.0),
+                        KeyCode::Down => map.pan_step(0.0, -1.0),
+                        _ => {}
+                    }
+                    continue;
+                }
 
-            removed_contents = format!("{}{}", removed_contents[..i].to_string(), removed_contents[(end_index+11+i)..].to_string());
-        }
-    }
+                if state.lua_repl_open {
+                    if let Some(mut repl) = state.lua_repl.take() {
+                        match event.code {
+                            KeyCode::Char(c) => repl.input.push(c),
+                            KeyCode::Backspace => {
+                                repl.input.pop();
+                            }
+                            KeyCode::Enter => lua_repl::run_input(&mut repl, state),
+                            KeyCode::Esc => state.lua_repl_open = false,
+                            _ => {}
+                        }
+                        state.lua_repl = Some(repl);
+                    }
+                    continue;
+                }
 
+                if let Some(compare) = &mut state.compare {
+                    match event.code {
+                        KeyCode::Esc => state.compare = None,
+                        KeyCode::Left | KeyCode::Right | KeyCode::Tab => compare.toggle_focus(),
+                        KeyCode::Down => compare.focused_reader().scroll += 1,
+                        KeyCode::Up => {
+                            let reader = compare.focused_reader();
+                            reader.scroll = reader.scroll.saturating_sub(1);
+                        }
+                        _ => {}
+                    }
+                    continue;
+                }
 
-    Ok(removed_contents)
-}
+                //Only without an overlay open, the trending list would end up hidden behind it
+                if event.code == KeyCode::Char('t') && event.modifiers.contains(KeyModifiers::CONTROL) {
+                    show_trending(state, &worker_tx);
+                    continue;
+                }
 
-async fn search (search_term: String) -> Result<SearchResponse, Box<dyn std::error::Error>>  {
+                if state.focus == FocusedPane::SearchBox {
+                    //Readline's Ctrl+P and Ctrl+N do whatever Up and Down do
+                    let event = match event.code {
+                        KeyCode::Char('p') if event.modifiers.contains(KeyModifiers::CONTROL) => KeyEvent::new(KeyCode::Up, KeyModifiers::NONE),
+                        KeyCode::Char('n') if event.modifiers.contains(KeyModifiers::CONTROL) => KeyEvent::new(KeyCode::Down, KeyModifiers::NONE),
+                        _ => event,
+                    };
+                    if let Some(mut prompt) = state.prompt.take() {
+                        match event.code {
+                            KeyCode::Char(c) if !event.modifiers.contains(KeyModifiers::CONTROL) => {
+                                prompt.text.push(c);
+                                state.prompt = Some(prompt);
+                            }
+                            KeyCode::Backspace => {
+                                prompt.text.pop();
+                                state.prompt = Some(prompt);
+                            }
+                            KeyCode::Enter if prompt.kind == PromptKind::Find => start_find(state, prompt.text.trim()),
+                            KeyCode::Enter if prompt.kind == PromptKind::Search => {
+                                if prompt.text.trim().is_empty() {
+                                    state.focus = FocusedPane::ArticleContent;
+                                } else {
+                                    state.search_string = prompt.text.trim().to_string();
+                                    submit_search(state, false, &worker_tx);
+                                }
+                            }
+                            KeyCode::Enter if !prompt.text.trim().is_empty() => save_preset(state, prompt.text.trim()),
+                            KeyCode::Esc if prompt.kind != PromptKind::PresetName => state.focus = FocusedPane::ArticleContent,
+                            KeyCode::Esc => {}
+                            _ => state.prompt = Some(prompt),
+                        }
+                        continue;
+                    }
+                    if let Some(mut reverse) = state.reverse_search.take() {
+                        let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+                        let searching = match event.code {
+                            KeyCode::Char('r') if ctrl => {
+                                reverse.index += 1;
+                                true
+                            }
+                            KeyCode::Char(c) if !ctrl => {
+                                reverse.pattern.push(c);
+                                reverse.index = 0;
+                                true
+                            }
+                            KeyCode::Backspace => {
+                                reverse.pattern.pop();
+                                reverse.index = 0;
+                                true
+                            }
+                            KeyCode::Esc => {
+                                state.search_string = reverse.original.clone();
+                                false
+                            }
+                            _ => false,
+                        };
 
-    let url = format!("https://en.wikipedia.org/w/api.php?action=query&format=json&list=search&srsearch={}", search_term);
+                        if searching {
+                            let matches = history::history_matches(&state.search_history, &reverse.pattern);
+                            reverse.index = reverse.index.min(matches.len().saturating_sub(1));
+                            if let Some(found) = matches.get(reverse.index) {
+                                state.search_string = found.to_string();
+                            }
+                            state.reverse_search = Some(reverse);
+                            continue;
+                        }
+                        //Enter searches for the match, other keys just keep it like in bash
+                        if event.code != KeyCode::Enter {
+                            continue;
+                        }
+                    }
 
-    let resp = reqwest::get(&url)
-        .await?    
-        .json::<serde_json::Value>()        
-        .await?;
+                    if event.code != KeyCode::Tab && event.code != KeyCode::BackTab {
+                        state.completion = None;
+                    }
+                    //Editing the query dismisses a pending suggestion, 'y' accepts it
+                    let accepts_suggestion = event.code == KeyCode::Char('y') && state.spelling_suggestion.is_some();
+                    if matches!(event.code, KeyCode::Char(_) | KeyCode::Backspace) && !accepts_suggestion {
+                        state.spelling_suggestion = None;
+                    }
+                    if event.code != KeyCode::Enter {
+                        state.confirm_replace = false;
+                    }
 
-    let search_resp: SearchResponse = serde_json::from_value(resp).unwrap();
+                    let ctrl = event.modifiers.contains(KeyModifiers::CONTROL);
+                    let selection_used = state.select_all && match event.code {
+                        KeyCode::Char(c) => !ctrl || c == 'c',
+                        KeyCode::Backspace | KeyCode::Delete => true,
+                        _ => false,
+                    };
+                    if state.select_all {
+                        state.select_all = false;
+                        match event.code {
+                            KeyCode::Char('c') if ctrl => {
+                                state.select_all = true;
+                                copy_search_string(state);
+                            }
+                            KeyCode::Char(c) if !ctrl => state.search_string = c.to_string(),
+                            KeyCode::Backspace | KeyCode::Delete => state.search_string.clear(),
+                            _ => {}
+                        }
+                    }
 
-    // println!("{:#?}", search_resp.query.search);
+                    match event.code {
+                        KeyCode::Char('a') if ctrl => state.select_all = !state.search_string.is_empty(),
+                        KeyCode::Char('r') if ctrl => {
+                            state.reverse_search = Some(ReverseSearch {
+                                pattern: String::new(),
+                                index: 0,
+                                original: state.search_string.clone(),
+                            });
+                            state.pending_suggestions = None;
+                            state.suggestions.clear();
+                            state.suggestion_index = None;
+                            continue;
+                        }
+                        KeyCode::Char('s') if ctrl => {
+                            if state.search_string.trim().is_empty() {
+                                state.status_message = Some(String::from("Type a query to save it as a preset"));
+                            } else {
+                                state.prompt = Some(Prompt { kind: PromptKind::PresetName, text: String::new() });
+                                state.pending_suggestions = None;
+                                state.suggestions.clear();
+                                state.suggestion_index = None;
+                            }
+                            continue;
+                        }
+                        KeyCode::Char('v') if ctrl => {
+                            match clipboard::paste_from_clipboard() {
+                                Some(text) => {
+                                    let (text, truncated) = clipboard::sanitize_paste(&text, clipboard::MAX_PASTE_CHARS);
+                                    state.search_string.push_str(&text);
+                                    if truncated {
+                                        state.status_message = Some(format!("(pasted text truncated to {} chars)", clipboard::MAX_PASTE_CHARS));
+                                    }
+                                }
+                                None => state.status_message = Some(String::from("Clipboard not available on this system")),
+                            }
+                        }
+                        //Already handled by the selection above
+                        _ if selection_used => {}
+                        //Readline editing, the cursor is always at the end of the query
+                        KeyCode::Char('u') if ctrl => state.search_string.clear(),
+                        KeyCode::Char('w') if ctrl => {
+                            let end = state.search_string.trim_end().rfind(' ').map_or(0, |i| i + 1);
+                            state.search_string.truncate(end);
+                        }
+                        //Deletes up to the end of the line, which is always empty
+                        KeyCode::Char('k') if ctrl => {}
+                        KeyCode::Tab | KeyCode::BackTab => {
+                            let (prefix, index) = match &state.completion {
+                                Some((prefix, index)) => (prefix.clone(), Some(*index)),
+                                None => (state.search_string.clone(), None),
+                            };
+                            let candidates = history::history_completions(&state.search_history, &prefix);
 
-    Ok(search_resp)
-}
+                            if !candidates.is_empty() {
+                                let next = match (index, event.code) {
+                                    (None, KeyCode::BackTab) => candidates.len() - 1,
+                                    (None, _) => 0,
+                                    (Some(i), KeyCode::BackTab) => (i + candidates.len() - 1) % candidates.len(),
+                                    (Some(i), _) => (i + 1) % candidates.len(),
+                                };
 
-fn render_home<'a>() -> Paragraph<'a> {
-    let home = Paragraph::new(vec![
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Welcome")]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("to")]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::styled(
-            "xPedia",
-            Style::default().fg(Color::LightBlue),
-        )]),
-        Spans::from(vec![Span::raw("")]),
-        Spans::from(vec![Span::raw("Press 's' to search")]),
-    ])
-    .alignment(Alignment::Center)
-    .block(
-        Block::default()
-            .borders(Borders::ALL)
+                                state.search_string = candidates[next].to_string();
+                                state.completion = Some((prefix, next));
+                            } else if event.code == KeyCode::Tab {
+                                //Tab only leaves the search box if there is nothing to complete
+                                cycle_focus(state);
+                                continue;
+                            }
+                        }
+                        KeyCode::Char('y') if state.spelling_suggestion.is_some() => {
+                            if let Some(suggestion) = state.spelling_suggestion.take() {
+                                state.search_string = suggestion;
+                            }
+                            state.status_message = None;
+                            submit_search(state, event.modifiers.contains(KeyModifiers::ALT), &worker_tx);
+                        }
+                        KeyCode::Char(c) => {
+                            state.search_string.push(c);
+                        }
+                        KeyCode::Backspace => {
+                            state.search_string.pop();
+                        }
+                        KeyCode::F(7) => {
+                            state.spelling_suggestion = spell::suggest_correction(&state.search_string);
+                            state.status_message = Some(match &state.spelling_suggestion {
+                                Some(suggestion) => spelling_prompt(suggestion),
+                                None => String::from("No spelling mistakes found"),
+                            });
+                        }
+                        KeyCode::Down if !state.suggestions.is_empty() => {
+                            state.suggestion_index = Some(state.suggestion_index.map_or(0, |i| (i + 1) % state.suggestions.len()));
+                        }
+                        KeyCode::Up if !state.suggestions.is_empty() => {
+                            state.suggestion_index = state.suggestion_index.and_then(|i| i.checked_sub(1));
+                        }
+                        KeyCode::Enter => {
+                            if let Some(suggestion) = state.suggestion_index.and_then(|i| state.suggestions.get(i)) {
+                                state.search_string = suggestion.text.clone();
+                            }
+
+                            //A second Enter searches anyway
+                            let suggestion = match state.spelling_suggestion.take() {
+                                Some(_) => {
+                                    state.status_message = None;
+                                    None
+                                }
+                                None if state.config.spell_check && state.config.language == "en" => {
+                                    spell::suggest_correction(&state.search_string)
+                                }
+                                None => None,
+                            };
+
+                            match suggestion {
+                                Some(suggestion) => {
+                                    state.status_message = Some(spelling_prompt(&suggestion));
+                                    state.spelling_suggestion = Some(suggestion);
+                                }
+                                None if state.config.new_search == NewSearchMode::Confirm && !state.confirm_replace && !state.current_search_results.is_empty() => {
+                                    state.confirm_replace = true;
+                                    state.status_message = Some(String::from("Press Enter again to replace the current results"));
+                                }
+                                None => {
+                                    state.confirm_replace = false;
+                                    submit_search(state, event.modifiers.contains(KeyModifiers::ALT), &worker_tx);
+                                }
+                            }
+                        }
+                        KeyCode::Esc => {
+                            state.spelling_suggestion = None;
+                            leave_search(state);
+                        }
+                        _ => {}
+                    }
+
+                    let edited = matches!(event.code, KeyCode::Char(_) | KeyCode::Backspace | KeyCode::Delete | KeyCode::Tab | KeyCode::BackTab);
+                    if state.focus != FocusedPane::SearchBox || event.code == KeyCode::Enter {
+                        state.pending_search = None;
+                        state.pending_suggestions = None;
+                        state.suggestions.clear();
+                        state.suggestion_index = None;
+                    } else if edited {
+                        if state.config.incremental_search {
+                            schedule_incremental_search(state, &worker_tx);
+                        }
+                        update_suggestions(state);
+                        schedule_api_suggestions(state, &worker_tx);
+                    }
+                } 
+                else if state.focus == FocusedPane::MenuBar {
+                    let tabs = MenuItem::all();
+                    let current = tabs.iter().position(|m| *m == state.active_menu_item).unwrap_or(0);
+
+                    match event.code {
+                        KeyCode::Right => state.active_menu_item = tabs[(current + 1) % tabs.len()],
+                        KeyCode::Left => state.active_menu_item = tabs[(current + tabs.len() - 1) % tabs.len()],
+                        KeyCode::Enter | KeyCode::Down => state.focus = FocusedPane::ResultsList,
+                        _ => {}
+                    }
+                }
+                else if state.focus == FocusedPane::StatusBar {
+                    match event.code {
+                        KeyCode::Char('y') => {
+                            if let Some(message) = state.status_message.clone() {
+                                state.status_message = match clipboard::copy_to_clipboard(&message) {
+                                    Ok(_) => Some(String::from("Copied the status message")),
+                                    Err(e) => Some(format!("Copying failed: {}", e)),
+                                };
+                            }
+                        }
+                        KeyCode::Delete => state.status_message = None,
+                        //Back to the article or the list, like leaving the search box
+                        KeyCode::Esc | KeyCode::Up => leave_search(state),
+                        _ => {}
+                    }
+                }
+                else if state.active_menu_item == MenuItem::History {
+                    let amount = state.search_history.len();
+                    let selected = state.history_list_state.selected();
+                    //The list shows the newest entry first, the history keeps it last
+                    let entry = selected.and_then(|i| amount.checked_sub(i + 1));
+
+                    match event.code {
+                        KeyCode::Enter => {
+                            if let Some(entry) = entry {
+                                state.search_string = state.search_history[entry].query.clone();
+                                submit_search(state, false, &worker_tx);
+                            }
+                        }
+                        KeyCode::Char('d') | KeyCode::Delete => {
+                            if let Some(entry) = entry {
+                                let removed = state.search_history.remove(entry);
+                                state.history_list_state.select(selected.filter(|_| amount > 1).map(|i| i.min(amount - 2)));
+                                state.status_message = match history::save_history(&state.search_history) {
+                                    Ok(()) => Some(format!("Deleted '{}' from the history", removed.query)),
+                                    Err(e) => Some(e.to_string()),
+                                };
+                            }
+                        }
+                        KeyCode::Down if amount > 0 => state.history_list_state.select(Some(selected.map_or(0, |i| (i + 1) % amount))),
+                        KeyCode::Up if amount > 0 => state.history_list_state.select(Some(selected.map_or(0, |i| (i + amount - 1) % amount))),
+                        _ => {}
+                    }
+                }
+                else if state.focus == FocusedPane::Toc && state.active_menu_item == MenuItem::Results {
+                    let outline = article_outline(state);
+                    let selected = state.toc_list_state.selected();
+
+                    match event.code {
+                        KeyCode::Down if !outline.is_empty() => state.toc_list_state.select(Some(selected.map_or(0, |i| (i + 1) % outline.len()))),
+                        KeyCode::Up if !outline.is_empty() => state.toc_list_state.select(Some(selected.map_or(0, |i| (i + outline.len() - 1) % outline.len()))),
+                        KeyCode::Enter => {
+                            if let Some((_, line)) = selected.and_then(|i| outline.get(i)) {
+                                state.reveal = None;
+                                scroll_to(state, (*line).try_into().unwrap_or(u16::MAX));
+                            }
+                            state.focus = FocusedPane::ArticleContent;
+                        }
+                        KeyCode::Esc => state.focus = FocusedPane::ArticleContent,
+                        _ => {}
+                    }
+                }
+                else if state.focus == FocusedPane::ArticleContent {
+                    //Any scroll key ends the typewriter reveal
+                    if matches!(event.code, KeyCode::Up | KeyCode::Down | KeyCode::PageUp | KeyCode::PageDown | KeyCode::Home) {
+                        state.reveal = None;
+                    }
+
+                    match event.code {
+                        KeyCode::Char(' ') if state.reveal.is_some() => state.reveal_paused = !state.reveal_paused,
+                        KeyCode::Esc => close_article(state),
+                        KeyCode::Char('w') if event.modifiers.contains(KeyModifiers::CONTROL) => discard_article(state),
+                        KeyCode::Char(c) if c == state.config.article_toggle_key => close_article(state),
+                        //Line scrolling stops a running animation
+                        KeyCode::Down => {
+                            state.scroll_target = None;
+                            state.scroll += 1;
+                        }
+                        KeyCode::Up => {
+                            state.scroll_target = None;
+                            state.scroll = state.scroll.saturating_sub(1);
+                        }
+                        //Page jumps during an animation continue from its target
+                        KeyCode::PageDown | KeyCode::PageUp if state.section_scroll => {
+                            let from = state.scroll_target.unwrap_or(state.scroll);
+                            let target = section_jump(state, from, event.code == KeyCode::PageDown);
+                            scroll_to(state, target);
+                        }
+                        KeyCode::PageDown => {
+                            let target = state.scroll_target.unwrap_or(state.scroll).saturating_add(state.page_height);
+                            scroll_to(state, target);
+                        }
+                        KeyCode::PageUp => {
+                            let target = state.scroll_target.unwrap_or(state.scroll).saturating_sub(state.page_height);
+                            scroll_to(state, target);
+                        }
+                        KeyCode::Home => scroll_to(state, 0),
+                        KeyCode::Char('~') => {
+                            if let Some(article) = &state.current_article {
+                                state.markdown_preview = Some(markdown::article_to_markdown(&article.search.title, &article.content));
+                                state.overlay_scroll = 0;
+                            }
+                        }
+                        KeyCode::Char('b') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            state.config.styled_headings = !state.config.styled_headings;
+                        }
+                        KeyCode::Char('e') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            if let Some(selected_item) = safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+                                let url = util::wayback_url(&state.config.article_url(&selected_item.title), &selected_item.timestamp);
+                                state.status_message = Some(format!("Opening {}", url));
+                                state.pending_open = Some((url, Instant::now()));
+                            }
+                        }
+                        KeyCode::Char('g') if event.modifiers.contains(KeyModifiers::CONTROL) => open_location(state, &worker_tx),
+                        KeyCode::Char('x') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            if let Some(article) = state.current_article.as_mut() {
+                                let text = annotations::editor_text(article.annotation.as_deref(), &article.content);
+
+                                disable_raw_mode()?;
+                                let edited = external::edit_in_editor(&text);
+                                enable_raw_mode()?;
+                                screen.blocking_lock().terminal.clear()?;
+
+                                state.status_message = match edited {
+                                    Ok(edited) => {
+                                        article.annotation = annotations::notes_of(&edited);
+                                        match annotations::save_annotation(article.search.pageid, article.annotation.as_deref()) {
+                                            Ok(()) if article.annotation.is_some() => Some(format!("Saved notes for {}", article.search.title)),
+                                            Ok(()) => Some(format!("No notes for {}", article.search.title)),
+                                            Err(e) => Some(e.to_string()),
+                                        }
+                                    }
+                                    Err(e) => Some(e.to_string()),
+                                };
+                            }
+                        }
+                        KeyCode::Char('t') => open_talk_page(state, &worker_tx),
+                        KeyCode::Char('G') => open_gallery(state, &worker_tx),
+                        KeyCode::Char('L') => open_links(state, &worker_tx),
+                        KeyCode::Char('W') => open_backlinks(state, &worker_tx),
+                        KeyCode::Char('C') => open_categories(state, &worker_tx),
+                        KeyCode::Char('M') => state.highlight_terms = !state.highlight_terms,
+                        KeyCode::Char('S') => {
+                            state.section_scroll = !state.section_scroll;
+                            let message = if state.section_scroll { "PageUp/PageDown move by section" } else { "PageUp/PageDown move by page" };
+                            brief_status(state, String::from(message));
+                        }
+                        KeyCode::Char('/') => {
+                            state.prompt = Some(Prompt { kind: PromptKind::Find, text: state.find.clone().unwrap_or_default() });
+                            state.focus = FocusedPane::SearchBox;
+                        }
+                        KeyCode::Char('n') if event.modifiers.contains(KeyModifiers::CONTROL) => search_from_article(state),
+                        KeyCode::Char('n') => find_next(state, false),
+                        KeyCode::Char('N') => find_next(state, true),
+                        KeyCode::Backspace => breadcrumb_back(state, &worker_tx),
+                        KeyCode::Char('z') | KeyCode::Char('Z') | KeyCode::Char('X') => fold_sections(state, event.code),
+                        KeyCode::Char('i') => {
+                            //The infobox is part of the text when the panel is hidden, only the text is converted again
+                            state.show_infobox = !state.show_infobox;
+                            if let Some((search, width)) = state.current_article.as_ref().map(|a| (a.search.clone(), a.width)) {
+                                schedule_article_fetch(state, &search, width, &worker_tx);
+                            }
+                        }
+                        KeyCode::Char('b') => {
+                            if let Some(selected_item) = safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+                                let added = bookmarks::toggle_bookmark(&mut state.bookmarks, selected_item);
+
+                                state.status_message = match bookmarks::save_bookmarks(&state.bookmarks) {
+                                    Ok(()) if added => Some(format!("Bookmarked {}", selected_item.title)),
+                                    Ok(()) => Some(format!("Removed bookmark {}", selected_item.title)),
+                                    Err(e) => Some(e.to_string()),
+                                };
+                            }
+                        }
+                        KeyCode::Char('H') if state.config.debug => {
+                            let pageid = state.current_article.as_ref().map(|a| a.search.pageid);
+                            match state.raw_view.take().filter(|(shown, _)| Some(*shown) == pageid) {
+                                //Back to the article where it was left
+                                Some((_, article_scroll)) => {
+                                    state.scroll = article_scroll;
+                                    state.scroll_target = None;
+                                }
+                                None => with_raw_html(state, RawHtmlUse::View, &worker_tx),
+                            }
+                        }
+                        KeyCode::Char('R') if state.config.debug => with_raw_html(state, RawHtmlUse::Report, &worker_tx),
+                        KeyCode::Char('c') => pick_compare_article(state),
+                        KeyCode::Char('U') => {
+                            if let Some(selected_item) = safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+                                state.client.forget_cached(selected_item.pageid as usize);
+                                state.scroll_positions.remove(&selected_item.pageid);
+                                state.current_article = None;
+                                state.scroll = 0;
+                                state.scroll_target = None;
+                                state.status_message = Some(format!("Refreshed {}", selected_item.title));
+                            }
+                        }
+                        KeyCode::F(8) => open_concept_map(state, &worker_tx),
+                        KeyCode::Char('*') => {
+                            //There is no cursor in the article, so the first word of the top visible line is used
+                            let word = state.current_article
+                                .as_ref()
+                                .and_then(|a| a.content.lines().nth(state.scroll as usize))
+                                .and_then(|l| l.split_whitespace().next())
+                                .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric()).to_string());
+
+                            if let Some(word) = word.filter(|w| !w.is_empty()) {
+                                state.search_string = word;
+                                state.focus = FocusedPane::SearchBox;
+                            }
+                        }
+                        KeyCode::Char('&') => {
+                            match (&state.config.external_tool, &state.current_article) {
+                                (Some(command), Some(WikipediaArticle { content, .. })) => {
+                                    //The tool is not interactive, stdin is closed and stdout is captured for the overlay
+                                    disable_raw_mode()?;
+                                    let output = external::run_external_tool(command, content);
+                                    enable_raw_mode()?;
+                                    screen.blocking_lock().terminal.clear()?;
+
+                                    match output {
+                                        Ok(output) => {
+                                            state.tool_output = Some(output);
+                                            state.overlay_scroll = 0;
+                                        }
+                                        Err(e) => state.status_message = Some(e.to_string()),
+                                    }
+                                }
+                                (None, _) => state.status_message = Some(String::from("No external_tool configured")),
+                                _ => {}
+                            }
+                        }
+                        _ => {}
+                    }
+                } 
+                else if state.active_menu_item == MenuItem::Home && home_presets_focused(state) {
+                    let confirm_delete = state.confirm_delete_preset.take();
+                    let selected = state.preset_list_state.selected().and_then(|i| state.config.search_presets.iter().nth(i));
+                    let amount = state.config.search_presets.len();
+
+                    match event.code {
+                        KeyCode::Enter => {
+                            if let Some((_, query)) = selected {
+                                state.search_string = query.clone();
+                                submit_search(state, false, &worker_tx);
+                            }
+                        }
+                        KeyCode::Char('X') => {
+                            if let Some((name, _)) = selected {
+                                let name = name.clone();
+                                if confirm_delete.as_ref() == Some(&name) {
+                                    state.config.search_presets.remove(&name);
+                                    state.preset_list_state.select(Some(0));
+                                    state.status_message = match config::save_config(&state.config, &["search_presets"]) {
+                                        Ok(()) => Some(format!("Deleted preset {}", name)),
+                                        Err(e) => Some(e.to_string()),
+                                    };
+                                } else {
+                                    state.status_message = Some(format!("Press X again to delete preset {}", name));
+                                    state.confirm_delete_preset = Some(name);
+                                }
+                            }
+                        }
+                        KeyCode::Down => {
+                            let selected = state.preset_list_state.selected().unwrap_or(0);
+                            state.preset_list_state.select(Some((selected + 1) % amount));
+                        }
+                        KeyCode::Up => {
+                            let selected = state.preset_list_state.selected().unwrap_or(0);
+                            state.preset_list_state.select(Some((selected + amount - 1) % amount));
+                        }
+                        KeyCode::Left | KeyCode::Right => state.presets_focused = false,
+                        _ => {}
+                    }
+                }
+                else if state.active_menu_item == MenuItem::Home && !state.recent_articles.is_empty() {
+                    match event.code {
+                        KeyCode::Left | KeyCode::Right if !state.config.search_presets.is_empty() => {
+                            state.presets_focused = true;
+                            if state.preset_list_state.selected().is_none() {
+                                state.preset_list_state.select(Some(0));
+                            }
+                        }
+                        KeyCode::Enter => {
+                            if let Some(selected) = state.recent_list_state.selected() {
+                                record_navigation(state);
+                                //The recently viewed articles become the result list
+                                state.current_search_results = state.recent_articles.iter().map(|r| r.search.clone()).collect();
+                                state.results_kind = ResultsKind::Recent;
+                                state.results_query = String::from(RECENT_LIST);
+                                set_pagination(state, 0);
+                                state.search_continuation = None;
+                                state.search_result_list_state.select(Some(selected));
+                                state.active_menu_item = MenuItem::Results;
+                                open_selected_article(state);
+                            }
+                        }
+                        KeyCode::Down => {
+                            if let Some(selected) = state.recent_list_state.selected() {
+                                state.recent_list_state.select(Some((selected + 1) % state.recent_articles.len()));
+                            }
+                        }
+                        KeyCode::Up => {
+                            if let Some(selected) = state.recent_list_state.selected() {
+                                let amount = state.recent_articles.len();
+                                state.recent_list_state.select(Some((selected + amount - 1) % amount));
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                else if  state.active_menu_item == MenuItem::Results {
+                    match event.code {
+                        KeyCode::Enter => {
+                            record_navigation(state);
+                            open_selected_article(state);
+                        }
+                        KeyCode::Char(c) if c == state.config.article_toggle_key => {
+                            record_navigation(state);
+                            open_selected_article(state);
+                        }
+                        //The rank shown beside the result, ranks past the end are ignored
+                        KeyCode::Char(c @ '1'..='9') => {
+                            let rank = c as usize - '1' as usize;
+                            let index = match filtered_results(state) {
+                                Some(indices) => indices.get(rank).copied(),
+                                None => Some(rank),
+                            };
+                            if let Some(index) = index.filter(|&i| i < state.current_search_results.len()) {
+                                record_navigation(state);
+                                state.search_result_list_state.select(Some(index));
+                                open_selected_article(state);
+                            }
+                        }
+                        KeyCode::Char('f') if event.modifiers.contains(KeyModifiers::CONTROL) => cycle_size_filter(state),
+                        KeyCode::Up | KeyCode::Down | KeyCode::Home | KeyCode::End if state.size_filter != SizeFilter::All => {
+                            let indices = filtered_results(state).unwrap_or_default();
+                            let selected = state.search_result_list_state.selected();
+                            let next = match event.code {
+                                KeyCode::Home => indices.first().copied(),
+                                KeyCode::End => indices.last().copied(),
+                                code => analysis::step_filtered(&indices, selected, code == KeyCode::Down),
+                            };
+                            if next.is_some() {
+                                state.search_result_list_state.select(next);
+                            }
+                        }
+                        KeyCode::Down => {
+                            let amount_results = state.current_search_results.len();
+
+                            if let (Some(selected), true) = (state.search_result_list_state.selected(), amount_results != 0) {
+                                if selected + 1 >= amount_results {
+                                    state.search_result_list_state.select(Some(0));
+                                } else {
+                                    state.search_result_list_state.select(Some(selected + 1));
+                                }
+                            }
+                        }
+                        KeyCode::PageDown | KeyCode::PageUp if !state.current_search_results.is_empty() && state.size_filter == SizeFilter::All => {
+                            let selected = state.search_result_list_state.selected().unwrap_or(0);
+                            //Without windowing the jumps are as big as an API page
+                            let window_size = match state.config.results_window {
+                                0 => usize::from(pagination::RESULTS_PER_PAGE),
+                                size => size,
+                            };
+                            let loaded = state.current_search_results.len();
+                            state.search_result_list_state.select(Some(pagination::jump_window(selected, loaded, window_size, event.code == KeyCode::PageDown)));
+                        }
+                        KeyCode::Home if !state.current_search_results.is_empty() => state.search_result_list_state.select(Some(0)),
+                        KeyCode::End if !state.current_search_results.is_empty() => {
+                            state.search_result_list_state.select(Some(state.current_search_results.len() - 1));
+                        }
+                        KeyCode::Char('c') => pick_compare_article(state),
+                        KeyCode::Char('P') => toggle_pinned_result(state),
+                        KeyCode::Char('d') if event.modifiers.contains(KeyModifiers::CONTROL) => {
+                            state.duplicate_results = analysis::find_adjacent_duplicates(&state.current_search_results);
+                            state.status_message = match state.duplicate_results.len() {
+                                0 => Some(String::from("No duplicate results found")),
+                                n => Some(format!("{} results look like duplicates", n)),
+                            };
+                        }
+                        KeyCode::Char('y') => copy_search_string(state),
+                        KeyCode::Esc => pop_results_layer(state),
+                        KeyCode::Char(']') => turn_search_page(state, true, &worker_tx),
+                        KeyCode::Char('[') => turn_search_page(state, false, &worker_tx),
+                        KeyCode::Char('m') => load_more_results(state, &worker_tx),
+                        KeyCode::F(9) => {
+                            let path = export::results_csv_path();
+                            let file_name = path.file_name().map(|f| f.to_string_lossy().into_owned()).unwrap_or_default();
+
+                            state.status_message = match export::export_results_csv(&state.current_search_results, &path) {
+                                Ok(()) => Some(format!("Exported {} results to {}", state.current_search_results.len(), file_name)),
+                                Err(e) => Some(e.to_string()),
+                            };
+                        }
+                        KeyCode::Up => {
+                            let amount_results = state.current_search_results.len();
+
+                            if let (Some(selected), true) = (state.search_result_list_state.selected(), amount_results != 0) {
+                                if selected > 0 {
+                                    state.search_result_list_state.select(Some(selected - 1));
+                                } else {
+                                    state.search_result_list_state.select(Some(amount_results - 1));
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+
+                if state.focus != FocusedPane::SearchBox {
+                    match event.code {
+                        KeyCode::Char('q') => {
+                            save_on_exit(state, initial_styled_headings);
+                            restore_terminal(&mut screen.blocking_lock(), state.fullscreen)?;
+                            break;
+                        }
+                        //Terminals that send Ctrl+H as Backspace can use F2 instead
+                        _ if is_bug_report_key(&event) => {
+                            state.status_message = match external::open_url(&util::bug_report_url()) {
+                                Ok(()) => Some(String::from("Opening GitHub issues in browser…")),
+                                Err(e) => Some(e.to_string()),
+                            };
+                        }
+                        KeyCode::Char('h') => state.active_menu_item = MenuItem::Home,
+                        KeyCode::Char('v') => {
+                            state.active_menu_item = MenuItem::Home;
+                            set_article_open(state, false);
+                            state.recent_list_state.select(Some(0));
+                        }
+                        KeyCode::Char('r') => state.active_menu_item = MenuItem::Results,
+                        KeyCode::Char('p') => {
+                            state.active_menu_item = MenuItem::History;
+                            state.history_list_state.select(if state.search_history.is_empty() { None } else { Some(0) });
+                        }
+                        KeyCode::Char('s') => {
+                            state.focus = FocusedPane::SearchBox;
+                        },
+                        KeyCode::Char('B') => {
+                            if state.bookmarks.is_empty() {
+                                state.status_message = Some(String::from("No bookmarks to export"));
+                            } else {
+                                let client = state.client.clone();
+                                let bookmarks = state.bookmarks.clone();
+                                let tx = worker_tx.clone();
+
+                                state.runtime.spawn(async move {
+                                    let progress = |message: String| {
+                                        let _ = tx.send(Event::Status(message));
+                                    };
+
+                                    let message = match bookmarks::export_bookmarks(&client, &bookmarks, progress).await {
+                                        Ok((path, failed)) if failed.is_empty() => format!("Exported {} bookmarks to {}", bookmarks.len(), path.display()),
+                                        Ok((path, failed)) => format!("Exported to {}, failed: {}", path.display(), failed.join(", ")),
+                                        Err(e) => format!("Export failed: {}", e),
+                                    };
+                                    let _ = tx.send(Event::Status(message));
+                                });
+                            }
+                        }
+                        KeyCode::Tab => cycle_focus(state),
+                        KeyCode::F(6) => state.snapshot_requested = true,
+                        KeyCode::F(12) => {
+                            state.lua_repl.get_or_insert_with(LuaRepl::new);
+                            state.lua_repl_open = true;
+                        }
+                        KeyCode::F(11) => {
+                            state.fullscreen = !state.fullscreen;
+                            execute!(screen.blocking_lock().terminal.backend_mut(), Print(util::window_state_sequence(state.fullscreen)))?;
+                            screen.blocking_lock().terminal.clear()?;
+                        }
+                        KeyCode::F(10) => {
+                            state.status_message = match keybindings::write_keybindings() {
+                                Ok(path) => Some(format!("Keybindings reference saved to {}", path.display())),
+                                Err(e) => Some(format!("Saving the keybindings failed: {}", e)),
+                            };
+                        }
+                        KeyCode::F(5) => {
+                            state.status_message = match reload_config(state) {
+                                Ok(()) => Some(String::from("Config reloaded")),
+                                Err(e) => Some(e.to_string()),
+                            };
+                        }
+                        _ => {}
+                    }
+                } 
+            },
+            Event::Tick => {
+                //A lost resize event is noticed here, the draw adapts to the size on its own
+                if state.config.resize_poll {
+                    let size = screen.blocking_lock().terminal.size()?;
+                    if size != known_size {
+                        known_size = size;
+                        if state.last_resize.is_none_or(|t| t.elapsed() > RESIZE_BURST) {
+                            screen.blocking_lock().terminal.clear()?;
+                        }
+                        state.last_resize = Some(Instant::now());
+                    }
+                }
+
+                //Moving the selection away from the top result makes the prefetch useless
+                let selected = safe_get_selected(&state.current_search_results, &state.search_result_list_state).map(|s| s.pageid);
+                if state.prefetch.as_ref().is_some_and(|(pageid, _)| Some(*pageid) != selected) {
+                    state.prefetch = None;
+                }
+
+                if let Some((_, message)) = state.status_expires.take_if(|(at, _)| Instant::now() >= *at) {
+                    if state.status_message.as_ref() == Some(&message) {
+                        state.status_message = None;
+                    }
+                }
+
+                if let Some((url, since)) = &state.pending_open {
+                    if since.elapsed() >= OPEN_DELAY {
+                        if let Err(e) = external::open_url(url) {
+                            state.status_message = Some(e.to_string());
+                        }
+                        state.pending_open = None;
+                    }
+                }
+
+                if state.config.idle_timeout_minutes > 0 {
+                    let timeout = Duration::from_secs(state.config.idle_timeout_minutes * 60);
+                    match timeout.checked_sub(last_input.elapsed()) {
+                        None => {
+                            log::write_log("Quit after the idle timeout");
+                            save_on_exit(state, initial_styled_headings);
+                            restore_terminal(&mut screen.blocking_lock(), state.fullscreen)?;
+                            break;
+                        }
+                        Some(left) if left <= IDLE_WARNING => {
+                            state.status_message = Some(format!("Idle, quitting in {}s, press any key to stay", left.as_secs() + 1));
+                            idle_warning_shown = true;
+                        }
+                        Some(_) => {}
+                    }
+                }
+
+                if let (Some(revealed), false, true) = (state.reveal, state.reveal_paused, state.is_selected) {
+                    let revealed = revealed + state.config.typewriter_speed.max(1);
+                    let length = state.current_article.as_ref().map(|a| a.content.chars().count());
+
+                    state.reveal = match length {
+                        Some(length) if revealed >= length => None,
+                        _ => Some(revealed),
+                    };
+                }
+
+                if let Some(target) = state.scroll_target {
+                    let speed = state.config.smooth_scroll_speed.max(1);
+
+                    state.scroll = if target > state.scroll {
+                        (state.scroll + speed).min(target)
+                    } else {
+                        state.scroll.saturating_sub(speed).max(target)
+                    };
+                    if state.scroll == target || !state.is_selected {
+                        state.scroll_target = None;
+                    }
+                }
+            }
+            Event::Status(message) => state.status_message = Some(message),
+            //Results of a query the user has typed past are dropped
+            Event::SearchResults(query, res) if state.focus == FocusedPane::SearchBox && query == state.search_string => {
+                state.last_failure = None;
+                push_recent_query(state, &query, true);
+                state.search_continuation = res.next_offset().map(|offset| (query, offset));
+                state.results_kind = ResultsKind::Search;
+                state.results_query = state.search_string.clone();
+                set_pagination(state, res.query.searchinfo.totalhits);
+                state.current_search_results = pins::pin_first(res.query.search, &state.pinned_results);
+                state.size_filter = SizeFilter::All;
+                state.active_menu_item = MenuItem::Results;
+                set_article_open(state, false);
+                state.current_article = None;
+                state.scroll = 0;
+                state.search_result_list_state.select(Some(first_unpinned(state)));
+                schedule_prefetch(state);
+            }
+            Event::SearchResults(..) => {}
+            Event::Suggestions(query, titles) if state.focus == FocusedPane::SearchBox && query == state.search_string => {
+                state.api_suggestions = titles;
+                update_suggestions(state);
+            }
+            Event::Suggestions(..) => {}
+            //Articles closed or replaced while they were fetched are dropped
+            Event::ArticleFetched(pageid, width, result) if state.pending_article.as_ref().is_some_and(|(pending, _)| *pending == pageid) => {
+                state.pending_article = None;
+                let selected = safe_get_selected(&state.current_search_results, &state.search_result_list_state).cloned();
+
+                match (selected, result) {
+                    (Some(search), Ok((content, rows))) if state.is_selected && search.pageid == pageid => match &mut state.current_article {
+                        //Converted again for a new width, everything else about the article stays
+                        Some(article) if article.search.pageid == pageid => {
+                            article.content = content;
+                            article.width = width;
+                            article.infobox = rows;
+                        }
+                        Some(_) => {}
+                        None => {
+                            state.last_failure = None;
+                            recent::push_recent(&mut state.recent_articles, &search, state.config.recent_limit);
+                            if let Err(e) = recent::save_recent(&state.recent_articles) {
+                                state.status_message = Some(e.to_string());
+                            }
+                            let mut article = WikipediaArticle::new(search, content, width, rows);
+                            article.annotation = annotations::load_annotation(pageid);
+                            state.current_article = Some(article);
+                        }
+                    },
+                    (Some(search), Err(e)) if state.is_selected && search.pageid == pageid && state.current_article.is_none() => {
+                        report_failure(state, &e, RetryAction::OpenArticle(pageid));
+                        set_article_open(state, false);
+                    }
+                    //A failed conversion for a new width isn't tried again until the next resize
+                    (_, Err(e)) => {
+                        if let Some(article) = state.current_article.as_mut().filter(|a| a.search.pageid == pageid) {
+                            article.width = width;
+                        }
+                        state.status_message = Some(e.to_string());
+                    }
+                    _ => {}
+                }
+            }
+            Event::ArticleFetched(..) => {}
+            //Dropped if the compare view was closed meanwhile
+            Event::CompareFetched(pageid, result) => {
+                if let Some(reader) = state.compare.as_mut().and_then(|c| c.readers.iter_mut().find(|r| r.article.pageid == pageid && r.content.is_none())) {
+                    match result {
+                        Ok(content) => reader.content = Some(content),
+                        Err(e) => state.status_message = Some(e.to_string()),
+                    }
+                }
+            }
+            //Only the newest submitted query is shown
+            Event::SearchFinished(query, feeling_lucky, result) if state.pending_submit.as_ref().is_some_and(|(pending, _)| *pending == query) => {
+                state.pending_submit = None;
+                finish_search(state, query, feeling_lucky, result);
+            }
+            Event::SearchFinished(..) => {}
+            Event::TrendingFetched(result) => finish_trending(state, result),
+            Event::BacklinksFetched(pageid, result) => finish_backlinks(state, pageid, result),
+            Event::CategoriesFetched(title, result) => finish_categories(state, title, result),
+            Event::CategoryMembersFetched(path, title, result) => finish_category_members(state, path, title, result),
+            Event::CoordinatesFetched(pageid, result) => finish_location(state, pageid, result),
+            Event::ConceptMapBuilt(title, result) => finish_concept_map(state, title, result),
+            Event::MoreResultsFetched(query, offset, result) => finish_more_results(state, query, offset, result),
+            Event::SearchPageFetched(query, page, offset, result) => finish_search_page(state, query, page, offset, result),
+            Event::TalkPageFetched(pageid, result) => finish_talk_page(state, pageid, result),
+            //Only the newest lookup opens its article
+            Event::TitledArticleFetched(title, trail, result) if state.pending_title.as_deref() == Some(title.as_str()) => {
+                state.pending_title = None;
+                finish_titled_article(state, title, trail, result);
+            }
+            Event::TitledArticleFetched(..) => {}
+            Event::LinksFetched(pageid, result) => finish_links(state, pageid, result),
+            Event::ImagesFetched(pageid, result) => finish_gallery(state, pageid, result),
+            Event::RawHtmlFetched(pageid, purpose, result) => finish_raw_html(state, pageid, purpose, result),
+            //Only the first event of a resize burst clears, clearing on every step flickers
+            Event::Resize => {
+                if state.last_resize.is_none_or(|t| t.elapsed() > RESIZE_BURST) {
+                    screen.blocking_lock().terminal.clear()?;
+                }
+                state.last_resize = Some(Instant::now());
+            }
+        }
+    }
+
+    Ok(())
+}
+
+//Everything on the screen, drawn by the render task
+fn draw_frame<B: Backend>(rect: &mut Frame<B>, state: &mut AppState, menu_titles: &[&str], worker_tx: &mpsc::Sender<Event<KeyEvent>>) {
+    state.article_link = None;
+
+    let size = rect.size();
+    //Full screen drops the margin, the menu bar and the footer
+    let (margin, bar_height) = if state.fullscreen { (0, 0) } else { (2, 3) };
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(margin)
+        .constraints(
+            [
+                Constraint::Length(bar_height),
+                Constraint::Min(2),
+                Constraint::Length(bar_height),
+            ]
+            .as_ref(),
+        )
+        .split(size);
+
+    let footer_text = state.status_message.clone().unwrap_or_else(|| String::from("by Lucas Engleder"));
+    let copyright = Paragraph::new(footer_text)
+        .style(Style::default().fg(Color::LightCyan))
+        .alignment(Alignment::Center)
+        .block(focus_border(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(Color::White))
+                .border_type(BorderType::Plain),
+            state.focus == FocusedPane::StatusBar,
+        ));
+
+    let menu = menu_titles
+        .iter()
+        .map(|t| {
+            let (first, rest) = t.split_at(1);
+            Spans::from(vec![
+                Span::styled(
+                    first,
+                    Style::default()
+                        .fg(Color::Yellow)
+                        .add_modifier(Modifier::UNDERLINED),
+                ),
+                Span::styled(rest, Style::default().fg(Color::White)),
+            ])
+        })
+        .collect();
+
+    if !state.fullscreen { 
+        let tabs = Tabs::new(menu)
+            .select(state.active_menu_item.into())
+            .block(focus_border(Block::default().title("Menu").borders(Borders::ALL), state.focus == FocusedPane::MenuBar))
             .style(Style::default().fg(Color::White))
-            .title("Home")
-            .border_type(BorderType::Plain),
-    );
-    home
+            .highlight_style(Style::default().fg(Color::Yellow))
+            .divider(Span::raw("|"));
+
+        let search_focused = state.focus == FocusedPane::SearchBox;
+        let search_color = if search_focused || !state.config.dim_search_box { Color::Yellow } else { Color::DarkGray };
+        let search_box = focus_border(Block::default() 
+            .borders(Borders::ALL)
+            .style(Style::default().fg(search_color))
+            .border_type(BorderType::Plain), search_focused);
+
+        //The selected query is shown inverted until it is replaced
+        let query_style = if state.select_all { Style::default().add_modifier(Modifier::REVERSED) } else { Style::default() };
+        let (prompt, text) = match (&state.prompt, &state.reverse_search) {
+            (Some(Prompt { kind: PromptKind::PresetName, text }), _) => (String::from(" Preset name: "), text.clone()),
+            (Some(Prompt { kind: PromptKind::Find, text }), _) => (String::from(" Find: "), text.clone()),
+            (Some(Prompt { kind: PromptKind::Search, text }), _) => (String::from(" Search for: "), text.clone()),
+            (None, Some(reverse)) => (format!(" (reverse-i-search)'{}': ", reverse.pattern), state.search_string.clone()),
+            (None, None) => (String::from(" 🔍 "), state.search_string.clone()),
+        };
+        let mut search_spans = vec![
+            Span::raw(prompt),
+            Span::styled(text, query_style),
+        ];
+        //Typing only goes to the search box while it has the focus, the cursor shows when
+        if search_focused {
+            search_spans.push(Span::styled(" ", Style::default().add_modifier(Modifier::REVERSED)));
+        }
+        let search_text = Paragraph::new(Spans::from(search_spans))
+            .block(search_box)
+            .style(Style::default()
+            .fg(search_color));
+
+        let navbar = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [Constraint::Percentage(70), Constraint::Percentage(30)].as_ref(),
+            )
+            .split(chunks[0]);
+
+
+        rect.render_widget(tabs, navbar[0]);
+
+        rect.render_widget(search_text, navbar[1]);   
+    }
+
+    //Content Page, depends on which tab
+    match state.active_menu_item {
+        MenuItem::Home => {
+            if state.recent_articles.is_empty() && state.config.search_presets.is_empty() {
+                rect.render_widget(render_home(), chunks[1]);
+            } else {
+                let home_chunks = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints(
+                        [Constraint::Length(13), Constraint::Min(3)].as_ref(),
+                    )
+                    .split(chunks[1]);
+
+                rect.render_widget(render_home(), home_chunks[0]);
+
+                //Only the lists that have entries are shown, side by side
+                let lists = usize::from(!state.recent_articles.is_empty()) + usize::from(!state.config.search_presets.is_empty());
+                let list_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(vec![Constraint::Ratio(1, lists as u32); lists])
+                    .split(home_chunks[1]);
+                let presets_focused = home_presets_focused(state);
+                let lists_focused = state.focus == FocusedPane::ResultsList;
+
+                if !state.recent_articles.is_empty() {
+                    let list = render_recent_list(&state.recent_articles, lists_focused && !presets_focused);
+                    rect.render_stateful_widget(list, list_chunks[0], &mut state.recent_list_state);
+                }
+                if !state.config.search_presets.is_empty() {
+                    let list = render_preset_list(&state.config.search_presets, lists_focused && presets_focused);
+                    rect.render_stateful_widget(list, list_chunks[lists - 1], &mut state.preset_list_state);
+                }
+            }
+        }
+        MenuItem::History => {
+            let list = render_history_list(&state.search_history);
+            rect.render_stateful_widget(list, chunks[1], &mut state.history_list_state);
+        }
+        MenuItem::Results => {
+            let results_chunks = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints(
+                    [Constraint::Percentage(20), Constraint::Percentage(80)].as_ref(),
+                )
+                .split(chunks[1]);
+
+
+            let marks = ResultMarks {
+                duplicates: &state.duplicate_results,
+                pinned: &state.pinned_results,
+                views: state.trending.as_ref().filter(|_| state.results_kind == ResultsKind::Trending).map(|(_, _, views)| views),
+                width: results_chunks[0].width.saturating_sub(2),
+                filter: Some(state.size_filter.label()).filter(|_| state.size_filter != SizeFilter::All),
+            };
+            if state.focus == FocusedPane::Toc {
+                let rows = article_outline(state).into_iter().map(|(title, _)| vec![Span::raw(title)]).collect();
+                let list = render_labeled_list("Contents (Enter: jump)", rows, true);
+                rect.render_stateful_widget(list, results_chunks[0], &mut state.toc_list_state);
+            }
+            //The filtered results are listed on their own, the selection is mapped into them
+            else if let Some(indices) = filtered_results(state) {
+                let shown = indices.iter().map(|&i| state.current_search_results[i].clone()).collect();
+                let list = render_search_list(shown, 0, &marks, state.focus == FocusedPane::ResultsList, page_indicator(state), &state.config);
+                let mut filtered_state = ListState::default();
+                filtered_state.select(state.search_result_list_state.selected().and_then(|s| indices.iter().position(|&i| i == s)));
+                rect.render_stateful_widget(list, results_chunks[0], &mut filtered_state);
+            } else {
+                //Only the window with the selection is listed, its own list state points into it
+                let loaded = state.current_search_results.len();
+                let selected_index = state.search_result_list_state.selected().unwrap_or(0);
+                match pagination::window(selected_index, loaded, state.config.results_window) {
+                    Some((start, end)) => {
+                        let list = render_search_list(state.current_search_results[start..end].to_vec(), start, &marks, state.focus == FocusedPane::ResultsList, page_indicator(state), &state.config);
+                        let mut window_state = ListState::default();
+                        window_state.select(state.search_result_list_state.selected().map(|i| i - start));
+                        rect.render_stateful_widget(list, results_chunks[0], &mut window_state);
+                    }
+                    None => {
+                        let list = render_search_list(state.current_search_results.clone(), 0, &marks, state.focus == FocusedPane::ResultsList, page_indicator(state), &state.config);
+                        rect.render_stateful_widget(list, results_chunks[0], &mut state.search_result_list_state);
+                    }
+                }
+            }
+
+            let selected = safe_get_selected(&state.current_search_results, &state.search_result_list_state).cloned();
+            if let Some(compare) = &mut state.compare {
+                let reader_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [Constraint::Percentage(50), Constraint::Percentage(50)].as_ref(),
+                    )
+                    .split(results_chunks[1]);
+
+                let focused = compare.focused;
+                let mut fetches = Vec::new();
+                for (i, reader) in compare.readers.iter_mut().enumerate() {
+                    let page = match &reader.content {
+                        Some(content) => render_page_content(&reader.article, content, reader.scroll, reader_chunks[i].width, &TextDisplay::default(), &state.config),
+                        None => {
+                            //Fetched at the minimum width when the pane is too narrow, so it is ready once the terminal is widened
+                            if !reader.requested {
+                                reader.requested = true;
+                                fetches.push((reader.article.pageid, util::fetch_width(reader_chunks[i].width)));
+                            }
+                            Paragraph::new(format!("Loading {}…", reader.article.title))
+                        }
+                    };
+                    let page = page.block(focus_border(content_block(&reader.article, "", &state.config), i == focused));
+                    rect.render_widget(page, reader_chunks[i]);
+                }
+                for (pageid, fetch_width) in fetches {
+                    schedule_compare_fetch(state, pageid, fetch_width, worker_tx);
+                }
+            }
+            else if let (Some(selected_item), true, Some(html)) = (&selected, state.is_selected, raw_html_shown(state)) {
+                let title = format!("RAW HTML of {}, no cleaning applied (H: back)", selected_item.title);
+                let raw = Paragraph::new(html.clone())
+                    .block(focus_border(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::White)).title(Span::styled(title, Style::default().fg(Color::Red))).border_type(BorderType::Plain), state.focus == FocusedPane::ArticleContent))
+                    .wrap(Wrap { trim: false })
+                    .scroll((state.scroll, 0));
+                state.page_height = results_chunks[1].height.saturating_sub(2);
+                rect.render_widget(raw, results_chunks[1]);
+            }
+            else if let (Some(selected_item), true) = (selected, state.is_selected) {
+                let show_infobox = state.show_infobox && state.current_article.as_ref().is_some_and(|a| !a.infobox.is_empty());
+                let article_chunks = Layout::default()
+                    .direction(Direction::Horizontal)
+                    .constraints(
+                        [Constraint::Min(0), Constraint::Length(if show_infobox { results_chunks[1].width / 3 } else { 0 })].as_ref(),
+                    )
+                    .split(results_chunks[1]);
+                let text_width = ((size.width as f64 * 0.8).floor() as u16).saturating_sub(article_chunks[1].width);
+
+                if state.current_article.is_none() && state.config.typewriter {
+                    state.reveal = Some(0);
+                    state.reveal_paused = false;
+                }
+
+                //The article is fetched in the background, until it arrives the pane says so
+                let fetch_width = util::fetch_width(text_width);
+                let resizing = state.last_resize.is_some_and(|t| t.elapsed() <= RESIZE_BURST);
+                let content = match &state.current_article {
+                    Some(article) => {
+                        //The old text stays until the one for the new width arrives
+                        if article.width != fetch_width && !resizing && state.pending_article.is_none() {
+                            schedule_article_fetch(state, &selected_item, fetch_width, worker_tx);
+                        }
+                        state.current_article.as_ref().map(|a| a.content.clone())
+                    }
+                    None => {
+                        if state.pending_article.as_ref().map(|(pageid, _)| *pageid) != Some(selected_item.pageid) {
+                            schedule_article_fetch(state, &selected_item, fetch_width, worker_tx);
+                        }
+
+                        let loading = Paragraph::new(format!("Loading {}…", selected_item.title))
+                            .block(focus_border(content_block(&selected_item, "", &state.config), state.focus == FocusedPane::ArticleContent));
+                        rect.render_widget(loading, results_chunks[1]);
+                        None
+                    }
+                };
+
+                if let Some(content) = content {
+                    let terms = highlighted_terms(state);
+                    let display = TextDisplay {
+                        reveal: state.reveal,
+                        collapsed: state.collapsed_sections.get(&selected_item.pageid),
+                        find: state.find.as_deref(),
+                        terms: &terms,
+                    };
+                    let page = render_page_content(&selected_item, &content, state.scroll, text_width, &display, &state.config);
+                    //Half the pane at most, the title itself has to stay readable
+                    let trail = util::breadcrumb_prefix(&state.breadcrumbs, state.config.breadcrumb_depth, (article_chunks[0].width / 2).into());
+                    let page = page.block(focus_border(content_block(&selected_item, &trail, &state.config), state.focus == FocusedPane::ArticleContent));
+
+                    //Notes take at most a third of the pane, the text stays the main thing
+                    let notes = state.current_article.as_ref().and_then(|a| a.annotation.clone());
+                    let notes_height = notes.as_ref().map_or(0, |n| (n.lines().count() as u16 + 2).min(article_chunks[0].height / 3));
+                    let text_chunks = Layout::default()
+                        .direction(Direction::Vertical)
+                        .constraints([Constraint::Length(notes_height), Constraint::Min(0)].as_ref())
+                        .split(article_chunks[0]);
+
+                    state.page_height = text_chunks[1].height.saturating_sub(2);
+                    if let Some(notes) = notes {
+                        rect.render_widget(render_notes(notes), text_chunks[0]);
+                    }
+                    rect.render_widget(page, text_chunks[1]);
+                    if let Some(article) = state.current_article.as_ref().filter(|a| state.show_infobox && !a.infobox.is_empty()) {
+                        rect.render_widget(render_infobox(&article.infobox), article_chunks[1]);
+                    }
+
+                    let url = state.config.article_url(&selected_item.title);
+                    state.article_link = Some((results_chunks[1].x + 1, results_chunks[1].y, selected_item.title.clone(), url));
+                }
+            }
+        }
+    }
+
+    //Footer
+    if !state.fullscreen {
+        rect.render_widget(copyright, chunks[2]);
+    }
+
+    if let Some(error) = &state.error_state {
+        rect.render_widget(Clear, chunks[1]);
+        rect.render_widget(render_error_state(error, state.config.debug), chunks[1]);
+    }
+
+    //Markdown preview covers the whole screen
+    if let Some(markdown) = &state.markdown_preview {
+        rect.render_widget(Clear, size);
+        rect.render_widget(render_overlay("Markdown Preview (w: write to disk, Esc: cancel)", markdown.clone(), state.overlay_scroll), size);
+    }
+
+    if let Some(output) = &state.tool_output {
+        rect.render_widget(Clear, size);
+        rect.render_widget(render_overlay("External Tool Output (Esc: close)", output.clone(), state.overlay_scroll), size);
+    }
+
+    if state.focus == FocusedPane::SearchBox && !state.suggestions.is_empty() && !state.fullscreen {
+        let navbar = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(
+                [Constraint::Percentage(70), Constraint::Percentage(30)].as_ref(),
+            )
+            .split(chunks[0]);
+
+        let height = (state.suggestions.len() as u16 + 2).min(size.height.saturating_sub(navbar[1].bottom()));
+        let dropdown_area = Rect::new(navbar[1].x, navbar[1].bottom(), navbar[1].width, height);
+        let mut dropdown_state = ListState::default();
+        dropdown_state.select(state.suggestion_index);
+
+        rect.render_widget(Clear, dropdown_area);
+        rect.render_stateful_widget(render_suggestions(&state.suggestions), dropdown_area, &mut dropdown_state);
+    }
+
+    if let (true, Some(images)) = (state.gallery_open, state.current_article.as_ref().and_then(|a| a.images.as_ref())) {
+        rect.render_widget(Clear, size);
+        rect.render_stateful_widget(render_gallery(images), size, &mut state.gallery_state);
+    }
+
+    if let (true, Some(all_links)) = (state.links_open, state.current_article.as_ref().and_then(|a| a.links.as_ref())) {
+        let shown = links::visible_links(all_links, state.config.max_links, state.links_show_all);
+        rect.render_widget(Clear, size);
+        rect.render_stateful_widget(render_links(shown, all_links.len()), size, &mut state.links_state);
+    }
+
+    if let Some(tree) = &state.category_tree {
+        rect.render_widget(Clear, size);
+        rect.render_stateful_widget(render_category_tree(tree), size, &mut state.category_state);
+    }
+
+    if let (true, Some(article)) = (state.backlinks_open, &state.current_article) {
+        if let Some(backlinks) = &article.backlinks {
+            rect.render_widget(Clear, size);
+            rect.render_stateful_widget(render_backlinks(&article.search.title, backlinks), size, &mut state.backlinks_state);
+        }
+    }
+
+    if let Some(nodes) = &state.concept_map {
+        rect.render_widget(Clear, size);
+        rect.render_stateful_widget(render_concept_map(nodes), size, &mut state.concept_map_state);
+    }
+
+    if let Some(map) = &state.geo_map {
+        let width = geo_map::MAP_WIDTH.min(size.width);
+        let height = geo_map::MAP_HEIGHT.min(size.height);
+        let area = Rect::new(size.x + (size.width - width) / 2, size.y + (size.height - height) / 2, width, height);
+
+        rect.render_widget(Clear, area);
+        rect.render_widget(render_geo_map(map), area);
+    }
+
+    if let (true, Some(repl)) = (state.lua_repl_open, &state.lua_repl) {
+        rect.render_widget(Clear, size);
+        let repl_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)].as_ref())
+            .split(size);
+
+        //The newest output sits right above the input line
+        let visible = repl_chunks[0].height.saturating_sub(2) as usize;
+        let output = repl.output[repl.output.len().saturating_sub(visible)..].join("\n");
+        rect.render_widget(render_overlay("Lua REPL (Esc: close)", output, 0), repl_chunks[0]);
+
+        let input_box = focus_border(Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Yellow))
+            .border_type(BorderType::Plain), true);
+        let input = Paragraph::new(format!("{}{}", " > ", repl.input))
+            .block(input_box)
+            .style(Style::default().fg(Color::Yellow));
+        rect.render_widget(input, repl_chunks[1]);
+    }
+
+    if state.recent_queries_open {
+        let width = PALETTE_WIDTH.min(size.width);
+        let height = (state.recent_queries.len() as u16 + 2).min(size.height);
+        let area = Rect::new(size.x + (size.width - width) / 2, size.y + (size.height - height) / 2, width, height);
+
+        let (block, list) = render::render_recent_queries_popup(state.recent_queries.make_contiguous());
+        let list_area = block.inner(area);
+        rect.render_widget(Clear, area);
+        rect.render_widget(block, area);
+        rect.render_stateful_widget(list, list_area, &mut state.recent_queries_state);
+    }
+
+    if let Some(palette) = &mut state.palette {
+        let width = PALETTE_WIDTH.min(size.width);
+        let height = PALETTE_HEIGHT.min(size.height);
+        let area = Rect::new(size.x + (size.width - width) / 2, size.y + (size.height - height) / 2, width, height);
+        let palette_chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(0)].as_ref())
+            .split(area);
+
+        let input_box = focus_border(Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::Yellow))
+            .title("Command Palette (Enter: run, Esc: close)")
+            .border_type(BorderType::Plain), true);
+        let input = Paragraph::new(format!("{}{}", " > ", palette.query))
+            .block(input_box)
+            .style(Style::default().fg(Color::Yellow));
+        let commands = render_palette(palette);
+
+        rect.render_widget(Clear, area);
+        rect.render_widget(input, palette_chunks[0]);
+        rect.render_stateful_widget(commands, palette_chunks[1], &mut palette.list_state);
+    }
+
+    if state.snapshot_requested {
+        rect.render_widget(BufferCapture(&mut state.snapshot), size);
+        state.snapshot_requested = false;
+    }
+}
+
+//tpedia index --dump-path <path>
+fn run_index_command(args: &[String], config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let dump_path = args
+        .iter()
+        .position(|a| a == "--dump-path")
+        .and_then(|i| args.get(i + 1))
+        .ok_or("usage: tpedia index --dump-path <articles.xml.bz2>")?;
+
+    let index_path = config
+        .local_index_path
+        .as_ref()
+        .map(PathBuf::from)
+        .or_else(config::default_index_path)
+        .ok_or("no local_index_path configured")?;
+
+    println!("Indexing {} into {}", dump_path, index_path.display());
+    let indexed = local_index::build_index(Path::new(dump_path), &index_path)?;
+    println!("Indexed {} articles, set local_index_path = \"{}\" in the config to use it", indexed, index_path.display());
+
+    Ok(())
+}
+
+//tpedia --batch <queries.txt> --out <dir>
+fn run_batch_command(args: &[String], config: &Config) -> Result<(), Box<dyn std::error::Error>> {
+    let usage = "usage: tpedia --batch <queries.txt> --out <dir>";
+    let arg_value = |name: &str| args.iter().position(|a| a == name).and_then(|i| args.get(i + 1));
+
+    let queries_path = arg_value("--batch").ok_or(usage)?;
+    let out_dir = arg_value("--out").ok_or(usage)?;
+
+    let client = WikipediaClient::new(config)?;
+    let rt = tokio::runtime::Runtime::new()?;
+    let summary = rt.block_on(batch::run_batch(&client, Path::new(queries_path), Path::new(out_dir)))?;
+
+    let report = batch::summary_report(&summary);
+    fs::write(Path::new(out_dir).join("summary.txt"), &report)?;
+    print!("{}", report);
+
+    Ok(())
+}
+
+fn reload_config(state: &mut AppState) -> Result<(), ConfigError> {
+    let config = config::load_config()?;
+
+    //Build the client first so an invalid proxy keeps the old settings
+    state.client = WikipediaClient::new(&config)?;
+    state.tick_ms.store(config.tick_ms, Ordering::Relaxed);
+    state.config = config;
+
+    Ok(())
+}
+
+//Alt+Enter is "I'm Feeling Lucky" and always opens the top result.
+//A newer submit replaces the pending one, the results come back as Event::SearchFinished
+fn submit_search(state: &mut AppState, feeling_lucky: bool, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let query = state.search_string.clone();
+    let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
+    state.pending_submit = Some((query.clone(), cancel_tx));
+    state.status_message = Some(format!("Searching for '{}'…", query));
+    leave_search(state);
+
+    let client = state.client.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = tokio::select! {
+            _ = &mut cancel_rx => return,
+            result = client.search(&query) => result,
+        };
+        let _ = tx.send(Event::SearchFinished(query, feeling_lucky, result));
+    });
+}
+
+fn finish_search(state: &mut AppState, query: String, feeling_lucky: bool, result: Result<SearchResponse, Error>) {
+    if state.status_message.as_deref() == Some(format!("Searching for '{}'…", query).as_str()) {
+        state.status_message = None;
+    }
+
+    match result {
+        Ok(res) => {
+            state.last_failure = None;
+            record_navigation(state);
+            push_recent_query(state, &query, false);
+            history::push_history(&mut state.search_history, &query, res.query.searchinfo.totalhits, state.config.history_limit);
+            if let Err(e) = history::save_history(&state.search_history) {
+                state.status_message = Some(e.to_string());
+            }
+
+            if state.config.new_search == NewSearchMode::Stack && !state.current_search_results.is_empty() {
+                let layer = take_results_layer(state);
+                results_stack::push_layer(&mut state.results_stack, layer, state.config.results_stack_depth);
+            }
+
+            let open_directly = feeling_lucky
+                || (state.config.feeling_lucky && is_unique_match(&res, &query));
+
+            state.search_continuation = res.next_offset().map(|offset| (query.clone(), offset));
+            state.results_kind = ResultsKind::Search;
+            state.results_query = query;
+            set_pagination(state, res.query.searchinfo.totalhits);
+            let found = !res.query.search.is_empty();
+            state.current_search_results = pins::pin_first(res.query.search, &state.pinned_results);
+            state.size_filter = SizeFilter::All;
+            state.active_menu_item = MenuItem::Results;
+
+            set_article_open(state, open_directly && found);
+            state.current_article = None;
+            state.scroll = 0;
+            state.search_result_list_state.select(Some(first_unpinned(state)));
+            schedule_prefetch(state);
+        }
+        Err(e) => report_failure(state, &e, RetryAction::Search(query)),
+    }
+}
+
+//New results start on the first page
+fn set_pagination(state: &mut AppState, totalhits: i64) {
+    state.search_page = 0;
+    state.search_offset = 0;
+    state.search_total_pages = pagination::total_pages(totalhits, pagination::RESULTS_PER_PAGE);
+}
+
+//']' and '[' stop at the first and the last page
+fn turn_search_page(state: &mut AppState, forward: bool, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    if forward && state.search_page + 1 < state.search_total_pages {
+        let offset = pagination::next_page_offset(state.search_offset, pagination::RESULTS_PER_PAGE);
+        load_search_page(state, state.search_page + 1, offset, tx);
+    } else if !forward && state.search_page > 0 {
+        let offset = pagination::previous_page_offset(state.search_offset, pagination::RESULTS_PER_PAGE);
+        load_search_page(state, state.search_page - 1, offset, tx);
+    }
+}
+
+//Replaces the results with another page of the same search
+fn load_search_page(state: &mut AppState, page: u32, offset: i64, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let client = state.client.clone();
+    let query = state.results_query.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.search_from(&query, offset).await;
+        let _ = tx.send(Event::SearchPageFetched(query, page, offset, result));
+    });
+}
+
+//Dropped if the list changed meanwhile or the page is shown already
+fn finish_search_page(state: &mut AppState, query: String, page: u32, offset: i64, result: Result<SearchResponse, Error>) {
+    if state.results_query != query || state.search_offset == offset {
+        return;
+    }
+
+    match result {
+        Ok(res) if !res.query.search.is_empty() => {
+            record_navigation(state);
+            state.search_continuation = res.next_offset().map(|offset| (query, offset));
+            state.current_search_results = pins::pin_first(res.query.search, &state.pinned_results);
+            state.search_result_list_state.select(Some(first_unpinned(state)));
+            state.duplicate_results.clear();
+            state.search_page = page;
+            state.search_offset = offset;
+        }
+        //totalhits is only an estimate, the real last page came before
+        Ok(_) => {
+            state.search_total_pages = page;
+            state.status_message = Some(String::from("No more results"));
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+//'m' appends the next batch to the list
+fn load_more_results(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let (query, offset) = match state.search_continuation.clone() {
+        Some(continuation) => continuation,
+        None => {
+            state.status_message = Some(format!("All {} results loaded", state.current_search_results.len()));
+            return;
+        }
+    };
+
+    let client = state.client.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.search_from(&query, offset).await;
+        let _ = tx.send(Event::MoreResultsFetched(query, offset, result));
+    });
+}
+
+//Dropped if the list changed meanwhile or the batch was appended already
+fn finish_more_results(state: &mut AppState, query: String, offset: i64, result: Result<SearchResponse, Error>) {
+    if state.search_continuation.as_ref().is_none_or(|(q, o)| *q != query || *o != offset) {
+        return;
+    }
+
+    match result {
+        Ok(res) => {
+            state.search_continuation = res.next_offset().map(|offset| (query, offset));
+            //The last loaded batch counts as the current page, ']' continues after it
+            state.search_offset = offset;
+            state.search_page = pagination::page_of(offset, pagination::RESULTS_PER_PAGE);
+            let pinned = &state.pinned_results;
+            let others = res.query.search.into_iter().filter(|s| !pins::is_pinned(pinned, s.pageid));
+            state.current_search_results.extend(others);
+
+            state.status_message = match state.search_continuation {
+                Some(_) => Some(format!("{} results loaded, 'm' loads more", state.current_search_results.len())),
+                None => Some(format!("All {} results loaded", state.current_search_results.len())),
+            };
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+//The first 'c' remembers the selected article, the second opens both side by side
+fn pick_compare_article(state: &mut AppState) {
+    let selected = match safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        Some(selected) => selected.clone(),
+        None => return,
+    };
+
+    match state.compare_first.take() {
+        Some(first) => {
+            state.status_message = None;
+            state.compare = Some(CompareView::new(first, selected));
+        }
+        None => {
+            state.status_message = Some(format!("Comparing {}, select a second article and press 'c'", selected.title));
+            state.compare_first = Some(selected);
+        }
+    }
+}
+
+//Links only carry a title, the article is looked up with a search for it
+//Following a link from an open article extends the breadcrumb
+fn open_article_by_title(state: &mut AppState, title: &str, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let mut trail = state.breadcrumbs.clone();
+    if let (true, Some(current)) = (state.is_selected, safe_get_selected(&state.current_search_results, &state.search_result_list_state)) {
+        trail.push(current.title.clone());
+    }
+    open_titled_article(state, title, trail, tx);
+}
+
+//Ctrl+H reports a bug, F2 does the same where Ctrl+H arrives as Backspace
+fn is_bug_report_key(event: &KeyEvent) -> bool {
+    event.code == KeyCode::F(2) || (event.code == KeyCode::Char('h') && event.modifiers.contains(KeyModifiers::CONTROL))
+}
+
+//Backspace goes back to the previous article of the breadcrumb.
+//Coming from a talk page the results it covered are restored instead of searched again
+fn breadcrumb_back(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let mut trail = state.breadcrumbs.clone();
+    let covered = state.results_stack.last().and_then(|layer| layer.selected.and_then(|i| layer.results.get(i)));
+
+    match trail.pop() {
+        Some(title) if covered.is_some_and(|article| article.title == title) => {
+            record_navigation(state);
+            if let Some(layer) = state.results_stack.pop() {
+                restore_results_layer(state, layer);
+            }
+            open_selected_article(state);
+            state.breadcrumbs = trail;
+        }
+        Some(title) => open_titled_article(state, &title, trail, tx),
+        None => state.status_message = Some(String::from("No previous article")),
+    }
+}
+
+//Esc goes back to the list the last search covered
+fn pop_results_layer(state: &mut AppState) {
+    if let Some(layer) = state.results_stack.pop() {
+        state.status_message = Some(format!("Back to the results for '{}'", layer.query));
+        restore_results_layer(state, layer);
+    }
+}
+
+//Moves the shown results into a layer for the results stack
+fn take_results_layer(state: &mut AppState) -> ResultsLayer {
+    ResultsLayer {
+        kind: state.results_kind,
+        query: std::mem::take(&mut state.results_query),
+        results: std::mem::take(&mut state.current_search_results),
+        selected: state.search_result_list_state.selected(),
+        continuation: state.search_continuation.take(),
+        page: state.search_page,
+        offset: state.search_offset,
+        total_pages: state.search_total_pages,
+    }
+}
+
+//The list comes back on the page it was left on
+fn restore_results_layer(state: &mut AppState, layer: ResultsLayer) {
+    state.current_search_results = layer.results;
+    state.results_kind = layer.kind;
+    state.results_query = layer.query;
+    state.search_page = layer.page;
+    state.search_offset = layer.offset;
+    state.search_total_pages = layer.total_pages;
+    state.search_result_list_state.select(layer.selected);
+    state.search_continuation = layer.continuation;
+}
+
+//The talk page covers the results like a new search, Backspace returns to the article
+fn open_talk_page(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let current = match safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        Some(current) => current.clone(),
+        None => return,
+    };
+    let title = match wikimedia_types::talk_page_title(current.ns, &current.title) {
+        Some(title) => title,
+        None => {
+            state.status_message = Some(format!("{} is a talk page", current.title));
+            return;
+        }
+    };
+
+    let client = state.client.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_page_info(&title).await;
+        let _ = tx.send(Event::TalkPageFetched(current.pageid, result));
+    });
+}
+
+//Dropped if another article was selected meanwhile
+fn finish_talk_page(state: &mut AppState, pageid: i64, result: Result<Option<Search>, Error>) {
+    let current = match safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        Some(current) if current.pageid == pageid => current.clone(),
+        _ => return,
+    };
+
+    match result {
+        Ok(Some(talk)) => {
+            record_navigation(state);
+            let mut trail = std::mem::take(&mut state.breadcrumbs);
+            trail.push(current.title);
+            state.scroll_positions.insert(current.pageid, state.scroll_target.unwrap_or(state.scroll));
+
+            let layer = take_results_layer(state);
+            //The layer is what Backspace returns to, so it is kept even with stacking turned off
+            results_stack::push_layer(&mut state.results_stack, layer, state.config.results_stack_depth.max(1));
+            state.results_kind = ResultsKind::Search;
+            state.results_query = talk.title.clone();
+            set_pagination(state, 0);
+            state.current_search_results = vec![talk];
+            state.search_result_list_state.select(Some(0));
+            state.active_menu_item = MenuItem::Results;
+            open_selected_article(state);
+            state.breadcrumbs = trail;
+        }
+        Ok(None) => state.status_message = Some(format!("{} has no talk page", current.title)),
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+//The article opens once the search for its title is back
+fn open_titled_article(state: &mut AppState, title: &str, trail: Vec<String>, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    state.pending_title = Some(title.to_string());
+
+    let client = state.client.clone();
+    let title = title.to_string();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.search(&title).await;
+        let _ = tx.send(Event::TitledArticleFetched(title, trail, result));
+    });
+}
+
+fn finish_titled_article(state: &mut AppState, title: String, trail: Vec<String>, result: Result<SearchResponse, Error>) {
+    match result {
+        Ok(res) if !res.query.search.is_empty() => {
+            record_navigation(state);
+            let index = res.query.search.iter().position(|s| s.title == title).unwrap_or(0);
+
+            state.search_continuation = res.next_offset().map(|offset| (title.clone(), offset));
+            state.results_kind = ResultsKind::Search;
+            state.results_query = title.clone();
+            set_pagination(state, res.query.searchinfo.totalhits);
+            state.current_search_results = res.query.search;
+            state.search_result_list_state.select(Some(index));
+            state.active_menu_item = MenuItem::Results;
+            open_selected_article(state);
+            state.breadcrumbs = trail;
+        }
+        Ok(_) => state.status_message = Some(format!("{} not found", title)),
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+const INCREMENTAL_SEARCH_DELAY: Duration = Duration::from_millis(400);
+
+//Searches once the user stopped typing for a moment, every keystroke replaces the pending search
+fn schedule_incremental_search(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let query = state.search_string.clone();
+    if query.trim().chars().count() < 3 {
+        state.pending_search = None;
+        return;
+    }
+
+    let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
+    state.pending_search = Some(cancel_tx);
+
+    let client = state.client.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        tokio::select! {
+            _ = &mut cancel_rx => return,
+            _ = tokio::time::sleep(INCREMENTAL_SEARCH_DELAY) => {}
+        }
+
+        let result = tokio::select! {
+            _ = &mut cancel_rx => return,
+            result = client.search(&query) => result,
+        };
+
+        let _ = match result {
+            Ok(res) => tx.send(Event::SearchResults(query, res)),
+            Err(e) => tx.send(Event::Status(e.to_string())),
+        };
+    });
+}
+
+//The infobox is split off before cleaning, so with the infobox panel the fetch goes through fetch_article.
+//An open article that kept its parse HTML is only converted again
+fn schedule_article_fetch(state: &mut AppState, search: &Search, text_width: u16, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
+    state.pending_article = Some((search.pageid, cancel_tx));
+
+    let client = state.client.clone();
+    let pageid = search.pageid;
+    let with_infobox = state.show_infobox;
+    let raw_html = state.current_article.as_ref().filter(|a| a.search.pageid == pageid).and_then(|a| a.raw_html.clone());
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let fetch = async {
+            match raw_html {
+                Some(html) => Ok(client::convert_article(&html, text_width, with_infobox)),
+                None if with_infobox => client.fetch_article(pageid as usize, text_width).await,
+                None => client.fetch_html(pageid as usize, text_width).await.map(|content| (content, Vec::new())),
+            }
+        };
+
+        let result = tokio::select! {
+            _ = &mut cancel_rx => return,
+            result = fetch => result,
+        };
+        let _ = tx.send(Event::ArticleFetched(pageid, text_width, result));
+    });
+}
+
+//A failed fetch isn't tried again until the compare view is opened again
+fn schedule_compare_fetch(state: &AppState, pageid: i64, text_width: u16, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let client = state.client.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_html(pageid as usize, text_width).await;
+        let _ = tx.send(Event::CompareFetched(pageid, result));
+    });
+}
+
+fn schedule_prefetch(state: &mut AppState) {
+    state.prefetch = None;
+    let top = match state.current_search_results.first() {
+        Some(top) if state.config.prefetch_top_result && !state.is_selected => top.pageid,
+        _ => return,
+    };
+
+    let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
+    state.prefetch = Some((top, cancel_tx));
+
+    let client = state.client.clone();
+    state.runtime.spawn(async move {
+        //Opening the article fetches it anyway, so errors are ignored
+        tokio::select! {
+            _ = &mut cancel_rx => {}
+            _ = client.prefetch(top as usize) => {}
+        }
+    });
+}
+
+const SUGGESTIONS_DELAY: Duration = Duration::from_millis(150);
+
+//History entries are found locally, so they show up right away
+fn update_suggestions(state: &mut AppState) {
+    state.suggestion_index = None;
+    if state.search_string.trim().is_empty() {
+        state.suggestions.clear();
+        return;
+    }
+
+    let history = history::history_completions(&state.search_history, &state.search_string);
+    let prefix = state.search_string.to_lowercase();
+    let api: Vec<String> = state.api_suggestions.iter().filter(|a| a.to_lowercase().starts_with(&prefix)).cloned().collect();
+
+    state.suggestions = suggestions::merge_suggestions(&history, &api, state.config.suggestions_history, state.config.suggestions_api);
+}
+
+//Fetched in the background, typing replaces the pending request like with the incremental search
+fn schedule_api_suggestions(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let query = state.search_string.clone();
+    if query.trim().is_empty() || state.config.suggestions_api == 0 {
+        state.pending_suggestions = None;
+        return;
+    }
+
+    let (cancel_tx, mut cancel_rx) = oneshot::channel::<()>();
+    state.pending_suggestions = Some(cancel_tx);
+
+    let client = state.client.clone();
+    let limit = state.config.suggestions_api;
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        tokio::select! {
+            _ = &mut cancel_rx => return,
+            _ = tokio::time::sleep(SUGGESTIONS_DELAY) => {}
+        }
+
+        //Suggestions are optional, a failed request just leaves the dropdown as it is
+        let result = tokio::select! {
+            _ = &mut cancel_rx => return,
+            result = client.opensearch(&query, limit) => result,
+        };
+        if let Ok(titles) = result {
+            let _ = tx.send(Event::Suggestions(query, titles));
+        }
+    });
+}
+
+fn spelling_prompt(suggestion: &str) -> String {
+    format!("Did you mean: '{}'? [y to accept, Enter to search anyway]", suggestion)
+}
+
+fn is_unique_match(res: &SearchResponse, search_term: &str) -> bool {
+    if res.query.searchinfo.totalhits == 1 {
+        return true;
+    }
+
+    res.query.search
+        .first()
+        .map(|s| s.title.to_lowercase() == search_term.trim().to_lowercase())
+        .unwrap_or(false)
+}
+
+fn render_home<'a>() -> Paragraph<'a> {
+    let home = Paragraph::new(vec![
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Welcome")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("to")]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::styled(
+            "xPedia",
+            Style::default().fg(Color::LightBlue),
+        )]),
+        Spans::from(vec![Span::raw("")]),
+        Spans::from(vec![Span::raw("Press 's' to search")]),
+        Spans::from(vec![Span::raw("Alt+Enter opens the top result directly")]),
+        Spans::from(vec![Span::raw("Press 'v' for recently viewed articles")]),
+        Spans::from(vec![Span::raw("Tab moves between search box, results and article")]),
+    ])
+    .alignment(Alignment::Center)
+    .block(
+        Block::default()
+            .borders(Borders::ALL)
+            .style(Style::default().fg(Color::White))
+            .title("Home")
+            .border_type(BorderType::Plain),
+    );
+    home
+}
+
+//Falls back to the list that has entries if one of them is empty
+fn home_presets_focused(state: &AppState) -> bool {
+    !state.config.search_presets.is_empty() && (state.presets_focused || state.recent_articles.is_empty())
+}
+
+fn save_preset(state: &mut AppState, name: &str) {
+    state.config.search_presets.insert(name.to_string(), state.search_string.clone());
+    state.status_message = match config::save_config(&state.config, &["search_presets"]) {
+        Ok(()) => Some(format!("Saved preset {}", name)),
+        Err(e) => Some(e.to_string()),
+    };
+}
+
+//Without smooth_scroll the article jumps to the target right away
+fn scroll_to(state: &mut AppState, target: u16) {
+    if state.config.smooth_scroll {
+        state.scroll_target = Some(target);
+    } else {
+        state.scroll = target;
+        state.scroll_target = None;
+    }
+}
+
+//The search box hands the focus to the article if one is open
+fn leave_search(state: &mut AppState) {
+    state.focus = if state.is_selected { FocusedPane::ArticleContent } else { FocusedPane::ResultsList };
+}
+
+//Opening or closing the article moves the focus along unless it is on the search box, menu or status bar
+fn set_article_open(state: &mut AppState, open: bool) {
+    state.is_selected = open;
+    if matches!(state.focus, FocusedPane::ResultsList | FocusedPane::ArticleContent | FocusedPane::Toc) {
+        state.focus = if open { FocusedPane::ArticleContent } else { FocusedPane::ResultsList };
+    }
+}
+
+fn focus_border(block: Block, focused: bool) -> Block {
+    if focused {
+        block.border_style(Style::default().fg(Color::Yellow)).border_type(BorderType::Thick)
+    } else {
+        block
+    }
+}
+
+//Menu -> search box -> result list -> article -> contents -> status bar -> menu
+fn cycle_focus(state: &mut AppState) {
+    match state.focus {
+        FocusedPane::MenuBar => state.focus = FocusedPane::SearchBox,
+        FocusedPane::SearchBox => {
+            state.focus = FocusedPane::ResultsList;
+            set_article_open(state, false);
+            state.active_menu_item = MenuItem::Results;
+        }
+        FocusedPane::ResultsList if state.active_menu_item != MenuItem::Results => state.active_menu_item = MenuItem::Results,
+        FocusedPane::ResultsList => {
+            open_selected_article(state);
+
+            //Nothing to read yet, so the article and its contents are skipped
+            if !state.is_selected {
+                state.focus = FocusedPane::StatusBar;
+            }
+        }
+        FocusedPane::ArticleContent => {
+            let outline = article_outline(state);
+            if outline.is_empty() {
+                state.focus = FocusedPane::StatusBar;
+            } else {
+                //The contents start at the section that is being read
+                let current = outline.iter().rposition(|(_, line)| *line <= state.scroll as usize);
+                state.toc_list_state.select(Some(current.unwrap_or(0)));
+                state.focus = FocusedPane::Toc;
+            }
+        }
+        FocusedPane::Toc => state.focus = FocusedPane::StatusBar,
+        FocusedPane::StatusBar => state.focus = FocusedPane::MenuBar,
+    }
+}
+
+//Headings of the open article as it is shown, collapsed sections hide their subsections
+fn article_outline(state: &AppState) -> Vec<(String, usize)> {
+    match &state.current_article {
+        Some(article) => {
+            let collapsed = state.collapsed_sections.get(&article.search.pageid).cloned().unwrap_or_default();
+            sections::outline(&sections::fold(&article.content, &collapsed).0)
+        }
+        None => Vec::new(),
+    }
+}
+
+fn copy_search_string(state: &mut AppState) {
+    state.status_message = match clipboard::copy_to_clipboard(&state.search_string) {
+        Ok(clipboard::CopyMethod::System) => Some(format!("Copied '{}'", state.search_string)),
+        Ok(clipboard::CopyMethod::Terminal) => Some(format!("Copied '{}' through the terminal", state.search_string)),
+        Err(e) => Some(format!("Copying failed: {}", e)),
+    };
+}
+
+fn navigation_snapshot(state: &AppState) -> NavigationSnapshot {
+    NavigationSnapshot {
+        kind: state.results_kind,
+        query: state.results_query.clone(),
+        results: state.current_search_results.clone(),
+        selected: state.search_result_list_state.selected(),
+        article_open: state.is_selected,
+        scroll: state.scroll_target.unwrap_or(state.scroll),
+        breadcrumbs: state.breadcrumbs.clone(),
+    }
+}
+
+//Ctrl+U restores the view before the last search or opened article, Ctrl+Y the one after it
+fn undo_navigation(state: &mut AppState, action: AppAction) {
+    let current = navigation_snapshot(state);
+    let target = match action {
+        AppAction::Redo => state.undo_history.redo(current),
+        _ => state.undo_history.undo(current),
+    };
+    match target {
+        Some(snapshot) => restore_navigation(state, snapshot),
+        None => state.status_message = Some(String::from("Nothing to go back or forward to")),
+    }
+}
+
+//Called before a search or an opened article changes what is shown
+fn record_navigation(state: &mut AppState) {
+    let snapshot = navigation_snapshot(state);
+    state.undo_history.record(snapshot);
+}
+
+//The article is fetched again, usually from the cache
+fn restore_navigation(state: &mut AppState, snapshot: NavigationSnapshot) {
+    state.results_kind = snapshot.kind;
+    state.results_query = snapshot.query;
+    state.current_search_results = snapshot.results;
+    state.search_result_list_state.select(snapshot.selected);
+    state.search_continuation = None;
+    set_pagination(state, 0);
+    state.active_menu_item = MenuItem::Results;
+    set_article_open(state, snapshot.article_open);
+    state.current_article = None;
+    state.find = None;
+    state.breadcrumbs = snapshot.breadcrumbs;
+    state.scroll = snapshot.scroll;
+    state.scroll_target = None;
+}
+
+//Opens the selected result where it was left, if it was read before in this session
+fn open_selected_article(state: &mut AppState) {
+    let selected = safe_get_selected(&state.current_search_results, &state.search_result_list_state).map(|s| s.pageid);
+
+    state.breadcrumbs.clear();
+    state.find = None;
+    set_article_open(state, selected.is_some());
+    state.current_article = None;
+    state.scroll = selected.and_then(|pageid| state.scroll_positions.get(&pageid).copied()).unwrap_or(0);
+    state.scroll_target = None;
+}
+
+fn close_article(state: &mut AppState) {
+    if let Some(selected) = safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        state.scroll_positions.insert(selected.pageid, state.scroll_target.unwrap_or(state.scroll));
+    }
+
+    set_article_open(state, false);
+    state.scroll_target = None;
+}
+
+//Ctrl+W, unlike Esc the position is forgotten, the article opens at the top next time
+fn discard_article(state: &mut AppState) {
+    if let Some(selected) = safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        state.scroll_positions.remove(&selected.pageid);
+    }
+
+    set_article_open(state, false);
+    state.current_article = None;
+    state.scroll = 0;
+    state.scroll_target = None;
+    if state.current_search_results.is_empty() {
+        state.active_menu_item = MenuItem::Home;
+    }
+
+    brief_status(state, String::from("Article closed"));
+}
+
+//Lines of the open article that contain the find query, collapsed sections are skipped
+fn find_lines(state: &AppState) -> Vec<usize> {
+    match (&state.current_article, &state.find) {
+        (Some(article), Some(query)) => match state.collapsed_sections.get(&article.search.pageid) {
+            Some(collapsed) if !collapsed.is_empty() => find::matching_lines(&sections::fold(&article.content, collapsed).0, query),
+            _ => find::matching_lines(&article.content, query),
+        },
+        _ => Vec::new(),
+    }
+}
+
+//Ctrl+N, the query starts as the find match on the top line, or the query while it has
+//matches elsewhere, else the first word of the top line
+fn search_from_article(state: &mut AppState) {
+    let article = match &state.current_article {
+        Some(article) => article,
+        None => return,
+    };
+    let text = match state.collapsed_sections.get(&article.search.pageid) {
+        Some(collapsed) if !collapsed.is_empty() => sections::fold(&article.content, collapsed).0,
+        _ => article.content.clone(),
+    };
+    let line = text.lines().nth(state.scroll as usize).unwrap_or("");
+
+    let matched = state.find.as_ref().and_then(|query| match find::find_in_line(line, query).first() {
+        Some(&(start, end)) => Some(line[start..end].to_string()),
+        None if !find_lines(state).is_empty() => Some(query.clone()),
+        None => None,
+    });
+    let query = matched.or_else(|| find::first_word(line).map(str::to_string)).unwrap_or_default();
+
+    state.prompt = Some(Prompt { kind: PromptKind::Search, text: query });
+    state.focus = FocusedPane::SearchBox;
+}
+
+//An empty query removes the highlight
+fn start_find(state: &mut AppState, query: &str) {
+    state.focus = FocusedPane::ArticleContent;
+    state.find = Some(query.to_string()).filter(|q| !q.is_empty());
+
+    //A match on the top line is already in view
+    if state.find.is_some() && !find_lines(state).contains(&(state.scroll as usize)) {
+        find_next(state, false);
+    }
+}
+
+fn find_next(state: &mut AppState, backwards: bool) {
+    let query = match &state.find {
+        Some(query) => query.clone(),
+        None => return,
+    };
+    let lines = find_lines(state);
+
+    match find::next_match(&lines, state.scroll_target.unwrap_or(state.scroll) as usize, backwards) {
+        Some(line) => {
+            let index = lines.iter().position(|&l| l == line).unwrap_or(0);
+            state.status_message = Some(format!("'{}' {} of {}", query, index + 1, lines.len()));
+            scroll_to(state, line as u16);
+        }
+        None => state.status_message = Some(format!("No matches for '{}'", query)),
+    }
+}
+
+//Ctrl+F, the selection moves to the first shown result if the filter hides it
+fn cycle_size_filter(state: &mut AppState) {
+    state.size_filter = state.size_filter.next();
+    let loaded = state.current_search_results.len();
+    let selected = state.search_result_list_state.selected();
+
+    match filtered_results(state) {
+        Some(indices) => {
+            if !selected.is_some_and(|s| indices.contains(&s)) {
+                state.search_result_list_state.select(indices.first().copied());
+            }
+            state.status_message = Some(format!("Filter {}: {} of {} results", state.size_filter.label(), indices.len(), loaded));
+        }
+        None => {
+            if selected.is_none() && loaded != 0 {
+                state.search_result_list_state.select(Some(0));
+            }
+            state.status_message = Some(format!("Showing all {} results", loaded));
+        }
+    }
+}
+
+//Positions of the results shown under the size filter, pinned results are always shown
+fn filtered_results(state: &AppState) -> Option<Vec<usize>> {
+    let mut indices = analysis::filtered_indices(&state.current_search_results, state.size_filter)?;
+    let results = &state.current_search_results;
+    indices.extend((0..results.len()).filter(|&i| pins::is_pinned(&state.pinned_results, results[i].pageid)));
+    indices.sort_unstable();
+    indices.dedup();
+    Some(indices)
+}
+
+//Selected after a search, the pinned results above it are already known
+fn first_unpinned(state: &AppState) -> usize {
+    let results = &state.current_search_results;
+    let pinned = results.iter().take_while(|s| pins::is_pinned(&state.pinned_results, s.pageid)).count();
+    pinned.min(results.len().saturating_sub(1))
+}
+
+//'P' pins or unpins the selected result and moves it, the selection follows it
+fn toggle_pinned_result(state: &mut AppState) {
+    let selected = match safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        Some(selected) => selected.clone(),
+        None => return,
+    };
+
+    let pinned = pins::toggle_pin(&mut state.pinned_results, &selected);
+    let results = std::mem::take(&mut state.current_search_results);
+    state.current_search_results = pins::pin_first(results, &state.pinned_results);
+    state.search_result_list_state.select(state.current_search_results.iter().position(|s| s.pageid == selected.pageid));
+    brief_status(state, if pinned {
+        format!("Pinned {}", selected.title)
+    } else {
+        format!("Unpinned {}", selected.title)
+    });
+}
+
+//Line of the next or previous visible heading. Past the last heading PageDown
+//scrolls a page, before the first PageUp goes to the top
+fn section_jump(state: &AppState, from: u16, forward: bool) -> u16 {
+    let headings = match &state.current_article {
+        Some(article) => {
+            let collapsed = state.collapsed_sections.get(&article.search.pageid).cloned().unwrap_or_default();
+            sections::fold(&article.content, &collapsed).1
+        }
+        None => Vec::new(),
+    };
+
+    match sections::neighbour_heading(&headings, from as usize, forward) {
+        Some(line) => line.try_into().unwrap_or(u16::MAX),
+        None if forward => from.saturating_add(state.page_height),
+        None => 0,
+    }
+}
+
+//'z' folds or unfolds the section at the top of the pane, 'Z' folds and 'X' unfolds all.
+//The view then starts at the heading of that section
+fn fold_sections(state: &mut AppState, code: KeyCode) {
+    let (pageid, content) = match &state.current_article {
+        Some(article) => (article.search.pageid, &article.content),
+        None => return,
+    };
+
+    let collapsed = state.collapsed_sections.entry(pageid).or_default();
+    let (_, headings) = sections::fold(content, collapsed);
+    let current = sections::section_at(&headings, state.scroll as usize);
+
+    match code {
+        KeyCode::Char('z') => {
+            if let Some(section) = current {
+                if !collapsed.remove(&section) {
+                    collapsed.insert(section);
+                }
+            }
+        }
+        KeyCode::Char('Z') => *collapsed = (0..sections::heading_count(content)).collect(),
+        _ => collapsed.clear(),
+    }
+
+    let (_, headings) = sections::fold(content, collapsed);
+    state.scroll = current.and_then(|section| sections::heading_line(&headings, section)).unwrap_or(0) as u16;
+    state.scroll_target = None;
+}
+
+fn raw_html_shown(state: &AppState) -> Option<&String> {
+    let raw_pageid = state.raw_view.map(|(pageid, _)| pageid);
+    state.current_article.as_ref().filter(|a| raw_pageid == Some(a.search.pageid)).and_then(|a| a.raw_html.as_ref())
+}
+
+//Words of the search behind the result list, lists that are no search have none
+fn highlighted_terms(state: &AppState) -> Vec<String> {
+    if !state.highlight_terms || state.results_kind != ResultsKind::Search {
+        return Vec::new();
+    }
+    find::query_terms(&state.results_query)
+}
+
+//The pageviews of a day are only complete once it ended, so the newest list is yesterday's.
+//A list older than TRENDING_MAX_AGE is fetched again and comes back as Event::TrendingFetched
+fn show_trending(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    if state.config.api_url.is_some() {
+        state.status_message = Some(String::from("Trending articles are only available for Wikipedia"));
+        return;
+    }
+
+    if state.trending.as_ref().is_some_and(|(fetched_at, ..)| fetched_at.elapsed() < TRENDING_MAX_AGE) {
+        show_trending_list(state);
+        return;
+    }
+
+    state.status_message = Some(String::from("Loading the trending articles…"));
+    let client = state.client.clone();
+    let project = format!("{}.wikipedia", state.config.language);
+    let yesterday = chrono::Utc::now().naive_utc().date() - chrono::Duration::days(1);
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_top_viewed(&project, yesterday, TRENDING_ARTICLES).await;
+        let _ = tx.send(Event::TrendingFetched(result));
+    });
+}
+
+fn finish_trending(state: &mut AppState, result: Result<Vec<(Search, u64)>, Error>) {
+    match result {
+        Ok(top) => {
+            state.status_message = None;
+            let views = top.iter().map(|(search, views)| (search.pageid, *views)).collect();
+            let results = top.into_iter().map(|(search, _)| search).collect();
+            state.trending = Some((Instant::now(), results, views));
+            show_trending_list(state);
+        }
+        Err(e) => state.status_message = Some(format!("Loading the trending articles failed: {}", e)),
+    }
+}
+
+fn show_trending_list(state: &mut AppState) {
+    if let Some(results) = state.trending.as_ref().map(|(_, results, _)| results.clone()) {
+        record_navigation(state);
+        state.current_search_results = results;
+        state.results_kind = ResultsKind::Trending;
+        state.results_query = String::from(TRENDING_LIST);
+        set_pagination(state, 0);
+        state.search_continuation = None;
+        state.search_result_list_state.select(if state.current_search_results.is_empty() { None } else { Some(0) });
+        state.active_menu_item = MenuItem::Results;
+        set_article_open(state, false);
+        state.current_article = None;
+        state.scroll = 0;
+        if state.focus == FocusedPane::SearchBox {
+            state.focus = FocusedPane::ResultsList;
+        }
+    }
+}
+
+//Ctrl+O, the newest query is selected
+fn open_recent_queries(state: &mut AppState) {
+    if state.recent_queries.is_empty() {
+        state.status_message = Some(String::from("Nothing searched yet"));
+    } else {
+        state.recent_queries_open = true;
+        state.recent_queries_state.select(Some(0));
+    }
+}
+
+//Enter searches again, 'd' forgets the query, the popup closes with the last one
+fn recent_queries_key(state: &mut AppState, code: KeyCode, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let amount = state.recent_queries.len();
+    let selected = state.recent_queries_state.selected().unwrap_or(0);
+
+    match code {
+        KeyCode::Enter => {
+            state.recent_queries_open = false;
+            if let Some(query) = state.recent_queries.get(selected).cloned() {
+                state.search_string = query;
+                submit_search(state, false, tx);
+            }
+        }
+        KeyCode::Char('d') => {
+            state.recent_queries.remove(selected);
+            if state.recent_queries.is_empty() {
+                state.recent_queries_open = false;
+            } else {
+                state.recent_queries_state.select(Some(selected.min(amount - 2)));
+            }
+        }
+        KeyCode::Esc => state.recent_queries_open = false,
+        KeyCode::Down => state.recent_queries_state.select(Some((selected + 1) % amount)),
+        KeyCode::Up => state.recent_queries_state.select(Some((selected + amount - 1) % amount)),
+        _ => {}
+    }
+}
+
+//Moved to the front if it was searched before. Incremental searches run while typing,
+//so one that extends or shortens the newest query replaces it
+fn push_recent_query(state: &mut AppState, query: &str, incremental: bool) {
+    let query = query.trim().to_string();
+    if query.is_empty() {
+        return;
+    }
+
+    if incremental && state.recent_queries.front().is_some_and(|newest| query.starts_with(newest.as_str()) || newest.starts_with(&query)) {
+        state.recent_queries.pop_front();
+    }
+    state.recent_queries.retain(|q| *q != query);
+    state.recent_queries.push_front(query);
+    state.recent_queries.truncate(RECENT_QUERIES);
+}
+
+//Shown for BRIEF_STATUS, unless another message replaces it first
+fn brief_status(state: &mut AppState, message: String) {
+    state.status_expires = Some((Instant::now() + BRIEF_STATUS, message.clone()));
+    state.status_message = Some(message);
+}
+
+fn report_failure(state: &mut AppState, error: &Error, action: RetryAction) {
+    state.last_failure = Some(action.clone());
+    state.error_state = Some(AppError::new(error, action));
+}
+
+//Searches for the same query again or opens the same article if it is still in the results
+fn retry(state: &mut AppState, action: RetryAction, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    state.last_failure = None;
+    brief_status(state, String::from("Retrying…"));
+
+    match action {
+        RetryAction::Search(query) => {
+            state.search_string = query;
+            submit_search(state, false, tx);
+        }
+        RetryAction::OpenArticle(pageid) => match state.current_search_results.iter().position(|s| s.pageid == pageid) {
+            Some(index) => {
+                state.search_result_list_state.select(Some(index));
+                state.active_menu_item = MenuItem::Results;
+                open_selected_article(state);
+            }
+            None => state.status_message = Some(String::from("The article is no longer in the results")),
+        },
+    }
+}
+
+//Replays the action's key in the pane where the key works
+fn run_palette_action(state: &mut AppState, action: AppAction) {
+    if action.needs_article() {
+        if !state.is_selected || state.current_article.is_none() {
+            state.status_message = Some(String::from("Open an article first"));
+            return;
+        }
+        state.focus = FocusedPane::ArticleContent;
+    } else if state.focus == FocusedPane::SearchBox {
+        state.focus = FocusedPane::MenuBar;
+    }
+
+    state.replayed_key = Some(action.key());
+}
+
+//The categories of the page become the top level of the browser, for a category page these are its parents
+fn show_parent_categories(state: &mut AppState, title: &str, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let client = state.client.clone();
+    let title = title.to_string();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_categories(&title).await;
+        let _ = tx.send(Event::CategoriesFetched(title, result));
+    });
+}
+
+//The article's categories are fetched once and kept with it
+fn open_categories(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+
+    let title = article.search.title.clone();
+    match article.categories.clone() {
+        Some(categories) => show_category_tree(state, &title, categories),
+        None => show_parent_categories(state, &title, tx),
+    }
+}
+
+fn finish_categories(state: &mut AppState, title: String, result: Result<Vec<String>, Error>) {
+    match result {
+        Ok(categories) => {
+            if let Some(article) = state.current_article.as_mut().filter(|a| a.search.title == title) {
+                article.categories = Some(categories.clone());
+            }
+            show_category_tree(state, &title, categories);
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+fn show_category_tree(state: &mut AppState, title: &str, categories: Vec<String>) {
+    if categories.is_empty() {
+        state.status_message = Some(format!("{} is in no categories", title));
+    } else {
+        state.category_tree = Some(categories.into_iter().map(CategoryNode::new).collect());
+        state.category_state.select(Some(0));
+    }
+}
+
+//Index of the top level category the row is in
+fn selected_root(rows: &[TreeRow], selected: usize) -> usize {
+    match rows.get(selected) {
+        Some(TreeRow::Category(path)) | Some(TreeRow::Member(path, _)) => path[0],
+        None => 0,
+    }
+}
+
+fn set_category_expanded(state: &mut AppState, path: &[usize], expanded: bool) {
+    if let Some(node) = state.category_tree.as_mut().and_then(|tree| categories::node_mut(tree, path)) {
+        node.expanded = expanded;
+    }
+}
+
+//Subcategories and articles are fetched the first time, they come back as Event::CategoryMembersFetched
+fn expand_category(state: &mut AppState, path: &[usize], tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let (title, loaded) = match state.category_tree.as_ref().and_then(|tree| categories::node(tree, path)) {
+        Some(node) => (node.title.clone(), node.loaded),
+        None => return,
+    };
+
+    if loaded {
+        show_category_members(state, path);
+        return;
+    }
+
+    let client = state.client.clone();
+    let path = path.to_vec();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_category_members(&title).await;
+        let _ = tx.send(Event::CategoryMembersFetched(path, title, result));
+    });
+}
+
+//Dropped if the browser was closed or shows other categories by now
+fn finish_category_members(state: &mut AppState, path: Vec<usize>, title: String, result: Result<(Vec<String>, Vec<Search>), Error>) {
+    let node = match state.category_tree.as_mut().and_then(|tree| categories::node_mut(tree, &path)) {
+        Some(node) if node.title == title => node,
+        _ => return,
+    };
+
+    match result {
+        Ok((subcategories, members)) => {
+            node.children = subcategories.into_iter().map(CategoryNode::new).collect();
+            node.members = members;
+            node.loaded = true;
+            show_category_members(state, &path);
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+fn show_category_members(state: &mut AppState, path: &[usize]) {
+    if let Some(node) = state.category_tree.as_mut().and_then(|tree| categories::node_mut(tree, path)) {
+        if node.children.is_empty() && node.members.is_empty() {
+            state.status_message = Some(format!("{} is empty", node.title));
+        }
+        node.expanded = true;
+    }
+}
+
+//The batches loaded before stay with the article, the overlay opens once the first batch arrived
+fn open_backlinks(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let article = match state.current_article.as_mut() {
+        Some(article) => article,
+        None => return,
+    };
+
+    if article.backlinks.is_none() {
+        article.backlinks = Some(Backlinks { links: Vec::new(), continuation: None, loading: false });
+        load_backlinks(state, tx);
+    } else if article.backlinks.as_ref().is_some_and(|b| !b.links.is_empty()) {
+        state.backlinks_open = true;
+        state.backlinks_state.select(Some(0));
+    }
+}
+
+//The first batch while there are no links yet, the next one after that
+fn load_backlinks(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let (search, backlinks) = match state.current_article.as_mut() {
+        Some(WikipediaArticle { search, backlinks: Some(backlinks), .. }) if !backlinks.loading && (backlinks.links.is_empty() || backlinks.continuation.is_some()) => (search, backlinks),
+        _ => return,
+    };
+
+    backlinks.loading = true;
+    state.status_message = Some(String::from("Loading the links here…"));
+
+    let client = state.client.clone();
+    let pageid = search.pageid;
+    let title = search.title.clone();
+    let continuation = backlinks.continuation.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_backlinks(&title, continuation.as_deref()).await;
+        let _ = tx.send(Event::BacklinksFetched(pageid, result));
+    });
+}
+
+//Only opened if there is something to pick, an empty or failed first batch is fetched again next time
+fn finish_backlinks(state: &mut AppState, pageid: i64, result: Result<(Vec<String>, Option<String>), Error>) {
+    let article = match state.current_article.as_mut() {
+        Some(article) if article.search.pageid == pageid && article.backlinks.is_some() => article,
+        _ => return,
+    };
+    let first_batch = !state.backlinks_open;
+    state.status_message = None;
+
+    if let Some(backlinks) = article.backlinks.as_mut() {
+        backlinks.loading = false;
+        match result {
+            Ok((links, continuation)) => {
+                backlinks.links.extend(links);
+                backlinks.continuation = continuation;
+            }
+            Err(e) => state.status_message = Some(e.to_string()),
+        }
+    }
+
+    if !first_batch {
+        return;
+    }
+    if article.backlinks.as_ref().is_some_and(|b| !b.links.is_empty()) {
+        state.backlinks_open = true;
+        state.backlinks_state.select(Some(0));
+    } else {
+        article.backlinks = None;
+        if state.status_message.is_none() {
+            state.status_message = Some(format!("No articles link to {}", article.search.title));
+        }
+    }
+}
+
+//The parse HTML kept with the article, fetched if it has none yet
+async fn raw_html_or_fetch(client: &WikipediaClient, pageid: i64, raw_html: Option<String>) -> Result<String, Error> {
+    match raw_html {
+        Some(html) => Ok(html),
+        None => client.fetch_raw_html(pageid as usize).await,
+    }
+}
+
+//Which links are in the body comes from the parse HTML, which is usually cached already
+fn open_links(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+    if article.links.is_some() {
+        show_links(state);
+        return;
+    }
+
+    let client = state.client.clone();
+    let pageid = article.search.pageid;
+    let title = article.search.title.clone();
+    let raw_html = article.raw_html.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = async {
+            let links = client.fetch_links(&title).await?;
+            let raw_html = raw_html_or_fetch(&client, pageid, raw_html).await?;
+            Ok((links.into_iter().map(|l| l.title).collect(), raw_html))
+        };
+        let _ = tx.send(Event::LinksFetched(pageid, result.await));
+    });
+}
+
+//Dropped if another article was opened meanwhile
+fn finish_links(state: &mut AppState, pageid: i64, result: Result<(Vec<String>, String), Error>) {
+    let article = match state.current_article.as_mut() {
+        Some(article) if article.search.pageid == pageid => article,
+        _ => return,
+    };
+
+    match result {
+        Ok((links, raw_html)) => {
+            let raw_html = article.raw_html.get_or_insert(raw_html);
+            article.links = Some(links::prioritize_links(links, raw_html));
+            show_links(state);
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+fn show_links(state: &mut AppState) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+
+    match &article.links {
+        Some(links) if !links.is_empty() => {
+            state.links_open = true;
+            state.links_show_all = false;
+            state.links_state.select(Some(0));
+        }
+        _ => state.status_message = Some(format!("{} has no links", article.search.title)),
+    }
+}
+
+//The captions come from the parse HTML, which is usually cached already
+fn open_gallery(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+    if article.images.is_some() {
+        show_gallery(state);
+        return;
+    }
+
+    let client = state.client.clone();
+    let pageid = article.search.pageid;
+    let raw_html = article.raw_html.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = async {
+            let titles = client.fetch_images(pageid as usize).await?;
+            let raw_html = raw_html_or_fetch(&client, pageid, raw_html).await?;
+            Ok((titles, raw_html))
+        };
+        let _ = tx.send(Event::ImagesFetched(pageid, result.await));
+    });
+}
+
+//Dropped if another article was opened meanwhile
+fn finish_gallery(state: &mut AppState, pageid: i64, result: Result<(Vec<String>, String), Error>) {
+    let article = match state.current_article.as_mut() {
+        Some(article) if article.search.pageid == pageid => article,
+        _ => return,
+    };
+
+    match result {
+        Ok((titles, raw_html)) => {
+            let raw_html = article.raw_html.get_or_insert(raw_html);
+            article.images = Some(gallery::build_gallery(titles, raw_html));
+            show_gallery(state);
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+fn show_gallery(state: &mut AppState) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+
+    match &article.images {
+        Some(images) if !images.is_empty() => {
+            state.gallery_open = true;
+            state.gallery_state.select(Some(0));
+        }
+        _ => state.status_message = Some(format!("{} has no images", article.search.title)),
+    }
+}
+
+//Fetched on first use and kept with the article, the debug views ask for it repeatedly
+fn with_raw_html(state: &mut AppState, purpose: RawHtmlUse, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+    if article.raw_html.is_some() {
+        use_raw_html(state, purpose);
+        return;
+    }
+
+    let client = state.client.clone();
+    let pageid = article.search.pageid;
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_raw_html(pageid as usize).await;
+        let _ = tx.send(Event::RawHtmlFetched(pageid, purpose, result));
+    });
+}
+
+//Dropped if another article was opened meanwhile
+fn finish_raw_html(state: &mut AppState, pageid: i64, purpose: RawHtmlUse, result: Result<String, Error>) {
+    let article = match state.current_article.as_mut() {
+        Some(article) if article.search.pageid == pageid => article,
+        _ => return,
+    };
+
+    match result {
+        Ok(raw_html) => {
+            article.raw_html = Some(raw_html);
+            use_raw_html(state, purpose);
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+fn use_raw_html(state: &mut AppState, purpose: RawHtmlUse) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+
+    match purpose {
+        RawHtmlUse::View => {
+            state.raw_view = Some((article.search.pageid, state.scroll));
+            state.scroll = 0;
+            state.scroll_target = None;
+        }
+        RawHtmlUse::Report => {
+            let raw_html = article.raw_html.as_deref().unwrap_or_default();
+            let report = report::build_report(&article.search, &state.config, raw_html, &article.content);
+            state.status_message = match report::write_report(&article.search, &report) {
+                Ok(path) => Some(format!("Report written to {}", path.display())),
+                Err(e) => Some(format!("Writing the report failed: {}", e)),
+            };
+        }
+    }
+}
+
+//Ctrl+G, the coordinates are fetched once and kept with the article
+fn open_location(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+    if article.coordinates.is_some() {
+        show_location(state);
+        return;
+    }
+
+    let client = state.client.clone();
+    let pageid = article.search.pageid;
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = client.fetch_coordinates(pageid as usize).await;
+        let _ = tx.send(Event::CoordinatesFetched(pageid, result));
+    });
 }
 
-fn get_selected_search(search_results: Vec<Search>, search_result_list_state: &ListState) -> Search {
-    let selected_result = search_results
-        .get(
-            search_result_list_state
-                .selected()
-                .expect("there is always a selected result"),
-        )
-        .expect("no search results")
-        .clone();
+//Dropped if another article was opened meanwhile
+fn finish_location(state: &mut AppState, pageid: i64, result: Result<Option<Coordinate>, Error>) {
+    let article = match state.current_article.as_mut() {
+        Some(article) if article.search.pageid == pageid => article,
+        _ => return,
+    };
+
+    match result {
+        Ok(location) => {
+            article.coordinates = Some(location);
+            show_location(state);
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+fn show_location(state: &mut AppState) {
+    let article = match state.current_article.as_ref() {
+        Some(article) => article,
+        None => return,
+    };
+
+    match article.coordinates {
+        Some(Some(location)) if util::terminal_supports_braille() => {
+            state.geo_map = Some(GeoMap::new(&article.search.title, location));
+        }
+        Some(Some(location)) => state.status_message = Some(geo_map::osm_url(location)),
+        Some(None) => state.status_message = Some(format!("{} has no coordinates", article.search.title)),
+        None => {}
+    }
+}
+
+//F8, the map takes several requests in a row
+fn open_concept_map(state: &mut AppState, tx: &mpsc::Sender<Event<KeyEvent>>) {
+    let selected = match safe_get_selected(&state.current_search_results, &state.search_result_list_state) {
+        Some(selected) => selected.clone(),
+        None => return,
+    };
+    state.status_message = Some(format!("Building the concept map of {}…", selected.title));
+
+    let client = state.client.clone();
+    let tx = tx.clone();
+    state.runtime.spawn(async move {
+        let result = concept_map::build_concept_map(&client, &selected).await;
+        let _ = tx.send(Event::ConceptMapBuilt(selected.title, result));
+    });
+}
+
+//Dropped if another article was selected meanwhile
+fn finish_concept_map(state: &mut AppState, title: String, result: Result<HashMap<String, Vec<String>>, Error>) {
+    if safe_get_selected(&state.current_search_results, &state.search_result_list_state).is_none_or(|s| s.title != title) {
+        return;
+    }
+
+    match result {
+        Ok(map) => {
+            state.status_message = None;
+            state.concept_map = Some(concept_map::flatten_concept_map(&title, &map));
+            state.concept_map_state.select(Some(0));
+        }
+        Err(e) => state.status_message = Some(e.to_string()),
+    }
+}
+
+//None if nothing is selected or the selection is out of bounds
+fn safe_get_selected<'a>(results: &'a [Search], state: &ListState) -> Option<&'a Search> {
+    state.selected().and_then(|i| results.get(i))
+}
+
+
+//"Page 2 of 123" for search results, "21-40 of 95 loaded" when the loaded results are windowed
+fn page_indicator(state: &AppState) -> Option<String> {
+    let loaded = state.current_search_results.len();
+    let selected = state.search_result_list_state.selected().unwrap_or(0);
+    //The size filter lists its results whole, without windows
+    let window = pagination::window(selected, loaded, state.config.results_window).filter(|_| state.size_filter == SizeFilter::All).map(|(start, end)| {
+        let current = start / state.config.results_window + 1;
+        format!("{}-{} of {} loaded, {}/{}", start + 1, end, loaded, current, pagination::window_count(loaded, state.config.results_window))
+    });
+    let page = match state.search_total_pages {
+        0 => None,
+        total => Some(format!("Page {} of {}", state.search_page + 1, total)),
+    };
+
+    match (page, window) {
+        (Some(page), Some(window)) => Some(format!("{}, {}", page, window)),
+        (page, window) => page.or(window),
+    }
+}
 
-    selected_result
+//first_rank is the position of the first result in the whole list, the results may be one window of it
+//What the result list shows besides the results themselves
+struct ResultMarks<'a> {
+    //Pageids of results with a near duplicate snippet, highlighted
+    duplicates: &'a [i64],
+    //Shown with a pin
+    pinned: &'a [Search],
+    //Views of the trending articles by pageid, shown right-aligned
+    views: Option<&'a HashMap<i64, u64>>,
+    //Inside the borders
+    width: u16,
+    //Label of the active size filter, shown as a badge in the title
+    filter: Option<&'static str>,
 }
 
+fn render_search_list<'a>(search_results: Vec<Search>, first_rank: usize, marks: &ResultMarks, focused: bool, page: Option<String>, config: &Config) -> List<'a> {
+    let duplicates = marks.duplicates;
+    let has_duplicates = search_results.iter().any(|s| duplicates.contains(&s.pageid));
+    let mut title = String::from("Results");
+    if let Some(filter) = marks.filter {
+        title.push_str(&format!(" [filter: {}]", filter));
+    }
+    if let Some(page) = page {
+        title.push_str(&format!(" ({})", page));
+    }
+    if has_duplicates {
+        title.push_str(" ⚠ Possible duplicate results detected");
+    }
 
-fn render_search_list<'a>(search_results: Vec<Search>) -> List<'a> {
-    let results = Block::default() 
+    let results = focus_border(Block::default() 
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
-        .title("Results")
-        .border_type(BorderType::Plain);
+        .title(title)
+        .border_type(BorderType::Plain), focused);
+
 
+    //Ranks are padded so the titles line up
+    let rank_width = (first_rank + search_results.len()).to_string().len();
 
-    let items: Vec<_> = if search_results.len() > 0 {
+    let items: Vec<_> = if !search_results.is_empty() {
         search_results
         .iter()
-        .map(|s| {
-            ListItem::new(Spans::from(vec![Span::styled(
-                s.title.clone(),
-                Style::default(),
-            )]))
+        .enumerate()
+        .map(|(i, s)| {
+            let freshness = Freshness::from_timestamp(&s.timestamp, &config.freshness_days);
+            let title_style = if config.freshness_colors { freshness.title_style() } else { Style::default() };
+            let mut spans = Vec::new();
+            if config.result_ranks {
+                spans.push(Span::styled(format!("{:>width$}. ", first_rank + i + 1, width = rank_width), Style::default().fg(Color::DarkGray)));
+            }
+            if s.ns != 0 {
+                spans.push(Span::styled(format!("[{}] ", wikimedia_types::namespace_name(s.ns)), Style::default().fg(Color::Cyan)));
+            }
+            if pins::is_pinned(marks.pinned, s.pageid) {
+                spans.push(Span::raw("📌 "));
+            }
+            if marks.views.is_some() {
+                spans.push(Span::raw("🔥 "));
+            }
+            spans.extend(vec![
+                Span::styled(s.title.clone(), title_style),
+                Span::raw(" "),
+                Span::styled(freshness.label(), Style::default().fg(freshness.color())),
+            ]);
+            if let Some(views) = marks.views.and_then(|views| views.get(&s.pageid)) {
+                let views = format!("{} views", views);
+                let used: usize = spans.iter().map(|span| span.content.width()).sum();
+                let padding = usize::from(marks.width).saturating_sub(used + views.len()).max(1);
+                spans.push(Span::raw(" ".repeat(padding)));
+                spans.push(Span::styled(views, Style::default().fg(Color::DarkGray)));
+            }
+            let mut lines = vec![Spans::from(spans)];
+            if config.show_snippets {
+                let snippet = if config.clean_snippets { util::clean_snippet(&s.snippet) } else { s.snippet.clone() };
+                let indent = if config.result_ranks { rank_width + 2 } else { 0 };
+                lines.push(Spans::from(Span::styled(format!("{}{}", " ".repeat(indent), snippet), Style::default().fg(Color::DarkGray))));
+            }
+            let item = ListItem::new(lines);
+
+            if duplicates.contains(&s.pageid) {
+                item.style(Style::default().bg(Color::Yellow).fg(Color::Black))
+            } else {
+                item
+            }
         })
         .collect()
     } else {
@@ -428,27 +4170,671 @@ fn render_search_list<'a>(search_results: Vec<Search>) -> List<'a> {
     list
 }
 
-fn render_page_content<'a>(selected_search: Search, content: Option<String>, scroll: u16, width: u16) -> (Paragraph<'a>,String) {
-    let text_block = Block::default() 
+//The lists of the Home screen and the history tab, every row a label followed by dimmed details
+fn render_labeled_list<'a>(title: &'static str, rows: Vec<Vec<Span<'a>>>, focused: bool) -> List<'a> {
+    let list_block = focus_border(Block::default()
         .borders(Borders::ALL)
         .style(Style::default().fg(Color::White))
-        .title(Span::styled(selected_search.title, Style::default().fg(Color::Green)))
-        .border_type(BorderType::Plain);
+        .title(title)
+        .border_type(BorderType::Plain), focused);
+
+    let items: Vec<_> = rows.into_iter().map(|spans| ListItem::new(Spans::from(spans))).collect();
+
+    List::new(items).block(list_block).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn render_preset_list<'a>(presets: &BTreeMap<String, String>, focused: bool) -> List<'a> {
+    let rows = presets
+        .iter()
+        .map(|(name, query)| vec![
+            Span::styled(name.clone(), Style::default()),
+            Span::styled(format!("  {}", query), Style::default().fg(Color::DarkGray)),
+        ])
+        .collect();
+
+    render_labeled_list("Search presets", rows, focused)
+}
+
+fn render_recent_list<'a>(recent: &[RecentArticle], focused: bool) -> List<'a> {
+    let rows = recent
+        .iter()
+        .map(|r| vec![
+            Span::styled(r.search.title.clone(), Style::default()),
+            Span::styled(
+                format!("  {}", r.viewed_at.format("%Y-%m-%d %H:%M")),
+                Style::default().fg(Color::DarkGray),
+            ),
+        ])
+        .collect();
+
+    render_labeled_list("Recently viewed", rows, focused)
+}
+
+fn render_history_list<'a>(history: &[HistoryEntry]) -> List<'a> {
+    let rows = history
+        .iter()
+        .rev()
+        .map(|h| {
+            let results = match h.result_count {
+                Some(count) => format!("  {} results", count),
+                None => String::new(),
+            };
+            vec![
+                Span::styled(h.query.clone(), Style::default()),
+                Span::styled(results, Style::default().fg(Color::Cyan)),
+                Span::styled(
+                    format!("  {}", h.timestamp.format("%Y-%m-%d %H:%M")),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]
+        })
+        .collect();
+
+    render_labeled_list("Past searches (Enter: search again, d: delete)", rows, true)
+}
+
+//The title is followed by how recently the article was edited,
+//trail is the breadcrumb of the articles opened before
+fn content_block<'a>(article: &Search, trail: &str, config: &Config) -> Block<'a> {
+    let freshness = Freshness::from_timestamp(&article.timestamp, &config.freshness_days);
+
+    Block::default() 
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Spans::from(vec![
+            Span::styled(trail.to_string(), Style::default().fg(Color::DarkGray)),
+            Span::styled(article.title.clone(), Style::default().fg(Color::Green)),
+            Span::raw(" "),
+            Span::styled(freshness.label(), Style::default().fg(freshness.color())),
+        ]))
+        .border_type(BorderType::Plain)
+}
+
+//How the main article pane shows the text, compare readers show all of it
+#[derive(Default)]
+struct TextDisplay<'a> {
+    reveal: Option<usize>,
+    collapsed: Option<&'a HashSet<usize>>,
+    find: Option<&'a str>,
+    //Words of the search, only highlighted where the find query doesn't match
+    terms: &'a [String],
+}
 
+//Only lays out text that was fetched already, drawing never waits for the network
+fn render_page_content<'a>(selected_search: &Search, text: &str, scroll: u16, width: u16, display: &TextDisplay, config: &Config) -> Paragraph<'a> {
+    let text_block = content_block(selected_search, "", config);
 
-    let text: String = match content {
+    let text_width = match util::text_width(width, config.min_text_width) {
+        Some(w) => w,
         None => {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            rt.block_on(fetch_html(selected_search.pageid.try_into().unwrap(), width - 10)).unwrap()
+            return Paragraph::new("Widen the terminal to read")
+                .block(text_block)
+                .wrap(Wrap { trim: true });
         }
-        Some(c) => c
     };
 
-    let text_paragraph = Paragraph::new(text.clone())
+    let folded = match display.collapsed {
+        Some(collapsed) if !collapsed.is_empty() => sections::fold(text, collapsed).0,
+        _ => text.to_string(),
+    };
+
+    //The typewriter reveal cuts the text before it is laid out
+    let visible_text = match display.reveal.and_then(|n| folded.char_indices().nth(n)) {
+        Some((end, _)) => &folded[..end],
+        None => &folded,
+    };
+
+    //html2text only breaks lines at spaces, which CJK text doesn't have
+    let display_text = match config.text_alignment {
+        _ if util::is_cjk_language(&config.language) => util::wrap_cjk(visible_text, text_width.into()),
+        TextAlignment::Left => visible_text.to_string(),
+        TextAlignment::Justify => util::justify_text(visible_text, text_width.into()),
+    };
+
+    Paragraph::new(style_headings(&display_text, config.styled_headings, display.find, display.terms))
         .block(text_block)
         .wrap(Wrap { trim: false })
-        .scroll((scroll, 0));
+        .scroll((scroll, 0))
+}
+
+fn render_error_state(error: &AppError, debug: bool) -> Paragraph<'static> {
+    let mut text = vec![
+        Spans::from(Span::styled(error.message.clone(), Style::default().fg(Color::White).add_modifier(Modifier::BOLD))),
+        Spans::from(Span::styled(format!("at {}", error.occurred_at.format("%H:%M:%S")), Style::default().fg(Color::DarkGray))),
+        Spans::from(""),
+        Spans::from("Press Enter to retry"),
+        Spans::from("Press Esc to dismiss"),
+    ];
+    if let (true, Some(raw)) = (debug, &error.raw_response) {
+        text.push(Spans::from(""));
+        text.push(Spans::from(Span::styled(raw.chars().take(MAX_RAW_RESPONSE_CHARS).collect::<String>(), Style::default().fg(Color::DarkGray))));
+    }
+
+    Paragraph::new(text)
+        .alignment(Alignment::Center)
+        .wrap(Wrap { trim: true })
+        .block(Block::default().borders(Borders::ALL).border_style(Style::default().fg(Color::Red)).title(Span::styled("Error", Style::default().fg(Color::Red))).border_type(BorderType::Plain))
+}
+
+fn render_notes<'a>(notes: String) -> Paragraph<'a> {
+    Paragraph::new(notes)
+        .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::Cyan)).title("Notes (Ctrl+X: edit)").border_type(BorderType::Plain))
+        .wrap(Wrap { trim: false })
+}
+
+fn render_infobox<'a>(rows: &[InfoboxRow]) -> Paragraph<'a> {
+    let mut lines = Vec::new();
+    for row in rows {
+        if !row.label.is_empty() {
+            lines.push(Spans::from(Span::styled(row.label.clone(), Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD))));
+        }
+        if !row.value.is_empty() {
+            lines.push(Spans::from(Span::raw(format!("  {}", row.value))));
+        }
+    }
+
+    Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::White)).title("Infobox").border_type(BorderType::Plain))
+        .wrap(Wrap { trim: false })
+}
+
+fn render_suggestions<'a>(suggestions: &[Suggestion]) -> List<'a> {
+    let items: Vec<_> = suggestions
+        .iter()
+        .map(|s| {
+            let icon = if s.from_history { "🕒 " } else { "   " };
+            ListItem::new(Spans::from(vec![
+                Span::styled(icon, Style::default().fg(Color::DarkGray)),
+                Span::raw(s.text.clone()),
+            ]))
+        })
+        .collect();
+
+    List::new(items)
+        .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::White)).border_type(BorderType::Plain))
+        .highlight_style(
+            Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+        )
+}
+
+fn render_palette<'a>(palette: &CommandPalette) -> List<'a> {
+    let items: Vec<_> = palette
+        .matches()
+        .iter()
+        .map(|c| ListItem::new(Spans::from(vec![
+            Span::styled(c.name.clone(), Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(format!("  {}", c.description), Style::default().fg(Color::DarkGray)),
+        ])))
+        .collect();
+
+    List::new(items)
+        .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::White)).border_type(BorderType::Plain))
+        .highlight_style(Style::default().bg(Color::Yellow).fg(Color::Black))
+}
+
+fn render_category_tree<'a>(tree: &[CategoryNode]) -> List<'a> {
+    let category_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled("Categories (Right: expand, Left: parent, Enter: open, Esc: close)", Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = categories::rows(tree)
+        .iter()
+        .map(|row| {
+            let style = match row {
+                TreeRow::Category(_) => Style::default().fg(Color::Cyan),
+                TreeRow::Member(..) => Style::default(),
+            };
+            ListItem::new(Span::styled(categories::row_label(tree, row), style))
+        })
+        .collect();
+
+    List::new(items).block(category_block).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn render_backlinks<'a>(article_title: &str, backlinks: &Backlinks) -> List<'a> {
+    let more = if backlinks.continuation.is_some() { ", m: load more" } else { "" };
+    let title = format!("What links here to {} ({}{}, Enter: open, Esc: close)", article_title, backlinks.links.len(), more);
+    let backlinks_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled(title, Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = backlinks.links.iter().map(|l| ListItem::new(l.clone())).collect();
+
+    List::new(items).block(backlinks_block).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn render_links<'a>(links: &[String], total: usize) -> List<'a> {
+    let title = if links.len() < total {
+        format!("Links ({} of {}, a: show all, Enter: open, Esc: close)", links.len(), total)
+    } else {
+        format!("Links ({}, Enter: open, Esc: close)", total)
+    };
+    let links_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled(title, Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = links.iter().map(|l| ListItem::new(l.clone())).collect();
+
+    List::new(items).block(links_block).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn render_gallery<'a>(images: &[GalleryImage]) -> List<'a> {
+    let gallery_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled(format!("{} images (Enter: open in viewer, Esc: close)", images.len()), Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = images
+        .iter()
+        .map(|image| {
+            let mut lines = vec![Spans::from(Span::styled(image.title.clone(), Style::default().add_modifier(Modifier::BOLD)))];
+            if !image.caption.is_empty() {
+                lines.push(Spans::from(Span::styled(format!("  {}", image.caption), Style::default().fg(Color::DarkGray))));
+            }
+            ListItem::new(lines)
+        })
+        .collect();
+
+    List::new(items).block(gallery_block).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+fn render_concept_map<'a>(nodes: &[ConceptNode]) -> List<'a> {
+    let map_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled("Concept Map (Enter: open, Esc: close)", Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = nodes
+        .iter()
+        .map(|n| {
+            let style = match n.depth {
+                0 => Style::default().fg(Color::Green).add_modifier(Modifier::BOLD),
+                1 => Style::default(),
+                _ => Style::default().fg(Color::DarkGray),
+            };
+            let indent = if n.depth == 0 { String::new() } else { format!("{}└ ", "  ".repeat(n.depth - 1)) };
+
+            ListItem::new(Span::styled(format!("{}{}", indent, n.title), style))
+        })
+        .collect();
+
+    List::new(items).block(map_block).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    )
+}
+
+//html2text marks headings with leading #s like Markdown
+//Matches of the find query and the search terms are highlighted on top of the heading style
+fn style_headings<'a>(text: &str, styled: bool, find: Option<&str>, terms: &[String]) -> Text<'a> {
+    let lines: Vec<Spans> = text
+        .lines()
+        .map(|line| {
+            let style = match line.split(' ').next() {
+                Some("##") if styled => Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+                Some("###") if styled => Style::default().add_modifier(Modifier::BOLD),
+                Some("####") if styled => Style::default().add_modifier(Modifier::ITALIC),
+                _ => Style::default(),
+            };
+
+            let found = find.map(|query| find::find_in_line(line, query)).unwrap_or_default();
+            let mut highlights: Vec<(usize, usize, Color)> = find::find_terms_in_line(line, terms)
+                .into_iter()
+                .filter(|&(start, end)| found.iter().all(|&(s, e)| end <= s || start >= e))
+                .map(|(start, end)| (start, end, Color::Cyan))
+                .chain(found.iter().map(|&(start, end)| (start, end, Color::Yellow)))
+                .collect();
+            highlights.sort_unstable_by_key(|&(start, ..)| start);
+
+            let mut spans = Vec::new();
+            let mut end = 0;
+            for (start, match_end, color) in highlights {
+                spans.push(Span::styled(line[end..start].to_string(), style));
+                spans.push(Span::styled(line[start..match_end].to_string(), style.bg(color).fg(Color::Black)));
+                end = match_end;
+            }
+            spans.push(Span::styled(line[end..].to_string(), style));
+            Spans::from(spans)
+        })
+        .collect();
+
+    Text::from(lines)
+}
+
+fn render_geo_map(map: &GeoMap) -> Canvas<'_, impl Fn(&mut tui::widgets::canvas::Context<'_>) + '_> {
+    let title = format!("{} ({:.2}, {:.2}) arrows: pan, Esc: close", map.title, map.location.lat, map.location.lon);
+
+    Canvas::default()
+        .block(Block::default().borders(Borders::ALL).style(Style::default().fg(Color::White)).title(Span::styled(title, Style::default().fg(Color::Green))).border_type(BorderType::Plain))
+        .marker(Marker::Braille)
+        .x_bounds(map.x_bounds())
+        .y_bounds(map.y_bounds())
+        .paint(move |ctx| {
+            ctx.draw(&Map {
+                color: Color::Gray,
+                resolution: MapResolution::High,
+            });
+            ctx.layer();
+            ctx.print(map.location.lon, map.location.lat, "✦", Color::Yellow);
+        })
+}
+
+fn render_overlay<'a>(title: &'a str, text: String, scroll: u16) -> Paragraph<'a> {
+    let overlay_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled(title, Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    Paragraph::new(text)
+        .block(overlay_block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tui::backend::TestBackend;
 
+    fn search(title: &str) -> Search {
+        Search {
+            ns: 0,
+            title: title.to_string(),
+            pageid: 1,
+            size: 0,
+            wordcount: 0,
+            snippet: String::new(),
+            timestamp: String::new(),
+        }
+    }
+
+    //The pageids count from 1, every result is 1KB bigger than the one before
+    fn results(titles: &[&str]) -> Vec<Search> {
+        titles.iter().enumerate().map(|(i, title)| Search { pageid: i as i64 + 1, size: (i as i64 + 1) * 1024, ..search(title) }).collect()
+    }
+
+    fn response(titles: &[&str], totalhits: i64) -> SearchResponse {
+        SearchResponse {
+            batchcomplete: None,
+            search_response_continue: None,
+            query: wikimedia_types::Query {
+                searchinfo: wikimedia_types::Searchinfo { totalhits, suggestion: None, suggestionsnippet: None },
+                search: results(titles),
+            },
+        }
+    }
+
+    //Requests go to a closed port instead of Wikipedia and fail at once
+    fn test_state(runtime: &tokio::runtime::Runtime) -> AppState {
+        let config = Config { proxy: Some(String::from("http://127.0.0.1:9")), cache_max_mb: 0, ..Config::default() };
+        let client = WikipediaClient::new(&config).unwrap();
+        let tick_ms = Arc::new(AtomicU64::new(config.tick_ms));
+
+        AppState::new(config, client, tick_ms, runtime.handle().clone())
+    }
+
+    fn show_results(state: &mut AppState, query: &str, titles: &[&str]) {
+        state.results_query = query.to_string();
+        state.current_search_results = results(titles);
+        state.search_result_list_state.select(Some(0));
+        state.active_menu_item = MenuItem::Results;
+    }
+
+    fn screen_text(terminal: &Terminal<TestBackend>) -> String {
+        terminal.backend().buffer().content().iter().map(|cell| cell.symbol.as_str()).collect()
+    }
+
+    #[test]
+    fn selection_outside_the_results_is_none() {
+        let mut list_state = ListState::default();
+        assert!(safe_get_selected(&[], &list_state).is_none());
+
+        list_state.select(Some(0));
+        assert!(safe_get_selected(&[], &list_state).is_none());
+
+        let single = [search("Rust")];
+        assert_eq!(safe_get_selected(&single, &list_state).map(|s| s.title.as_str()), Some("Rust"));
+
+        list_state.select(Some(1));
+        assert!(safe_get_selected(&single, &list_state).is_none());
+
+        list_state.select(None);
+        assert!(safe_get_selected(&single, &list_state).is_none());
+    }
+
+    #[test]
+    fn undo_and_redo_switch_between_searches() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        show_results(&mut state, "rust", &["Rust", "Rust (fungus)"]);
+        state.search_result_list_state.select(Some(1));
+
+        record_navigation(&mut state);
+        show_results(&mut state, "vienna", &["Vienna"]);
+
+        undo_navigation(&mut state, AppAction::Undo);
+        assert_eq!(state.results_query, "rust");
+        assert_eq!(state.current_search_results.len(), 2);
+        assert_eq!(state.search_result_list_state.selected(), Some(1));
+
+        undo_navigation(&mut state, AppAction::Undo);
+        assert_eq!(state.status_message.as_deref(), Some("Nothing to go back or forward to"));
+
+        undo_navigation(&mut state, AppAction::Redo);
+        assert_eq!(state.results_query, "vienna");
+        assert_eq!(state.current_search_results.len(), 1);
+    }
+
+    #[test]
+    fn stacked_results_come_back_on_their_page() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        show_results(&mut state, "rust", &["Rust", "Rust (fungus)", "Rust Belt"]);
+        state.search_result_list_state.select(Some(2));
+        state.search_page = 2;
+        state.search_offset = 20;
+        state.search_total_pages = 5;
+
+        let layer = take_results_layer(&mut state);
+        results_stack::push_layer(&mut state.results_stack, layer, 3);
+        show_results(&mut state, "vienna", &["Vienna"]);
+        set_pagination(&mut state, 1);
+
+        pop_results_layer(&mut state);
+        assert_eq!(state.results_query, "rust");
+        assert_eq!(state.current_search_results.len(), 3);
+        assert_eq!(state.search_result_list_state.selected(), Some(2));
+        assert_eq!((state.search_page, state.search_offset, state.search_total_pages), (2, 20, 5));
+        assert_eq!(state.status_message.as_deref(), Some("Back to the results for 'rust'"));
+
+        //Nothing left to go back to
+        state.status_message = None;
+        pop_results_layer(&mut state);
+        assert_eq!(state.results_query, "rust");
+        assert!(state.status_message.is_none());
+    }
 
-    (text_paragraph, text)
+    #[test]
+    fn pages_are_fetched_in_the_background_and_stop_at_the_ends() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        let (tx, rx) = mpsc::channel();
+        show_results(&mut state, "rust", &["Rust"]);
+        set_pagination(&mut state, 25);
+        assert_eq!(state.search_total_pages, 3);
+
+        turn_search_page(&mut state, false, &tx);
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        turn_search_page(&mut state, true, &tx);
+        match rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(Event::SearchPageFetched(query, page, offset, result)) => {
+                assert_eq!((query.as_str(), page, offset), ("rust", 1, 10));
+                assert!(result.is_err());
+            }
+            _ => panic!("expected the next page"),
+        }
+
+        //A page for a list that was replaced meanwhile is dropped
+        finish_search_page(&mut state, String::from("vienna"), 1, 10, Ok(response(&["Vienna"], 25)));
+        assert_eq!(state.search_page, 0);
+
+        finish_search_page(&mut state, String::from("rust"), 1, 10, Ok(response(&["Rust Belt", "Rust (fungus)"], 25)));
+        assert_eq!((state.search_page, state.search_offset), (1, 10));
+        assert_eq!(state.current_search_results[0].title, "Rust Belt");
+
+        //totalhits was too high, the empty page becomes the end
+        finish_search_page(&mut state, String::from("rust"), 2, 20, Ok(response(&[], 25)));
+        assert_eq!((state.search_page, state.search_total_pages), (1, 2));
+        turn_search_page(&mut state, true, &tx);
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+    }
+
+    #[test]
+    fn recent_queries_popup_opens_moves_and_forgets() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        let (tx, _rx) = mpsc::channel();
+
+        open_recent_queries(&mut state);
+        assert!(!state.recent_queries_open);
+        assert_eq!(state.status_message.as_deref(), Some("Nothing searched yet"));
+
+        state.recent_queries = VecDeque::from(vec![String::from("rust"), String::from("vienna"), String::from("lisp")]);
+        open_recent_queries(&mut state);
+        assert!(state.recent_queries_open);
+        assert_eq!(state.recent_queries_state.selected(), Some(0));
+
+        recent_queries_key(&mut state, KeyCode::Up, &tx);
+        assert_eq!(state.recent_queries_state.selected(), Some(2));
+        recent_queries_key(&mut state, KeyCode::Char('d'), &tx);
+        assert_eq!(state.recent_queries, ["rust", "vienna"]);
+        assert_eq!(state.recent_queries_state.selected(), Some(1));
+
+        recent_queries_key(&mut state, KeyCode::Enter, &tx);
+        assert!(!state.recent_queries_open);
+        assert_eq!(state.search_string, "vienna");
+        assert!(state.pending_submit.as_ref().is_some_and(|(query, _)| query == "vienna"));
+
+        open_recent_queries(&mut state);
+        recent_queries_key(&mut state, KeyCode::Esc, &tx);
+        assert!(!state.recent_queries_open);
+    }
+
+    #[test]
+    fn retry_opens_the_article_that_failed() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        let (tx, _rx) = mpsc::channel();
+        show_results(&mut state, "rust", &["Rust", "Rust (fungus)"]);
+
+        report_failure(&mut state, &Error::UnexpectedResponse, RetryAction::OpenArticle(2));
+        assert!(state.error_state.is_some());
+        let action = state.last_failure.clone().unwrap();
+
+        retry(&mut state, action, &tx);
+        assert!(state.last_failure.is_none());
+        assert!(state.is_selected);
+        assert_eq!(state.search_result_list_state.selected(), Some(1));
+
+        show_results(&mut state, "vienna", &["Vienna"]);
+        retry(&mut state, RetryAction::OpenArticle(2), &tx);
+        assert_eq!(state.status_message.as_deref(), Some("The article is no longer in the results"));
+    }
+
+    #[test]
+    fn size_filter_moves_the_selection_to_a_shown_result() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        show_results(&mut state, "rust", &["Rust"; 12]);
+
+        //Only the last two results are over 10KB
+        cycle_size_filter(&mut state);
+        assert_eq!(state.size_filter, SizeFilter::Over10Kb);
+        assert_eq!(state.search_result_list_state.selected(), Some(10));
+        assert_eq!(state.status_message.as_deref(), Some("Filter >10KB: 2 of 12 results"));
+
+        for _ in 0..3 {
+            cycle_size_filter(&mut state);
+        }
+        assert_eq!(state.size_filter, SizeFilter::Stubs);
+        assert_eq!(state.search_result_list_state.selected(), Some(0));
+
+        cycle_size_filter(&mut state);
+        assert_eq!(state.size_filter, SizeFilter::All);
+        assert_eq!(state.status_message.as_deref(), Some("Showing all 12 results"));
+    }
+
+    #[test]
+    fn compare_view_draws_while_its_articles_load() {
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let mut state = test_state(&runtime);
+        let (tx, rx) = mpsc::channel();
+        show_results(&mut state, "rust", &["Rust", "Vienna"]);
+
+        pick_compare_article(&mut state);
+        state.search_result_list_state.select(Some(1));
+        pick_compare_article(&mut state);
+        assert!(state.compare.is_some());
+
+        let menu_titles: Vec<&str> = MenuItem::all().iter().map(|m| m.title()).collect();
+        let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+        //Drawn inside the runtime like the render task, where blocking on a fetch would panic
+        runtime.block_on(async { terminal.draw(|rect| draw_frame(rect, &mut state, &menu_titles, &tx)) }).unwrap();
+        assert!(screen_text(&terminal).contains("Loading Rust…"));
+        assert!(state.compare.as_ref().unwrap().readers.iter().all(|r| r.requested));
+
+        //Both readers are fetched once, the next frame doesn't ask again
+        terminal.draw(|rect| draw_frame(rect, &mut state, &menu_titles, &tx)).unwrap();
+        let mut fetched: Vec<i64> = (0..2).map(|_| match rx.recv_timeout(Duration::from_secs(10)) {
+            Ok(Event::CompareFetched(pageid, _)) => pageid,
+            _ => panic!("expected a compare fetch"),
+        }).collect();
+        fetched.sort_unstable();
+        assert_eq!(fetched, [1, 2]);
+        assert!(rx.recv_timeout(Duration::from_millis(200)).is_err());
+
+        state.compare.as_mut().unwrap().readers[1].content = Some(String::from("Capital of Austria"));
+        terminal.draw(|rect| draw_frame(rect, &mut state, &menu_titles, &tx)).unwrap();
+        assert!(screen_text(&terminal).contains("Capital of Austria"));
+    }
 }
@@ -0,0 +1,100 @@
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+//Converts the cleaned html2text output of an article into Markdown
+pub fn to_markdown(content: &str) -> String {
+    let mut lines: Vec<String> = Vec::new();
+    let mut in_code = false;
+    let mut in_list = false;
+
+    for line in content.lines() {
+        let trimmed = line.trim_start();
+        let indent = line.len() - trimmed.len();
+
+        //html2text keeps preformatted text as is, which on Wikipedia is indented by at least 4 spaces
+        let is_code = indent >= 4 && !in_list && !trimmed.is_empty();
+        if is_code != in_code && !(in_code && trimmed.is_empty()) {
+            lines.push(String::from("```"));
+            in_code = is_code;
+        }
+        if in_code {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if let Some(heading) = normalize_heading(trimmed) {
+            in_list = false;
+            push_blank(&mut lines);
+            lines.push(heading);
+            lines.push(String::new());
+        } else if let Some(item) = bullet_item(trimmed) {
+            in_list = true;
+            lines.push(format!("{}- {}", " ".repeat(indent), item));
+        } else if trimmed.is_empty() {
+            in_list = false;
+            push_blank(&mut lines);
+        } else if in_list && indent > 0 {
+            lines.push(line.to_string());
+        } else {
+            in_list = false;
+            lines.push(trimmed.to_string());
+        }
+    }
+
+    if in_code {
+        lines.push(String::from("```"));
+    }
+
+    while lines.last().is_some_and(|l| l.is_empty()) {
+        lines.pop();
+    }
+
+    lines.join("\n")
+}
+
+//Full export of an article, the title is the only level 1 heading
+pub fn article_to_markdown(title: &str, content: &str) -> String {
+    format!("# {}\n\n{}\n", title, to_markdown(content))
+}
+
+pub fn write_markdown(title: &str, markdown: &str) -> io::Result<PathBuf> {
+    let file_name: String = title
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+        .collect();
+
+    let dir = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    let path = dir.join(format!("{}.md", file_name));
+    fs::write(&path, markdown)?;
+
+    Ok(path)
+}
+
+fn normalize_heading(line: &str) -> Option<String> {
+    let level = line.chars().take_while(|c| *c == '#').count();
+    if level == 0 {
+        return None;
+    }
+
+    let text = line[level..].trim();
+    if text.is_empty() || !line[level..].starts_with(' ') {
+        return None;
+    }
+
+    //Level 1 is reserved for the article title
+    Some(format!("{} {}", "#".repeat(level.clamp(2, 6)), text))
+}
+
+fn bullet_item(line: &str) -> Option<&str> {
+    ["* ", "- ", "• "]
+        .iter()
+        .find(|marker| line.starts_with(*marker))
+        .map(|marker| line[marker.len()..].trim())
+}
+
+fn push_blank(lines: &mut Vec<String>) {
+    if lines.last().is_some_and(|l| !l.is_empty()) {
+        lines.push(String::new());
+    }
+}
@@ -0,0 +1,78 @@
+use crate::results_stack::ResultsKind;
+use crate::wikimedia_types::Search;
+use serde::{Deserialize, Serialize};
+
+//How many steps Ctrl+U can go back
+pub const UNDO_LIMIT: usize = 50;
+
+//Where the user was before a search, an opened article or another page of results
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct NavigationSnapshot {
+    pub kind: ResultsKind,
+    pub query: String,
+    pub results: Vec<Search>,
+    pub selected: Option<usize>,
+    pub article_open: bool,
+    pub scroll: u16,
+    pub breadcrumbs: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct UndoHistory {
+    undo_stack: Vec<NavigationSnapshot>,
+    redo_stack: Vec<NavigationSnapshot>,
+}
+
+impl UndoHistory {
+    //A new action makes the undone ones unreachable, like in an editor
+    pub fn record(&mut self, before: NavigationSnapshot) {
+        self.redo_stack.clear();
+        self.undo_stack.push(before);
+        if self.undo_stack.len() > UNDO_LIMIT {
+            self.undo_stack.remove(0);
+        }
+    }
+
+    //current is where the user is now, it becomes the target of the redo
+    pub fn undo(&mut self, current: NavigationSnapshot) -> Option<NavigationSnapshot> {
+        let previous = self.undo_stack.pop()?;
+        self.redo_stack.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: NavigationSnapshot) -> Option<NavigationSnapshot> {
+        let next = self.redo_stack.pop()?;
+        self.undo_stack.push(current);
+        Some(next)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(query: &str) -> NavigationSnapshot {
+        NavigationSnapshot {
+            kind: ResultsKind::Search,
+            query: query.to_string(),
+            results: Vec::new(),
+            selected: None,
+            article_open: false,
+            scroll: 0,
+            breadcrumbs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn redo_returns_to_the_undone_state_until_something_new_happens() {
+        let mut history = UndoHistory::default();
+        history.record(at("rust"));
+
+        assert_eq!(history.undo(at("vienna")).map(|s| s.query).as_deref(), Some("rust"));
+        assert_eq!(history.redo(at("rust")).map(|s| s.query).as_deref(), Some("vienna"));
+
+        history.undo(at("vienna"));
+        history.record(at("rust"));
+        assert!(history.redo(at("lisp")).is_none());
+    }
+}
@@ -0,0 +1,76 @@
+//The search API returns srlimit results per request, 10 unless set
+pub const RESULTS_PER_PAGE: u8 = 10;
+
+pub fn next_page_offset(current: i64, limit: u8) -> i64 {
+    current + i64::from(limit)
+}
+
+pub fn previous_page_offset(current: i64, limit: u8) -> i64 {
+    (current - i64::from(limit)).max(0)
+}
+
+//Page the result at the offset is on
+pub fn page_of(offset: i64, limit: u8) -> u32 {
+    (offset.max(0) / i64::from(limit.max(1))) as u32
+}
+
+//totalhits is an estimate for large result sets, so the last pages may turn out empty
+pub fn total_pages(totalhits: i64, limit: u8) -> u32 {
+    let limit = i64::from(limit.max(1));
+    ((totalhits.max(0) + limit - 1) / limit) as u32
+}
+
+//Loaded results are shown window_size at a time, None if they all fit or windowing is off
+pub fn window(selected: usize, loaded: usize, window_size: usize) -> Option<(usize, usize)> {
+    if window_size == 0 || loaded <= window_size {
+        return None;
+    }
+
+    let start = selected.min(loaded - 1) / window_size * window_size;
+    Some((start, (start + window_size).min(loaded)))
+}
+
+//Number of windows the loaded results are split into
+pub fn window_count(loaded: usize, window_size: usize) -> usize {
+    loaded.div_ceil(window_size.max(1))
+}
+
+//Selection after PageUp or PageDown, the first result of the neighbouring window. Stays in the list
+pub fn jump_window(selected: usize, loaded: usize, window_size: usize, forward: bool) -> usize {
+    let window_size = window_size.max(1);
+    let start = selected / window_size * window_size;
+
+    if forward {
+        (start + window_size).min(loaded.saturating_sub(1))
+    } else {
+        start.saturating_sub(window_size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages_follow_the_offset() {
+        assert_eq!(next_page_offset(20, RESULTS_PER_PAGE), 30);
+        assert_eq!(previous_page_offset(5, RESULTS_PER_PAGE), 0);
+        assert_eq!(total_pages(1230, RESULTS_PER_PAGE), 123);
+        assert_eq!(total_pages(1231, RESULTS_PER_PAGE), 124);
+        assert_eq!(total_pages(0, RESULTS_PER_PAGE), 0);
+        assert_eq!(page_of(30, RESULTS_PER_PAGE), 3);
+        assert_eq!(page_of(35, RESULTS_PER_PAGE), 3);
+    }
+
+    #[test]
+    fn windows_split_the_loaded_results() {
+        assert_eq!(window(25, 95, 20), Some((20, 40)));
+        assert_eq!(window(94, 95, 20), Some((80, 95)));
+        assert_eq!(window(3, 15, 20), None);
+        assert_eq!(window(3, 95, 0), None);
+        assert_eq!(window_count(95, 20), 5);
+        assert_eq!(jump_window(25, 95, 20, true), 40);
+        assert_eq!(jump_window(85, 95, 20, true), 94);
+        assert_eq!(jump_window(25, 95, 20, false), 0);
+    }
+}
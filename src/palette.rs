@@ -0,0 +1,232 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use fuzzy_matcher::skim::SkimMatcherV2;
+use fuzzy_matcher::FuzzyMatcher;
+use tui::widgets::ListState;
+
+//What a command of the palette does. Every action is a key that already does it,
+//so running one replays that key and the palette stays in sync with the keybindings
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AppAction {
+    Search,
+    Home,
+    Results,
+    PastSearches,
+    RecentArticles,
+    Trending,
+    RecentSearches,
+    ExportBookmarks,
+    ReloadConfig,
+    SaveScreen,
+    SaveKeybindings,
+    FullScreen,
+    LuaRepl,
+    Quit,
+    Bookmark,
+    Infobox,
+    MarkdownPreview,
+    TalkPage,
+    Find,
+    Notes,
+    Gallery,
+    Links,
+    Backlinks,
+    Categories,
+    ConceptMap,
+    WorldMap,
+    Refresh,
+    Undo,
+    Redo,
+}
+
+impl AppAction {
+    pub fn key(self) -> KeyEvent {
+        let (code, modifiers) = match self {
+            AppAction::Search => (KeyCode::Char('s'), KeyModifiers::NONE),
+            AppAction::Home => (KeyCode::Char('h'), KeyModifiers::NONE),
+            AppAction::Results => (KeyCode::Char('r'), KeyModifiers::NONE),
+            AppAction::PastSearches => (KeyCode::Char('p'), KeyModifiers::NONE),
+            AppAction::RecentArticles => (KeyCode::Char('v'), KeyModifiers::NONE),
+            AppAction::Trending => (KeyCode::Char('t'), KeyModifiers::CONTROL),
+            AppAction::RecentSearches => (KeyCode::Char('o'), KeyModifiers::CONTROL),
+            AppAction::ExportBookmarks => (KeyCode::Char('B'), KeyModifiers::SHIFT),
+            AppAction::ReloadConfig => (KeyCode::F(5), KeyModifiers::NONE),
+            AppAction::SaveScreen => (KeyCode::F(6), KeyModifiers::NONE),
+            AppAction::SaveKeybindings => (KeyCode::F(10), KeyModifiers::NONE),
+            AppAction::FullScreen => (KeyCode::F(11), KeyModifiers::NONE),
+            AppAction::LuaRepl => (KeyCode::F(12), KeyModifiers::NONE),
+            AppAction::Quit => (KeyCode::Char('q'), KeyModifiers::NONE),
+            AppAction::Bookmark => (KeyCode::Char('b'), KeyModifiers::NONE),
+            AppAction::Infobox => (KeyCode::Char('i'), KeyModifiers::NONE),
+            AppAction::MarkdownPreview => (KeyCode::Char('~'), KeyModifiers::NONE),
+            AppAction::TalkPage => (KeyCode::Char('t'), KeyModifiers::NONE),
+            AppAction::Find => (KeyCode::Char('/'), KeyModifiers::NONE),
+            AppAction::Notes => (KeyCode::Char('x'), KeyModifiers::CONTROL),
+            AppAction::Gallery => (KeyCode::Char('G'), KeyModifiers::SHIFT),
+            AppAction::Links => (KeyCode::Char('L'), KeyModifiers::SHIFT),
+            AppAction::Backlinks => (KeyCode::Char('W'), KeyModifiers::SHIFT),
+            AppAction::Categories => (KeyCode::Char('C'), KeyModifiers::SHIFT),
+            AppAction::ConceptMap => (KeyCode::F(8), KeyModifiers::NONE),
+            AppAction::WorldMap => (KeyCode::Char('g'), KeyModifiers::CONTROL),
+            AppAction::Refresh => (KeyCode::Char('U'), KeyModifiers::SHIFT),
+            AppAction::Undo => (KeyCode::Char('u'), KeyModifiers::CONTROL),
+            AppAction::Redo => (KeyCode::Char('y'), KeyModifiers::CONTROL),
+        };
+        KeyEvent::new(code, modifiers)
+    }
+
+    //Article keys only work while the article has the focus
+    pub fn needs_article(self) -> bool {
+        matches!(
+            self,
+            AppAction::Bookmark
+                | AppAction::Infobox
+                | AppAction::MarkdownPreview
+                | AppAction::TalkPage
+                | AppAction::Find
+                | AppAction::Notes
+                | AppAction::Gallery
+                | AppAction::Links
+                | AppAction::Backlinks
+                | AppAction::Categories
+                | AppAction::ConceptMap
+                | AppAction::WorldMap
+                | AppAction::Refresh
+        )
+    }
+}
+
+pub struct Command {
+    pub name: String,
+    pub description: String,
+    pub action: AppAction,
+}
+
+fn command(name: &str, description: &str, action: AppAction) -> Command {
+    Command {
+        name: String::from(name),
+        description: String::from(description),
+        action,
+    }
+}
+
+pub fn commands() -> Vec<Command> {
+    vec![
+        command("Search Wikipedia", "Focus the search box", AppAction::Search),
+        command("Go Home", "Show the home tab", AppAction::Home),
+        command("Show Results", "Show the results tab", AppAction::Results),
+        command("Show Past Searches", "Show the search history", AppAction::PastSearches),
+        command("Show Recently Viewed", "Show the recently viewed articles", AppAction::RecentArticles),
+        command("Show Trending Articles", "List yesterday's most viewed articles", AppAction::Trending),
+        command("Show Recent Searches", "Pick one of this session's searches to run again", AppAction::RecentSearches),
+        command("Export Bookmarks", "Write all bookmarked articles into one Markdown file", AppAction::ExportBookmarks),
+        command("Reload Config", "Read the config file again", AppAction::ReloadConfig),
+        command("Save Screen", "Save the screen as plain text and ANSI", AppAction::SaveScreen),
+        command("Save Keybindings", "Write the keybinding reference to keybindings.txt", AppAction::SaveKeybindings),
+        command("Toggle Full Screen", "Hide menu and footer and maximize the window", AppAction::FullScreen),
+        command("Open Lua REPL", "Run Lua with the tpedia functions", AppAction::LuaRepl),
+        command("Quit", "Exit Tpedia", AppAction::Quit),
+        command("Toggle Bookmark", "Add or remove a bookmark for the article", AppAction::Bookmark),
+        command("Toggle Infobox", "Show or hide the infobox panel", AppAction::Infobox),
+        command("Export as Markdown", "Preview the article as Markdown, w writes it", AppAction::MarkdownPreview),
+        command("Open Talk Page", "Open the discussion of the article", AppAction::TalkPage),
+        command("Find in Article", "Find text in the article", AppAction::Find),
+        command("Edit Notes", "Write notes on the article in $EDITOR", AppAction::Notes),
+        command("Show Gallery", "List the article's images with their captions", AppAction::Gallery),
+        command("Show Links", "List the linked articles", AppAction::Links),
+        command("What Links Here", "List the articles that link to this one", AppAction::Backlinks),
+        command("Browse Categories", "Browse the categories of the article", AppAction::Categories),
+        command("Show Concept Map", "Show the article's links two hops deep", AppAction::ConceptMap),
+        command("Show on World Map", "Show the article's location on a world map", AppAction::WorldMap),
+        command("Refresh Article", "Fetch the article again instead of using the cache", AppAction::Refresh),
+        command("Undo", "Go back to before the last search or opened article", AppAction::Undo),
+        command("Redo", "Go forward again after an undo", AppAction::Redo),
+    ]
+}
+
+//Ctrl+K, typing filters the commands and Enter runs the selected one
+pub struct CommandPalette {
+    pub query: String,
+    pub list_state: ListState,
+    commands: Vec<Command>,
+}
+
+impl CommandPalette {
+    pub fn new() -> CommandPalette {
+        let mut list_state = ListState::default();
+        list_state.select(Some(0));
+
+        CommandPalette {
+            query: String::new(),
+            list_state,
+            commands: commands(),
+        }
+    }
+
+    //Commands matching the query, best match first. Names count more than descriptions
+    pub fn matches(&self) -> Vec<&Command> {
+        if self.query.is_empty() {
+            return self.commands.iter().collect();
+        }
+
+        let matcher = SkimMatcherV2::default();
+        let mut scored: Vec<(i64, &Command)> = self
+            .commands
+            .iter()
+            .filter_map(|c| {
+                let name = matcher.fuzzy_match(&c.name, &self.query).map(|s| s * 2);
+                let description = matcher.fuzzy_match(&c.description, &self.query);
+                name.max(description).map(|score| (score, c))
+            })
+            .collect();
+
+        //Stable, so equally good matches keep the order of the list
+        scored.sort_by_key(|(score, _)| -score);
+        scored.into_iter().map(|(_, c)| c).collect()
+    }
+
+    pub fn selected(&self) -> Option<AppAction> {
+        self.matches().get(self.list_state.selected().unwrap_or(0)).map(|c| c.action)
+    }
+
+    pub fn type_char(&mut self, c: char) {
+        self.query.push(c);
+        self.list_state.select(Some(0));
+    }
+
+    pub fn backspace(&mut self) {
+        self.query.pop();
+        self.list_state.select(Some(0));
+    }
+
+    pub fn move_selection(&mut self, down: bool) {
+        let amount = self.matches().len();
+        if amount == 0 {
+            return;
+        }
+
+        let selected = self.list_state.selected().unwrap_or(0);
+        let next = if down { (selected + 1) % amount } else { (selected + amount - 1) % amount };
+        self.list_state.select(Some(next));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn typing_filters_and_ranks_commands() {
+        let mut palette = CommandPalette::new();
+        for c in "galry".chars() {
+            palette.type_char(c);
+        }
+
+        assert_eq!(palette.selected(), Some(AppAction::Gallery));
+
+        palette.query = String::from("markdown");
+        assert_eq!(palette.selected(), Some(AppAction::MarkdownPreview));
+
+        palette.query = String::from("zzzz");
+        assert_eq!(palette.selected(), None);
+    }
+}
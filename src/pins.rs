@@ -0,0 +1,59 @@
+use crate::wikimedia_types::Search;
+
+//Pinned results are kept for the session and listed above the results of every search,
+//in the order they were pinned
+
+//Pins the result, or unpins it if it was pinned. Returns whether it is pinned now
+pub fn toggle_pin(pinned: &mut Vec<Search>, search: &Search) -> bool {
+    match pinned.iter().position(|p| p.pageid == search.pageid) {
+        Some(i) => {
+            pinned.remove(i);
+            false
+        }
+        None => {
+            pinned.push(search.clone());
+            true
+        }
+    }
+}
+
+pub fn is_pinned(pinned: &[Search], pageid: i64) -> bool {
+    pinned.iter().any(|p| p.pageid == pageid)
+}
+
+//The pinned results first, then the others without them
+pub fn pin_first(results: Vec<Search>, pinned: &[Search]) -> Vec<Search> {
+    let others = results.into_iter().filter(|r| !is_pinned(pinned, r.pageid));
+    pinned.iter().cloned().chain(others).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn search(pageid: i64) -> Search {
+        Search {
+            ns: 0,
+            title: pageid.to_string(),
+            pageid,
+            size: 0,
+            wordcount: 0,
+            snippet: String::new(),
+            timestamp: String::new(),
+        }
+    }
+
+    #[test]
+    fn pinned_results_stay_on_top_once() {
+        let mut pinned = Vec::new();
+        assert!(toggle_pin(&mut pinned, &search(3)));
+        assert!(toggle_pin(&mut pinned, &search(9)));
+
+        let ids: Vec<i64> = pin_first(vec![search(1), search(3), search(2)], &pinned).iter().map(|s| s.pageid).collect();
+        assert_eq!(ids, vec![3, 9, 1, 2]);
+
+        assert!(!toggle_pin(&mut pinned, &search(3)));
+        let ids: Vec<i64> = pin_first(vec![search(3), search(9), search(1)], &pinned).iter().map(|s| s.pageid).collect();
+        assert_eq!(ids, vec![9, 3, 1]);
+    }
+}
@@ -0,0 +1,31 @@
+use crate::storage;
+use crate::wikimedia_types::Search;
+use crate::Error;
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+
+const RECENT_FILE: &str = "recent.json";
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct RecentArticle {
+    pub search: Search,
+    pub viewed_at: DateTime<Local>,
+}
+
+pub fn load_recent() -> Result<Vec<RecentArticle>, Error> {
+    storage::load_json(RECENT_FILE)
+}
+
+pub fn save_recent(recent: &[RecentArticle]) -> Result<(), Error> {
+    storage::save_json(RECENT_FILE, recent)
+}
+
+//Most recently viewed first, every article only once
+pub fn push_recent(recent: &mut Vec<RecentArticle>, search: &Search, limit: usize) {
+    recent.retain(|r| r.search.pageid != search.pageid);
+    recent.insert(0, RecentArticle {
+        search: search.clone(),
+        viewed_at: Local::now(),
+    });
+    recent.truncate(limit);
+}
@@ -0,0 +1,24 @@
+use tui::style::{Color, Modifier, Style};
+use tui::text::Span;
+use tui::widgets::{Block, BorderType, Borders, List, ListItem};
+
+//Ctrl+O popup of this session's searches, newest first. The block comes separately,
+//the caller draws it around the list and clears the area below
+pub fn render_recent_queries_popup<'a>(queries: &[String]) -> (Block<'a>, List<'a>) {
+    let queries_block = Block::default()
+        .borders(Borders::ALL)
+        .style(Style::default().fg(Color::White))
+        .title(Span::styled("Recent searches (Enter: search, d: delete, Esc: close)", Style::default().fg(Color::Green)))
+        .border_type(BorderType::Plain);
+
+    let items: Vec<_> = queries.iter().map(|q| ListItem::new(q.clone())).collect();
+
+    let list = List::new(items).highlight_style(
+        Style::default()
+            .bg(Color::Yellow)
+            .fg(Color::Black)
+            .add_modifier(Modifier::BOLD),
+    );
+
+    (queries_block, list)
+}
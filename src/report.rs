@@ -0,0 +1,44 @@
+use crate::config::Config;
+use crate::wikimedia_types::Search;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+const EXCERPT_LENGTH: usize = 1500;
+
+fn excerpt(text: &str) -> String {
+    let mut excerpt: String = text.chars().take(EXCERPT_LENGTH).collect();
+    if text.chars().count() > EXCERPT_LENGTH {
+        excerpt.push_str("\n[...]");
+    }
+    excerpt
+}
+
+//Prefilled body for a GitHub issue about an article that is rendered badly
+pub fn build_report(search: &Search, config: &Config, raw_html: &str, cleaned: &str) -> String {
+    format!(
+        "### Article rendering issue\n\n\
+        - Title: {}\n\
+        - Page id: {}\n\
+        - Language: {}\n\
+        - URL: {}\n\
+        - Tpedia version: {}\n\n\
+        <details><summary>Raw HTML (truncated)</summary>\n\n```html\n{}\n```\n\n</details>\n\n\
+        <details><summary>Cleaned text (truncated)</summary>\n\n```\n{}\n```\n\n</details>\n",
+        search.title,
+        search.pageid,
+        config.language,
+        config.article_url(&search.title),
+        env!("CARGO_PKG_VERSION"),
+        excerpt(raw_html),
+        excerpt(cleaned),
+    )
+}
+
+pub fn write_report(search: &Search, report: &str) -> io::Result<PathBuf> {
+    let dir = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    let path = dir.join(format!("tpedia_report_{}.md", search.pageid));
+    fs::write(&path, report)?;
+
+    Ok(path)
+}
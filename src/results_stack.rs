@@ -0,0 +1,37 @@
+use crate::wikimedia_types::Search;
+use serde::{Deserialize, Serialize};
+
+//What a result list holds. Only search results have words to highlight,
+//the other lists are named by the query they are shown with
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Default)]
+pub enum ResultsKind {
+    #[default]
+    Search,
+    Recent,
+    Trending,
+}
+
+//A result list that was covered by a newer search, Esc brings it back
+pub struct ResultsLayer {
+    pub kind: ResultsKind,
+    pub query: String,
+    pub results: Vec<Search>,
+    pub selected: Option<usize>,
+    pub continuation: Option<(String, i64)>,
+    //The page the list was on, as search_page, search_offset and search_total_pages
+    pub page: u32,
+    pub offset: i64,
+    pub total_pages: u32,
+}
+
+//The oldest layer is dropped once the stack is deeper than max_depth
+pub fn push_layer(stack: &mut Vec<ResultsLayer>, layer: ResultsLayer, max_depth: usize) {
+    if max_depth == 0 {
+        return;
+    }
+
+    stack.push(layer);
+    if stack.len() > max_depth {
+        stack.remove(0);
+    }
+}
@@ -0,0 +1,148 @@
+use std::collections::HashSet;
+
+//html2text marks headings with leading #s like Markdown, e.g. "## History"
+fn heading_level(line: &str) -> Option<usize> {
+    let level = line.chars().take_while(|&c| c == '#').count();
+    if level > 0 && line[level..].starts_with(' ') {
+        Some(level)
+    } else {
+        None
+    }
+}
+
+pub fn heading_count(text: &str) -> usize {
+    text.lines().filter(|line| heading_level(line).is_some()).count()
+}
+
+//Hides the body of every collapsed section, including its subsections.
+//Sections are numbered by the order of their headings. Returns the folded text and,
+//for every visible heading, its number and line in the folded text
+pub fn fold(text: &str, collapsed: &HashSet<usize>) -> (String, Vec<(usize, usize)>) {
+    let mut lines: Vec<String> = Vec::new();
+    let mut visible_headings = Vec::new();
+    //Level of the collapsed section that is being skipped, and the index of its heading line
+    let mut skipping: Option<(usize, usize)> = None;
+    let mut hidden = 0;
+    let mut section = 0;
+
+    let close_fold = |lines: &mut Vec<String>, heading_line: usize, hidden: usize| {
+        if hidden > 0 {
+            lines[heading_line].push_str(&format!(" [+{} lines]", hidden));
+        }
+    };
+
+    for line in text.lines() {
+        let level = heading_level(line);
+
+        if let (Some((fold_level, heading_line)), Some(level)) = (skipping, level) {
+            if level <= fold_level {
+                close_fold(&mut lines, heading_line, hidden);
+                skipping = None;
+                hidden = 0;
+            }
+        }
+
+        if skipping.is_some() {
+            hidden += 1;
+            if level.is_some() {
+                section += 1;
+            }
+            continue;
+        }
+
+        if let Some(level) = level {
+            visible_headings.push((section, lines.len()));
+            if collapsed.contains(&section) {
+                skipping = Some((level, lines.len()));
+            }
+            section += 1;
+        }
+        lines.push(line.to_string());
+    }
+
+    if let Some((_, heading_line)) = skipping {
+        close_fold(&mut lines, heading_line, hidden);
+    }
+
+    (lines.join("\n"), visible_headings)
+}
+
+//Number of the section the given line of the folded text belongs to
+pub fn section_at(visible_headings: &[(usize, usize)], line: usize) -> Option<usize> {
+    visible_headings
+        .iter()
+        .take_while(|(_, heading_line)| *heading_line <= line)
+        .last()
+        .map(|(section, _)| *section)
+}
+
+//Line of the section's heading in the folded text, a heading hidden by a collapsed
+//parent gives the parent's line
+pub fn heading_line(visible_headings: &[(usize, usize)], section: usize) -> Option<usize> {
+    visible_headings
+        .iter()
+        .take_while(|(s, _)| *s <= section)
+        .last()
+        .map(|(_, line)| *line)
+}
+
+//Line of the next visible heading below the given line, or of the previous one above it
+pub fn neighbour_heading(visible_headings: &[(usize, usize)], line: usize, forward: bool) -> Option<usize> {
+    let mut lines = visible_headings.iter().map(|(_, heading_line)| *heading_line);
+    if forward {
+        lines.find(|&l| l > line)
+    } else {
+        lines.rev().find(|&l| l < line)
+    }
+}
+
+//Headings of the folded text with their lines, indented by level for a table of contents
+pub fn outline(folded: &str) -> Vec<(String, usize)> {
+    folded
+        .lines()
+        .enumerate()
+        .filter_map(|(line, text)| heading_level(text).map(|level| {
+            let title = text[level..].trim_start();
+            (format!("{}{}", "  ".repeat(level.saturating_sub(2)), title), line)
+        }))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ARTICLE: &str = "Intro\n## History\nOld\n### Middle Ages\nKnights\n## Geography\nHills";
+
+    #[test]
+    fn collapsed_section_hides_subsections() {
+        let collapsed: HashSet<usize> = std::iter::once(0).collect();
+        let (text, headings) = fold(ARTICLE, &collapsed);
+
+        assert_eq!(text, "Intro\n## History [+3 lines]\n## Geography\nHills");
+        assert_eq!(headings, vec![(0, 1), (2, 2)]);
+        assert_eq!(heading_line(&headings, 1), Some(1));
+        assert_eq!(section_at(&headings, 3), Some(2));
+        assert_eq!(neighbour_heading(&headings, 1, true), Some(2));
+        assert_eq!(neighbour_heading(&headings, 1, false), None);
+    }
+
+    #[test]
+    fn outline_indents_subsections() {
+        let entries = outline(ARTICLE);
+
+        assert_eq!(entries, vec![
+            (String::from("History"), 1),
+            (String::from("  Middle Ages"), 3),
+            (String::from("Geography"), 5),
+        ]);
+    }
+
+    #[test]
+    fn nothing_collapsed_keeps_the_text() {
+        let (text, headings) = fold(ARTICLE, &HashSet::new());
+
+        assert_eq!(text, ARTICLE);
+        assert_eq!(headings.len(), heading_count(ARTICLE));
+    }
+}
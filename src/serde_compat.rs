@@ -0,0 +1,50 @@
+use serde::de::IgnoredAny;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use tui::widgets::ListState;
+
+//tui's widget states can't be serialized, only their selected index is persisted
+#[derive(std::fmt::Debug, Clone, Copy, Default, PartialEq)]
+pub struct SerializableListState(pub Option<usize>);
+
+impl Serialize for SerializableListState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for SerializableListState {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Option::<usize>::deserialize(deserializer).map(SerializableListState)
+    }
+}
+
+impl From<&ListState> for SerializableListState {
+    fn from(state: &ListState) -> Self {
+        SerializableListState(state.selected())
+    }
+}
+
+impl From<SerializableListState> for ListState {
+    fn from(state: SerializableListState) -> Self {
+        let mut list_state = ListState::default();
+        list_state.select(state.0);
+        list_state
+    }
+}
+
+//formatversion 1 marks flags with an empty string and leaves them out when unset,
+//formatversion 2 uses booleans
+pub fn flag<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<bool>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Flag {
+        Bool(bool),
+        Present(IgnoredAny),
+    }
+
+    Ok(match Option::<Flag>::deserialize(deserializer)? {
+        Some(Flag::Bool(b)) => Some(b),
+        Some(Flag::Present(_)) => Some(true),
+        None => None,
+    })
+}
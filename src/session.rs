@@ -0,0 +1,27 @@
+use crate::results_stack::ResultsKind;
+use crate::serde_compat::SerializableListState;
+use crate::storage;
+use crate::wikimedia_types::Search;
+use crate::Error;
+use serde::{Deserialize, Serialize};
+
+const SESSION_FILE: &str = "session.json";
+
+//The result list and selections of the last run, restored on startup
+#[derive(Serialize, Deserialize, std::fmt::Debug, Default)]
+#[serde(default)]
+pub struct Session {
+    pub results_kind: ResultsKind,
+    pub results_query: String,
+    pub results: Vec<Search>,
+    pub search_result_list_state: SerializableListState,
+    pub recent_list_state: SerializableListState,
+}
+
+pub fn load_session() -> Result<Session, Error> {
+    storage::load_json(SESSION_FILE)
+}
+
+pub fn save_session(session: &Session) -> Result<(), Error> {
+    storage::save_json(SESSION_FILE, session)
+}
@@ -0,0 +1,130 @@
+use chrono::Local;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use tui::buffer::{Buffer, Cell};
+use tui::layout::Rect;
+use tui::style::{Color, Modifier};
+use tui::widgets::Widget;
+use unicode_width::UnicodeWidthStr;
+
+//Rendered last, it copies everything drawn in this frame
+pub struct BufferCapture<'a>(pub &'a mut Option<Buffer>);
+
+impl<'a> Widget for BufferCapture<'a> {
+    fn render(self, _area: Rect, buf: &mut Buffer) {
+        *self.0 = Some(buf.clone());
+    }
+}
+
+//Wide characters occupy two cells, the second one is skipped
+fn rows(buffer: &Buffer) -> Vec<Vec<&Cell>> {
+    let area = buffer.area();
+
+    (area.top()..area.bottom())
+        .map(|y| {
+            let mut row = Vec::new();
+            let mut skip = 0;
+            for x in area.left()..area.right() {
+                if skip > 0 {
+                    skip -= 1;
+                    continue;
+                }
+                let cell = buffer.get(x, y);
+                skip = cell.symbol.width().saturating_sub(1);
+                row.push(cell);
+            }
+            row
+        })
+        .collect()
+}
+
+pub fn buffer_to_text(buffer: &Buffer) -> String {
+    rows(buffer)
+        .iter()
+        .map(|row| row.iter().map(|c| c.symbol.as_str()).collect::<String>().trim_end().to_string())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn color_code(color: Color, background: bool) -> String {
+    let offset = if background { 10 } else { 0 };
+    match color {
+        Color::Reset => format!("{}", 39 + offset),
+        Color::Black => format!("{}", 30 + offset),
+        Color::Red => format!("{}", 31 + offset),
+        Color::Green => format!("{}", 32 + offset),
+        Color::Yellow => format!("{}", 33 + offset),
+        Color::Blue => format!("{}", 34 + offset),
+        Color::Magenta => format!("{}", 35 + offset),
+        Color::Cyan => format!("{}", 36 + offset),
+        Color::Gray => format!("{}", 37 + offset),
+        Color::DarkGray => format!("{}", 90 + offset),
+        Color::LightRed => format!("{}", 91 + offset),
+        Color::LightGreen => format!("{}", 92 + offset),
+        Color::LightYellow => format!("{}", 93 + offset),
+        Color::LightBlue => format!("{}", 94 + offset),
+        Color::LightMagenta => format!("{}", 95 + offset),
+        Color::LightCyan => format!("{}", 96 + offset),
+        Color::White => format!("{}", 97 + offset),
+        Color::Rgb(r, g, b) => format!("{};2;{};{};{}", 38 + offset, r, g, b),
+        Color::Indexed(i) => format!("{};5;{}", 38 + offset, i),
+    }
+}
+
+fn sgr(cell: &Cell) -> String {
+    let modifiers = [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::SLOW_BLINK, "5"),
+        (Modifier::RAPID_BLINK, "6"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::HIDDEN, "8"),
+        (Modifier::CROSSED_OUT, "9"),
+    ];
+
+    let mut codes = vec![String::from("0"), color_code(cell.fg, false), color_code(cell.bg, true)];
+    codes.extend(
+        modifiers
+            .iter()
+            .filter(|(m, _)| cell.modifier.contains(*m))
+            .map(|(_, code)| code.to_string()),
+    );
+
+    format!("\x1b[{}m", codes.join(";"))
+}
+
+pub fn buffer_to_ansi(buffer: &Buffer) -> String {
+    rows(buffer)
+        .iter()
+        .map(|row| {
+            let mut line = String::new();
+            let mut last_style = None;
+            for cell in row {
+                let style = (cell.fg, cell.bg, cell.modifier);
+                if last_style != Some(style) {
+                    line.push_str(&sgr(cell));
+                    last_style = Some(style);
+                }
+                line.push_str(&cell.symbol);
+            }
+            line.push_str("\x1b[0m");
+            line
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+//Returns the path of the plain text file, the ANSI version is written next to it
+pub fn save_snapshot(buffer: &Buffer) -> io::Result<PathBuf> {
+    let dir = dirs::download_dir().unwrap_or_else(|| PathBuf::from("."));
+    let name = format!("tpedia_snapshot_{}", Local::now().format("%Y%m%d_%H%M%S"));
+
+    let text_path = dir.join(format!("{}.txt", name));
+    fs::write(&text_path, buffer_to_text(buffer))?;
+    fs::write(dir.join(format!("{}.ansi.txt", name)), buffer_to_ansi(buffer))?;
+
+    Ok(text_path)
+}
@@ -0,0 +1,87 @@
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+
+//One lowercase word per line, the most common words first
+const DICTIONARY: &[u8] = include_bytes!("words.txt");
+
+//Shorter queries and words are mostly abbreviations and are never checked
+const MIN_LENGTH: usize = 3;
+
+//Split once, on the first spell check
+static WORDS: Lazy<Vec<&'static str>> = Lazy::new(|| std::str::from_utf8(DICTIONARY).unwrap_or_default().lines().collect());
+static KNOWN: Lazy<HashSet<&'static str>> = Lazy::new(|| WORDS.iter().copied().collect());
+
+//Levenshtein distance that also counts swapped neighbours as a single edit
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let mut rows = vec![vec![0; b.len() + 1]; a.len() + 1];
+
+    for (i, row) in rows.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in rows[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let cost = if a[i - 1] == b[j - 1] { 0 } else { 1 };
+            let mut distance = (rows[i - 1][j] + 1).min(rows[i][j - 1] + 1).min(rows[i - 1][j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                distance = distance.min(rows[i - 2][j - 2] + 1);
+            }
+            rows[i][j] = distance;
+        }
+    }
+
+    rows[a.len()][b.len()]
+}
+
+//Closest dictionary word, ties go to the more common word
+fn closest_word(word: &str, dictionary: &[&str]) -> Option<String> {
+    let max_distance = if word.chars().count() <= 4 { 1 } else { 2 };
+    let mut best: Option<(&str, usize)> = None;
+
+    for candidate in dictionary {
+        if candidate.len().abs_diff(word.len()) > max_distance {
+            continue;
+        }
+
+        let distance = edit_distance(word, candidate);
+        if distance <= max_distance && best.is_none_or(|(_, d)| distance < d) {
+            best = Some((candidate, distance));
+        }
+    }
+
+    best.map(|(w, _)| w.to_string())
+}
+
+//Returns a corrected query if none of its words are in the dictionary,
+//words that can't be corrected are kept as typed
+pub fn suggest_correction(query: &str) -> Option<String> {
+    if query.trim().chars().count() < MIN_LENGTH {
+        return None;
+    }
+
+    let words: Vec<String> = query.split_whitespace().map(|w| w.to_lowercase()).collect();
+
+    //Numbers, names with punctuation and abbreviations are treated as known
+    let is_known = |w: &String| {
+        w.chars().count() < MIN_LENGTH || !w.chars().all(|c| c.is_ascii_alphabetic()) || KNOWN.contains(w.as_str())
+    };
+    if words.iter().any(is_known) {
+        return None;
+    }
+
+    let corrected: Vec<String> = words
+        .iter()
+        .map(|w| closest_word(w, &WORDS).unwrap_or_else(|| w.clone()))
+        .collect();
+
+    if corrected == words {
+        None
+    } else {
+        Some(corrected.join(" "))
+    }
+}
@@ -0,0 +1,33 @@
+use crate::Error;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs;
+use std::path::PathBuf;
+
+//Every persisted list is a JSON file in ~/.local/share/tpedia
+pub fn data_path(file_name: &str) -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join("tpedia").join(file_name))
+}
+
+pub fn load_json<T: DeserializeOwned + Default>(file_name: &str) -> Result<T, Error> {
+    let path = match data_path(file_name) {
+        Some(p) if p.exists() => p,
+        _ => return Ok(T::default()),
+    };
+
+    let content = fs::read_to_string(path)?;
+    let value: T = serde_json::from_str(&content)?;
+
+    Ok(value)
+}
+
+pub fn save_json<T: Serialize + ?Sized>(file_name: &str, value: &T) -> Result<(), Error> {
+    if let Some(path) = data_path(file_name) {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(path, serde_json::to_string_pretty(value)?)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use crate::infobox::decode_entities;
+use regex::Regex;
+
+//Articles with less text than this are not worth reading on their own
+const STUB_CHARS: usize = 200;
+
+//A page without an article of its own, shown as a notice of where to go instead
+#[derive(Debug, PartialEq)]
+pub enum Stub {
+    //Title of the article the page redirects to
+    Redirect(String),
+    //Titles of the articles the name may refer to
+    Disambiguation(Vec<String>),
+    Empty,
+}
+
+//Redirect pages render as a redirectMsg box with the target, disambiguation pages carry
+//the disambiguation message box. Anything else only counts as a stub if it is nearly empty
+pub fn detect_stub(html: &str, text: &str) -> Option<Stub> {
+    let redirect = Regex::new(r#"(?s)class="redirectText".*?title="([^"]+)""#).unwrap();
+    if let Some(captures) = redirect.captures(html) {
+        return Some(Stub::Redirect(decode_entities(&captures[1])));
+    }
+
+    let disambiguation = Regex::new(r#"\b(disambigbox|dmbox-disambig)\b"#).unwrap();
+    if disambiguation.is_match(html) {
+        //The options are list entries starting with their link, "See also" entries included
+        let option = Regex::new(r#"<li>[^<]*<a href="/wiki/[^"]*" title="([^"]+)""#).unwrap();
+        let mut options: Vec<String> = Vec::new();
+        for captures in option.captures_iter(html) {
+            let title = decode_entities(&captures[1]);
+            if !options.contains(&title) {
+                options.push(title);
+            }
+        }
+        return Some(Stub::Disambiguation(options));
+    }
+
+    if text.trim().chars().count() < STUB_CHARS {
+        return Some(Stub::Empty);
+    }
+
+    None
+}
+
+//Shown in place of the article text
+pub fn notice(stub: &Stub, text: &str) -> String {
+    match stub {
+        Stub::Redirect(target) => format!("This page only redirects to \"{}\".\n\nPress L to open it.", target),
+        Stub::Disambiguation(options) if options.is_empty() => {
+            format!("This is a disambiguation page, it lists articles with similar names.\n\n{}", text.trim())
+        }
+        Stub::Disambiguation(options) => format!(
+            "This is a disambiguation page, it lists articles with similar names. Press L to pick one:\n\n{}",
+            options.iter().map(|o| format!("  * {}", o)).collect::<Vec<String>>().join("\n")
+        ),
+        Stub::Empty if text.trim().is_empty() => String::from("This article has no text yet."),
+        Stub::Empty => format!("This article is only a stub, it has hardly any text yet.\n\n{}", text.trim()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::wikimedia_types::HtmlPageResult;
+
+    #[test]
+    fn redirect_only_parse_response_names_the_target() {
+        let response = r#"{"parse": {"title": "UK", "pageid": 31717, "text": "<div class=\"mw-content-ltr mw-parser-output\" lang=\"en\" dir=\"ltr\"><div class=\"redirectMsg\"><p>Redirect to:</p><ul class=\"redirectText\"><li><a href=\"/wiki/United_Kingdom\" title=\"United Kingdom\">United Kingdom</a></li></ul></div></div>"}}"#;
+        let page: HtmlPageResult = serde_json::from_str(response).unwrap();
+
+        let stub = detect_stub(&page.parse.text, "Redirect to:\n\n * United Kingdom");
+
+        assert_eq!(stub, Some(Stub::Redirect(String::from("United Kingdom"))));
+        assert!(notice(&stub.unwrap(), "").contains("\"United Kingdom\""));
+    }
+
+    #[test]
+    fn disambiguation_pages_list_their_options() {
+        let html = concat!(
+            r#"<p><b>Mercury</b> may refer to:</p><ul><li><a href="/wiki/Mercury_(planet)" title="Mercury (planet)">Mercury (planet)</a></li>"#,
+            r#"<li><a href="/wiki/Mercury_(element)" title="Mercury (element)">Mercury (element)</a>, a chemical element</li></ul>"#,
+            r#"<table id="disambigbox" class="metadata plainlinks dmbox dmbox-disambig"></table>"#,
+        );
+
+        assert_eq!(
+            detect_stub(html, &"Mercury may refer to".repeat(20)),
+            Some(Stub::Disambiguation(vec![String::from("Mercury (planet)"), String::from("Mercury (element)")]))
+        );
+        assert_eq!(detect_stub("<p>Long article</p>", &"text ".repeat(100)), None);
+    }
+}
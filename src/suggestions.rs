@@ -0,0 +1,24 @@
+pub struct Suggestion {
+    pub text: String,
+    //History entries come first and are marked in the dropdown
+    pub from_history: bool,
+}
+
+//Local history first, API suggestions that aren't already in it after that
+pub fn merge_suggestions(history: &[&str], api: &[String], history_count: usize, api_count: usize) -> Vec<Suggestion> {
+    let mut suggestions: Vec<Suggestion> = history
+        .iter()
+        .take(history_count)
+        .map(|h| Suggestion { text: h.to_string(), from_history: true })
+        .collect();
+
+    let api_suggestions: Vec<Suggestion> = api
+        .iter()
+        .filter(|a| !suggestions.iter().any(|s| s.text.to_lowercase() == a.to_lowercase()))
+        .take(api_count)
+        .map(|a| Suggestion { text: a.clone(), from_history: false })
+        .collect();
+    suggestions.extend(api_suggestions);
+
+    suggestions
+}
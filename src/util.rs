@@ -0,0 +1,381 @@
+use crate::analysis;
+use crate::config::Config;
+use crate::infobox;
+use once_cell::sync::Lazy;
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use regex::Regex;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+//Characters that would end or change the srsearch parameter
+const QUERY_ESCAPE: &AsciiSet = &CONTROLS.add(b'&').add(b'+').add(b'=').add(b'%').add(b'#');
+
+const ABBREVIATIONS: &[(&str, &str)] = &[
+    ("US", "United States"),
+    ("USA", "United States"),
+    ("UK", "United Kingdom"),
+    ("EU", "European Union"),
+    ("UN", "United Nations"),
+    ("USSR", "Soviet Union"),
+    ("NYC", "New York City"),
+    ("WWI", "World War I"),
+    ("WW1", "World War I"),
+    ("WWII", "World War II"),
+    ("WW2", "World War II"),
+];
+
+#[derive(Clone, Copy)]
+pub struct QueryPreprocessOpts {
+    pub expand_abbreviations: bool,
+    pub capitalize_words: bool,
+}
+
+impl From<&Config> for QueryPreprocessOpts {
+    fn from(config: &Config) -> Self {
+        QueryPreprocessOpts {
+            expand_abbreviations: config.expand_abbreviations,
+            capitalize_words: config.capitalize_query,
+        }
+    }
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+//Normalizes the query and escapes it for the srsearch parameter
+pub fn preprocess_query(raw: &str, opts: &QueryPreprocessOpts) -> String {
+    let mut words: Vec<String> = raw
+        .split_whitespace()
+        .map(|word| match ABBREVIATIONS.iter().find(|(short, _)| *short == word) {
+            Some((_, long)) if opts.expand_abbreviations => long.to_string(),
+            _ => word.to_string(),
+        })
+        .collect();
+
+    if opts.capitalize_words {
+        words = words.iter().map(|w| capitalize(w)).collect();
+    }
+
+    //A bare year finds the article about the year instead of everything mentioning it
+    if let [year] = words.as_slice() {
+        if year.len() == 4 && year.chars().all(|c| c.is_ascii_digit()) {
+            words.push(String::from("year"));
+        }
+    }
+
+    utf8_percent_encode(&words.join(" "), QUERY_ESCAPE).to_string()
+}
+
+//Pads the spaces between words so every line of a paragraph fills the width,
+//the last line of a paragraph, headings and list items stay left aligned
+pub fn justify_text(text: &str, width: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+
+    lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| {
+            let is_paragraph_end = lines.get(i + 1).is_none_or(|next| next.trim().is_empty());
+            let is_structural = line.starts_with('#') || line.trim_start().starts_with("* ");
+
+            if is_paragraph_end || is_structural {
+                line.to_string()
+            } else {
+                justify_line(line, width)
+            }
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+pub fn justify_line(line: &str, width: usize) -> String {
+    let words: Vec<&str> = line.split_whitespace().collect();
+    //Wide characters like CJK and emoji take two columns
+    let text_len: usize = words.iter().map(|w| w.width()).sum();
+
+    if words.len() < 2 || text_len + words.len() > width {
+        return line.to_string();
+    }
+
+    let gaps = words.len() - 1;
+    let spaces = width - text_len;
+
+    let mut justified = String::with_capacity(width);
+    for (i, word) in words.iter().enumerate() {
+        justified.push_str(word);
+        if i < gaps {
+            //The leftmost gaps get the remaining spaces
+            let gap = spaces / gaps + if i < spaces % gaps { 1 } else { 0 };
+            justified.push_str(&" ".repeat(gap));
+        }
+    }
+
+    justified
+}
+
+//Room for the pane borders and the margin html2text keeps free
+const TEXT_MARGIN: u16 = 10;
+
+//html2text misbehaves on tiny widths, articles are never converted narrower than this
+pub const MIN_WIDTH: u16 = 20;
+
+//Width the html is converted at, never below MIN_WIDTH even on the smallest terminals
+pub fn fetch_width(pane_width: u16) -> u16 {
+    pane_width.saturating_sub(TEXT_MARGIN).max(MIN_WIDTH)
+}
+
+//Width the article text is wrapped at, None if the pane is too narrow to read
+pub fn text_width(pane_width: u16, min_width: u16) -> Option<u16> {
+    let width = pane_width.saturating_sub(TEXT_MARGIN);
+
+    if width < min_width.max(1) {
+        None
+    } else {
+        Some(width)
+    }
+}
+
+//OSC 8 escape sequence, supporting terminals render the text as a clickable link
+pub fn osc8_link(url: &str, text: &str) -> String {
+    format!("\x1b]8;;{}\x1b\\{}\x1b]8;;\x1b\\", url, text)
+}
+
+//There is no reliable query for OSC 8 support, so known terminals are detected by their env vars
+pub fn terminal_supports_hyperlinks() -> bool {
+    let known_program = std::env::var("TERM_PROGRAM")
+        .map(|p| ["iTerm.app", "WezTerm", "vscode", "Hyper"].contains(&p.as_str()))
+        .unwrap_or(false);
+    let known_term = std::env::var("TERM")
+        .map(|t| t.contains("kitty") || t.contains("foot") || t.contains("alacritty"))
+        .unwrap_or(false);
+
+    known_program || known_term || std::env::var("VTE_VERSION").is_ok() || std::env::var("WT_SESSION").is_ok()
+}
+
+//"Rust → Ownership → " for the last depth articles of the trail, older ones are
+//dropped and replaced by "…" so the prefix stays within max_width characters
+pub fn breadcrumb_prefix(trail: &[String], depth: usize, max_width: usize) -> String {
+    let mut shown = &trail[trail.len().saturating_sub(depth)..];
+
+    loop {
+        let hidden = shown.len() < trail.len();
+        let mut prefix: String = shown.iter().map(|t| format!("{} → ", t)).collect();
+        if hidden {
+            prefix.insert_str(0, "… → ");
+        }
+
+        if prefix.chars().count() <= max_width || shown.is_empty() {
+            return if shown.is_empty() { String::new() } else { prefix };
+        }
+        shown = &shown[1..];
+    }
+}
+
+//Snapshot closest to the last edit, timestamp as returned by the search API
+pub fn wayback_url(article_url: &str, timestamp: &str) -> String {
+    match chrono::DateTime::parse_from_rfc3339(timestamp) {
+        Ok(edited) => format!("https://web.archive.org/web/{}/{}", edited.format("%Y%m%d%H%M%S"), article_url),
+        Err(_) => format!("https://web.archive.org/web/*/{}", article_url),
+    }
+}
+
+//New issue on GitHub with the environment already filled in
+pub fn bug_report_url() -> String {
+    let body = format!(
+        "**Describe the bug**\n\n\n**Environment**\n- Tpedia: {}\n- OS: {}\n- TERM: {}\n",
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::var("TERM").unwrap_or_else(|_| String::from("unknown")),
+    );
+    let body: String = url::form_urlencoded::byte_serialize(body.as_bytes()).collect();
+
+    format!("https://github.com/EnglederLucas/Tpedia/issues/new?body={}", body)
+}
+
+//Braille patterns need a UTF-8 locale, the first set of LC_ALL, LC_CTYPE and LANG decides
+pub fn terminal_supports_braille() -> bool {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .map(|locale| {
+            let locale = locale.to_lowercase();
+            locale.contains("utf-8") || locale.contains("utf8")
+        })
+        .unwrap_or(false)
+}
+
+//xterm window manipulation, asks the window manager to maximize or restore the window.
+//Terminals that don't support it ignore the sequence and only the layout changes
+pub fn window_state_sequence(maximize: bool) -> &'static str {
+    if maximize {
+        "\x1b[9;1t"
+    } else {
+        "\x1b[9;0t"
+    }
+}
+
+//Wikis written without spaces between words
+pub fn is_cjk_language(language: &str) -> bool {
+    ["ja", "zh", "zh-yue", "zh-classical", "wuu", "gan", "lzh"].contains(&language)
+}
+
+fn is_cjk_char(c: char) -> bool {
+    matches!(c as u32,
+        0x3000..=0x30FF     // punctuation, hiragana, katakana
+        | 0x3400..=0x4DBF   // CJK extension A
+        | 0x4E00..=0x9FFF   // CJK unified ideographs
+        | 0xF900..=0xFAFF   // compatibility ideographs
+        | 0xFF00..=0xFFEF)  // full width forms
+}
+
+//Re-wraps lines by display width, CJK characters can be broken anywhere
+//while runs of latin text are kept together as words
+pub fn wrap_cjk(text: &str, width: usize) -> String {
+    let width = width.max(2);
+    let mut wrapped: Vec<String> = Vec::new();
+
+    for line in text.lines() {
+        let mut tokens: Vec<String> = Vec::new();
+        for c in line.chars() {
+            let joins_word = !is_cjk_char(c) && !c.is_whitespace()
+                && tokens.last().and_then(|t| t.chars().last()).is_some_and(|last| !is_cjk_char(last) && !last.is_whitespace());
+
+            match tokens.last_mut() {
+                Some(token) if joins_word => token.push(c),
+                _ => tokens.push(c.to_string()),
+            }
+        }
+
+        let mut current = String::new();
+        let mut current_width = 0;
+        for token in tokens {
+            let token_width: usize = token.chars().map(|c| c.width().unwrap_or(0)).sum();
+
+            if current_width + token_width > width && current_width > 0 {
+                wrapped.push(current.trim_end().to_string());
+                current = String::new();
+                current_width = 0;
+                if token.trim().is_empty() {
+                    continue;
+                }
+            }
+
+            current.push_str(&token);
+            current_width += token_width;
+        }
+        wrapped.push(current);
+    }
+
+    wrapped.join("\n")
+}
+
+//Search snippets are cut out of the wikitext mid-sentence, so they come with highlight markup,
+//citation markers and half a bracket at either end
+//Compiled once, the result list cleans every snippet on every draw
+static CITATIONS: Lazy<Regex> = Lazy::new(|| Regex::new(r"\[[^\[\]]*\]").unwrap());
+//A bracket whose other half was cut off
+static FRAGMENTS: Lazy<Regex> = Lazy::new(|| Regex::new(r"^[^\[]*\]|\[[^\]]*$").unwrap());
+
+pub fn clean_snippet(snippet: &str) -> String {
+    let text = analysis::strip_tags(snippet, "");
+    let text = infobox::decode_entities(&text);
+    let text = CITATIONS.replace_all(&text, "");
+    let text = FRAGMENTS.replace_all(&text, "");
+
+    let text = text.split_whitespace().collect::<Vec<&str>>().join(" ").replace(" ,", ",");
+    text.trim_start_matches(|c: char| c.is_ascii_punctuation() || c.is_whitespace())
+        .trim_end_matches(|c: char| (c.is_ascii_punctuation() && !".!?)\"".contains(c)) || c.is_whitespace())
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snippets_lose_markup_and_cut_off_fragments() {
+        let snippet = r#"ote 3] and , the <span class="searchmatch">Rust</span> language[1] is &quot;fast&quot;   and safe;[citation needed] it was (designed by Graydon Hoare, [[Mozilla"#;
+
+        assert_eq!(clean_snippet(snippet), r#"and, the Rust language is "fast" and safe; it was (designed by Graydon Hoare"#);
+    }
+
+    #[test]
+    fn queries_expand_abbreviations_and_capitalize_when_enabled() {
+        let off = QueryPreprocessOpts { expand_abbreviations: false, capitalize_words: false };
+        let expand = QueryPreprocessOpts { expand_abbreviations: true, capitalize_words: false };
+        let capitalize = QueryPreprocessOpts { expand_abbreviations: false, capitalize_words: true };
+
+        assert_eq!(preprocess_query("  US   history ", &off), "US history");
+        assert_eq!(preprocess_query("US history", &expand), "United States history");
+        assert_eq!(preprocess_query("us WWII", &expand), "us World War II");
+        assert_eq!(preprocess_query("new york CITY", &capitalize), "New York CITY");
+        //Non-ASCII characters are percent-encoded as UTF-8
+        assert_eq!(preprocess_query("élan vital", &capitalize), "%C3%89lan Vital");
+        assert_eq!(preprocess_query("1989", &off), "1989 year");
+        assert_eq!(preprocess_query("AT&T 50% a+b=c", &off), "AT%26T 50%25 a%2Bb%3Dc");
+    }
+
+    #[test]
+    fn justified_lines_fill_the_width_in_columns() {
+        let text = "Rust is a fast language\nand safe\n\n日本 東京 です\n終わり";
+        let justified = justify_text(text, 28);
+        let lines: Vec<&str> = justified.lines().collect();
+
+        assert_eq!(lines[0], "Rust   is  a  fast  language");
+        assert_eq!(lines[1], "and safe");
+        assert_eq!(lines[3], "日本        東京        です");
+        assert_eq!(lines[3].width(), 28);
+        assert_eq!(justify_line("🦀 crab", 10), "🦀    crab");
+    }
+
+    #[test]
+    fn japanese_text_wraps_between_characters() {
+        let wrapped = wrap_cjk("東京は日本の首都です。人口はTokyo Metropolisで約1400万人。", 10);
+
+        assert!(wrapped.lines().all(|line| line.width() <= 10));
+        assert_eq!(wrapped.lines().next(), Some("東京は日本"));
+        assert!(wrapped.lines().any(|line| line.contains("Metropolis")));
+        assert_eq!(wrapped.replace(['\n', ' '], ""), "東京は日本の首都です。人口はTokyoMetropolisで約1400万人。");
+    }
+
+    #[test]
+    fn narrow_pane_has_no_text_width() {
+        assert_eq!(text_width(5, 20), None);
+        assert_eq!(text_width(9, 0), None);
+        assert_eq!(text_width(25, 20), None);
+    }
+
+    #[test]
+    fn fetch_width_never_underflows() {
+        assert_eq!(fetch_width(0), MIN_WIDTH);
+        assert_eq!(fetch_width(12), MIN_WIDTH);
+        assert_eq!(fetch_width(100), 90);
+    }
+
+    #[test]
+    fn text_width_leaves_a_margin() {
+        assert_eq!(text_width(100, 20), Some(90));
+        assert_eq!(text_width(30, 20), Some(20));
+    }
+
+    #[test]
+    fn wayback_url_uses_the_edit_time() {
+        let url = "https://en.wikipedia.org/wiki/Rust";
+        assert_eq!(wayback_url(url, "2021-03-04T12:30:05Z"), "https://web.archive.org/web/20210304123005/https://en.wikipedia.org/wiki/Rust");
+        assert_eq!(wayback_url(url, ""), "https://web.archive.org/web/*/https://en.wikipedia.org/wiki/Rust");
+    }
+
+    #[test]
+    fn breadcrumb_keeps_the_newest_articles() {
+        let trail: Vec<String> = ["Rust", "Ownership", "Borrow checker"].iter().map(|t| t.to_string()).collect();
+        assert_eq!(breadcrumb_prefix(&trail, 5, 80), "Rust → Ownership → Borrow checker → ");
+        assert_eq!(breadcrumb_prefix(&trail, 2, 80), "… → Ownership → Borrow checker → ");
+        assert_eq!(breadcrumb_prefix(&trail, 5, 25), "… → Borrow checker → ");
+        assert_eq!(breadcrumb_prefix(&trail, 0, 80), "");
+    }
+}
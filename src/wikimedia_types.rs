@@ -1,10 +1,11 @@
+use crate::serde_compat;
 use serde::{Deserialize, Serialize};
 
 
 #[derive(Serialize, Deserialize, std::fmt::Debug)]
 pub struct SearchResponse {
-    #[serde(rename = "batchcomplete")]
-    pub batchcomplete: String,
+    #[serde(rename = "batchcomplete", default, deserialize_with = "serde_compat::flag")]
+    pub batchcomplete: Option<bool>,
 
     #[serde(rename = "continue")]
     pub search_response_continue: Option<Continue>,
@@ -13,6 +14,13 @@ pub struct SearchResponse {
     pub query: Query,
 }
 
+impl SearchResponse {
+    //The API omits the continue field once the last results were returned
+    pub fn next_offset(&self) -> Option<i64> {
+        self.search_response_continue.as_ref().map(|c| c.sroffset)
+    }
+}
+
 #[derive(Serialize, Deserialize, std::fmt::Debug)]
 pub struct Query {
     #[serde(rename = "searchinfo")]
@@ -46,6 +54,66 @@ pub struct Search {
     pub timestamp: String,
 }
 
+//Canonical names of the namespaces every wiki has, articles (ns 0) have none
+pub fn namespace_name(ns: i64) -> &'static str {
+    match ns {
+        0 => "",
+        1 => "Talk",
+        2 => "User",
+        3 => "User talk",
+        4 => "Project",
+        5 => "Project talk",
+        6 => "File",
+        7 => "File talk",
+        8 => "MediaWiki",
+        9 => "MediaWiki talk",
+        10 => "Template",
+        11 => "Template talk",
+        12 => "Help",
+        13 => "Help talk",
+        14 => "Category",
+        15 => "Category talk",
+        100 => "Portal",
+        101 => "Portal talk",
+        118 => "Draft",
+        119 => "Draft talk",
+        828 => "Module",
+        829 => "Module talk",
+        _ => "Other",
+    }
+}
+
+//Title of the page's talk page, None for talk pages themselves and unknown namespaces
+pub fn talk_page_title(ns: i64, title: &str) -> Option<String> {
+    match ns {
+        0 => Some(format!("Talk:{}", title)),
+        ns if ns % 2 == 1 || namespace_name(ns + 1) == "Other" => None,
+        ns => {
+            let name = title.split_once(':').map(|(_, name)| name).unwrap_or(title);
+            Some(format!("{}:{}", namespace_name(ns + 1), name))
+        }
+    }
+}
+
+//action=opensearch answers with an array instead of an object: [query, [titles], [descriptions], [urls]]
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct OpenSearchResponse(pub String, pub Vec<String>, pub Vec<String>, pub Vec<String>);
+
+impl OpenSearchResponse {
+    //(title, description, url) of every suggestion, Wikipedia leaves the descriptions empty
+    pub fn results(&self) -> Vec<(String, String, String)> {
+        self.1
+            .iter()
+            .enumerate()
+            .map(|(i, title)| {
+                let description = self.2.get(i).cloned().unwrap_or_default();
+                let url = self.3.get(i).cloned().unwrap_or_default();
+                (title.clone(), description, url)
+            })
+            .collect()
+    }
+}
+
 #[derive(Serialize, Deserialize, std::fmt::Debug)]
 pub struct Searchinfo {
     #[serde(rename = "totalhits")]
@@ -78,3 +146,274 @@ pub struct Parse {
     pub pageid: i64,
     pub text: String,
 }
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct LinksPageResult {
+    pub parse: LinksParse,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct LinksParse {
+    pub title: String,
+    pub links: Vec<Link>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct Link {
+    pub ns: i64,
+    pub title: String,
+
+    //Missing for red links
+    #[serde(default)]
+    pub exists: bool,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct CoordinatesResult {
+    pub query: CoordinatesQuery,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct CoordinatesQuery {
+    pub pages: Vec<CoordinatesPage>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct CoordinatesPage {
+    pub pageid: i64,
+
+    //Missing for articles without a location
+    #[serde(default)]
+    pub coordinates: Vec<Coordinate>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone, Copy)]
+pub struct Coordinate {
+    pub lat: f64,
+    pub lon: f64,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct ImagesResponse {
+    pub query: ImagesQuery,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct ImagesQuery {
+    pub pages: Vec<ImagesPage>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct ImagesPage {
+    pub pageid: i64,
+
+    //Missing for articles without images
+    #[serde(default)]
+    pub images: Vec<Image>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct Image {
+    pub ns: i64,
+    pub title: String,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct InfoResult {
+    pub query: InfoQuery,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct InfoQuery {
+    pub pages: Vec<InfoPage>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug, Clone)]
+pub struct InfoPage {
+    pub ns: i64,
+    pub title: String,
+
+    //Only pageid, touched and length are missing for pages that do not exist
+    #[serde(default)]
+    pub missing: bool,
+    pub pageid: Option<i64>,
+    pub touched: Option<String>,
+    pub length: Option<i64>,
+}
+
+//list=backlinks, continuation.blcontinue asks for the next batch
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct BacklinksResponse {
+    #[serde(rename = "continue")]
+    pub continuation: Option<BacklinksContinue>,
+    pub query: BacklinksQuery,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct BacklinksContinue {
+    pub blcontinue: String,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct BacklinksQuery {
+    pub backlinks: Vec<Backlink>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct Backlink {
+    pub pageid: i64,
+    pub ns: i64,
+    pub title: String,
+}
+
+//prop=categories, pages without categories have no categories field
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoriesResponse {
+    pub query: CategoriesQuery,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoriesQuery {
+    pub pages: Vec<CategoriesPage>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoriesPage {
+    #[serde(default)]
+    pub categories: Vec<CategoryLink>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoryLink {
+    pub ns: i64,
+    pub title: String,
+}
+
+//list=categorymembers
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoryMembersResponse {
+    pub query: CategoryMembersQuery,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoryMembersQuery {
+    pub categorymembers: Vec<CategoryMember>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct CategoryMember {
+    pub pageid: i64,
+    pub ns: i64,
+    pub title: String,
+}
+
+//pageviews REST API, one item for the requested day
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct TopViewedResponse {
+    pub items: Vec<TopViewedDay>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct TopViewedDay {
+    pub articles: Vec<TopViewedArticle>,
+}
+
+#[derive(Serialize, Deserialize, std::fmt::Debug)]
+pub struct TopViewedArticle {
+    //Title with underscores, e.g. "Main_Page"
+    pub article: String,
+    pub views: u64,
+    pub rank: u32,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LAST_PAGE: &str = r#"{
+        "batchcomplete": "",
+        "query": {
+            "searchinfo": { "totalhits": 1 },
+            "search": [{ "ns": 0, "title": "Rust", "pageid": 1, "size": 10, "wordcount": 2, "snippet": "", "timestamp": "2021-01-01T00:00:00Z" }]
+        }
+    }"#;
+
+    #[test]
+    fn missing_continue_means_exhausted() {
+        let res: SearchResponse = serde_json::from_str(LAST_PAGE).unwrap();
+        assert_eq!(res.next_offset(), None);
+    }
+
+    #[test]
+    fn batchcomplete_accepts_both_formatversions() {
+        let v1: SearchResponse = serde_json::from_str(LAST_PAGE).unwrap();
+        let v2: SearchResponse = serde_json::from_str(&LAST_PAGE.replacen("\"batchcomplete\": \"\"", "\"batchcomplete\": true", 1)).unwrap();
+        let missing: SearchResponse = serde_json::from_str(&LAST_PAGE.replacen("\"batchcomplete\": \"\",", "", 1)).unwrap();
+
+        assert_eq!(v1.batchcomplete, Some(true));
+        assert_eq!(v2.batchcomplete, Some(true));
+        assert_eq!(missing.batchcomplete, None);
+    }
+
+    #[test]
+    fn continue_gives_next_offset() {
+        let json = LAST_PAGE.replacen("\"batchcomplete\": \"\",", "\"batchcomplete\": \"\", \"continue\": { \"sroffset\": 10, \"continue\": \"-||\" },", 1);
+        let res: SearchResponse = serde_json::from_str(&json).unwrap();
+        assert_eq!(res.next_offset(), Some(10));
+    }
+
+    #[test]
+    fn opensearch_array_is_split_into_results() {
+        let json = r#"["Vienn",["Vienna","Vienna Philharmonic"],["",""],["https://en.wikipedia.org/wiki/Vienna","https://en.wikipedia.org/wiki/Vienna_Philharmonic"]]"#;
+        let res: OpenSearchResponse = serde_json::from_str(json).unwrap();
+
+        assert_eq!(res.0, "Vienn");
+        assert_eq!(res.results()[1], (
+            String::from("Vienna Philharmonic"),
+            String::new(),
+            String::from("https://en.wikipedia.org/wiki/Vienna_Philharmonic"),
+        ));
+    }
+
+    #[test]
+    fn common_namespaces_have_names() {
+        assert_eq!(namespace_name(0), "");
+        assert_eq!(namespace_name(1), "Talk");
+        assert_eq!(namespace_name(10), "Template");
+        assert_eq!(namespace_name(12), "Help");
+        assert_eq!(namespace_name(14), "Category");
+        assert_eq!(namespace_name(100), "Portal");
+        assert_eq!(namespace_name(4242), "Other");
+
+        assert_eq!(talk_page_title(0, "Vienna").as_deref(), Some("Talk:Vienna"));
+        assert_eq!(talk_page_title(14, "Category:Cities").as_deref(), Some("Category talk:Cities"));
+        assert_eq!(talk_page_title(1, "Talk:Vienna"), None);
+    }
+
+    #[test]
+    fn backlinks_carry_the_continue_token() {
+        let json = r#"{"batchcomplete": false, "continue": {"blcontinue": "0|48291", "continue": "-||"},
+            "query": {"backlinks": [{"pageid": 1207, "ns": 0, "title": "Analytical Engine"}]}}"#;
+        let res: BacklinksResponse = serde_json::from_str(json).unwrap();
+        assert_eq!(res.query.backlinks[0].title, "Analytical Engine");
+        assert_eq!(res.continuation.map(|c| c.blcontinue), Some(String::from("0|48291")));
+
+        let last: BacklinksResponse = serde_json::from_str(r#"{"batchcomplete": true, "query": {"backlinks": []}}"#).unwrap();
+        assert!(last.continuation.is_none() && last.query.backlinks.is_empty());
+    }
+
+    #[test]
+    fn top_viewed_articles_keep_their_rank() {
+        let json = r#"{"items": [{"project": "en.wikipedia", "access": "all-access", "year": "2026", "month": "10", "day": "15", "articles": [
+            {"article": "Main_Page", "views": 5312345, "rank": 1},
+            {"article": "Special:Search", "views": 1203456, "rank": 2},
+            {"article": "Ada_Lovelace", "views": 98765, "rank": 3}
+        ]}]}"#;
+        let res: TopViewedResponse = serde_json::from_str(json).unwrap();
+
+        let articles = &res.items[0].articles;
+        assert_eq!(articles.len(), 3);
+        assert_eq!(articles[2].article, "Ada_Lovelace");
+        assert_eq!(articles[2].views, 98765);
+    }
+}